@@ -0,0 +1,81 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use nes_core::{cpu::{Cpu, RESET_VECTOR}, memory::Memory};
+
+/// Flat 64 KB address space without any mirroring or side effects
+struct BenchMemory {
+    data: Box<[u8; 0x10000]>,
+}
+
+impl Memory for BenchMemory {
+    fn cpu_load8(&mut self, addr: u16) -> u8 {
+        self.data[addr as usize]
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        self.data[addr as usize] = val;
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> u8 {
+        self.data[addr as usize]
+    }
+}
+
+/// A mix of loads, stores, arithmetic, branches and subroutine calls, similar to what nestest exercises
+const PROGRAM: &[u8] = &[
+    0xA2, 0x00,       // 8000: LDX #$00
+    0xA0, 0x00,       // 8002: LDY #$00
+    0xBD, 0x00, 0x02, // 8004: LDA $0200,X
+    0x69, 0x01,       // 8007: ADC #$01
+    0x9D, 0x00, 0x02, // 8009: STA $0200,X
+    0xB1, 0x10,       // 800C: LDA ($10),Y
+    0x45, 0x20,       // 800E: EOR $20
+    0x85, 0x20,       // 8010: STA $20
+    0x20, 0x20, 0x80, // 8012: JSR $8020
+    0xE8,             // 8015: INX
+    0xC8,             // 8016: INY
+    0xD0, 0xEB,       // 8017: BNE $8004
+    0x4C, 0x00, 0x80, // 8019: JMP $8000
+    0xEA, 0xEA, 0xEA, // 801C: padding
+    0xEA,
+    0x48,             // 8020: PHA
+    0x26, 0x21,       // 8021: ROL $21
+    0x68,             // 8023: PLA
+    0x60,             // 8024: RTS
+];
+
+fn setup() -> (Cpu, BenchMemory) {
+    let mut memory = BenchMemory { data: Box::new([0; 0x10000]) };
+    memory.data[0x8000..0x8000 + PROGRAM.len()].copy_from_slice(PROGRAM);
+    memory.data[0x10] = 0x00;
+    memory.data[0x11] = 0x03;
+    memory.data[RESET_VECTOR as usize] = 0x00;
+    memory.data[RESET_VECTOR as usize + 1] = 0x80;
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut memory);
+    (cpu, memory)
+}
+
+fn bench_execute(c: &mut Criterion) {
+    let (mut cpu, mut memory) = setup();
+
+    c.bench_function("execute 10000 instructions", |b| b.iter(|| {
+        for _ in 0..10000 {
+            cpu.execute_single_instruction(&mut memory);
+        }
+    }));
+}
+
+fn bench_execute_dyn(c: &mut Criterion) {
+    let (mut cpu, mut memory) = setup();
+    let memory: &mut dyn Memory = &mut memory;
+
+    c.bench_function("execute 10000 instructions (dyn Memory)", |b| b.iter(|| {
+        for _ in 0..10000 {
+            cpu.execute_single_instruction(memory);
+        }
+    }));
+}
+
+criterion_group!(benches, bench_execute, bench_execute_dyn);
+criterion_main!(benches);
@@ -0,0 +1,429 @@
+use alloc::vec::Vec;
+
+use crate::state::{StateError, StateReader, StateWriter};
+
+/// CPU clock rate in Hz (NTSC), the rate at which the APU produces its raw output
+pub const CPU_FREQUENCY: u32 = 1_789_773;
+
+/// Audio Processing Unit (http://wiki.nesdev.com/w/index.php/APU)
+/// 
+/// Registers (written by the CPU through the [`Bus`](crate::bus::Bus)):
+/// - 0x4000-0x4003: pulse channel 1
+/// - 0x4004-0x4007: pulse channel 2
+/// - 0x4008-0x400B: triangle channel
+/// - 0x400C-0x400F: noise channel
+/// - 0x4015: channel enable (write), length counter and IRQ status (read)
+/// - 0x4017: frame counter
+pub struct Apu {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+
+    /// Number of CPU cycles executed, the channel timers are clocked every other CPU cycle
+    cycle: u64,
+
+    // frame counter (http://wiki.nesdev.com/w/index.php/APU_Frame_Counter)
+    /// CPU cycles since the frame sequence started
+    frame_cycle: u16,
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    frame_irq: bool,
+
+    // resampling to the host sample rate
+    sample_rate: u32,
+    /// Accumulates `sample_rate` per CPU cycle, a sample is produced whenever it exceeds [`CPU_FREQUENCY`]
+    sample_phase: u32,
+    sample_sum: f32,
+    sample_count: u32,
+    samples: Vec<f32>,
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::new(),
+            noise: Noise::new(),
+
+            cycle: 0,
+
+            frame_cycle: 0,
+            five_step_mode: false,
+            irq_inhibit: false,
+            frame_irq: false,
+
+            sample_rate: 44100,
+            sample_phase: 0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Writes the channel and frame counter state into a save state
+    ///
+    /// The resampler is not part of the save state
+    pub fn save_state(&self, w: &mut StateWriter) {
+        self.pulse1.save_state(w);
+        self.pulse2.save_state(w);
+        self.triangle.save_state(w);
+        self.noise.save_state(w);
+        w.write_u64(self.cycle);
+        w.write_u16(self.frame_cycle);
+        w.write_bool(self.five_step_mode);
+        w.write_bool(self.irq_inhibit);
+        w.write_bool(self.frame_irq);
+    }
+
+    /// Restores the state written by [`Apu::save_state`]
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.pulse1.load_state(r)?;
+        self.pulse2.load_state(r)?;
+        self.triangle.load_state(r)?;
+        self.noise.load_state(r)?;
+        self.cycle = r.read_u64()?;
+        self.frame_cycle = r.read_u16()?;
+        self.five_step_mode = r.read_bool()?;
+        self.irq_inhibit = r.read_bool()?;
+        self.frame_irq = r.read_bool()?;
+        Ok(())
+    }
+
+    /// Handles a CPU write to one of the APU registers in 0x4000-0x4017
+    pub fn write_register(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x4000..=0x4003 => self.pulse1.write_register(addr & 0x03, val),
+            0x4004..=0x4007 => self.pulse2.write_register(addr & 0x03, val),
+            0x4008..=0x400B => self.triangle.write_register(addr & 0x03, val),
+            0x400C..=0x400F => self.noise.write_register(addr & 0x03, val),
+            0x4015 => {
+                self.pulse1.length.set_enabled((val & 0x01) != 0);
+                self.pulse2.length.set_enabled((val & 0x02) != 0);
+                self.triangle.length.set_enabled((val & 0x04) != 0);
+                self.noise.length.set_enabled((val & 0x08) != 0);
+            }
+            0x4017 => {
+                self.five_step_mode = (val & 0x80) != 0;
+                self.irq_inhibit = (val & 0x40) != 0;
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                self.frame_cycle = 0;
+                // the 5-step mode clocks all units immediately
+                if self.five_step_mode {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a CPU read of the status register (0x4015), clears the frame IRQ flag
+    /// - bit 0: pulse 1 length counter > 0
+    /// - bit 1: pulse 2 length counter > 0
+    /// - bit 2: triangle length counter > 0
+    /// - bit 3: noise length counter > 0
+    /// - bit 6: frame IRQ
+    pub fn read_status(&mut self) -> u8 {
+        let res = self.peek_status();
+        self.frame_irq = false;
+        res
+    }
+
+    /// Returns the value a read of the status register would return, without any side effects
+    pub fn peek_status(&self) -> u8 {
+        let mut res = 0;
+        if self.pulse1.length.is_active() {
+            res |= 0x01;
+        }
+        if self.pulse2.length.is_active() {
+            res |= 0x02;
+        }
+        if self.triangle.length.is_active() {
+            res |= 0x04;
+        }
+        if self.noise.length.is_active() {
+            res |= 0x08;
+        }
+        if self.frame_irq {
+            res |= 0x40;
+        }
+        res
+    }
+
+    /// Advances the APU by a single CPU cycle
+    pub fn step(&mut self) {
+        self.triangle.clock_timer();
+        if (self.cycle & 1) == 1 {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+            self.noise.clock_timer();
+        }
+        self.cycle += 1;
+
+        self.clock_frame_counter();
+        self.clock_resampler();
+    }
+
+    /// Sets the rate (in Hz) of the samples returned by [`Apu::drain_samples`], defaults to 44100
+    pub fn set_sample_rate(&mut self, hz: u32) {
+        self.sample_rate = hz.min(CPU_FREQUENCY);
+    }
+
+    /// Moves all samples produced since the last call to the end of `out`
+    pub fn drain_samples(&mut self, out: &mut Vec<f32>) {
+        out.append(&mut self.samples);
+    }
+
+    /// Averages the raw output of all CPU cycles that fall into one host sample
+    fn clock_resampler(&mut self) {
+        self.sample_sum += self.output();
+        self.sample_count += 1;
+
+        self.sample_phase += self.sample_rate;
+        if self.sample_phase >= CPU_FREQUENCY {
+            self.sample_phase -= CPU_FREQUENCY;
+            // nobody is draining the buffer if it holds more than a second of audio
+            if self.samples.len() < self.sample_rate as usize {
+                self.samples.push(self.sample_sum / self.sample_count as f32);
+            }
+            self.sample_sum = 0.0;
+            self.sample_count = 0;
+        }
+    }
+
+    /// Returns whether the frame counter is asserting the CPU IRQ line
+    /// 
+    /// The line stays asserted until the status register is read or IRQs are inhibited through 0x4017
+    pub fn irq_pending(&self) -> bool {
+        self.frame_irq
+    }
+
+    /// Advances the frame sequence by one CPU cycle
+    /// 
+    /// | cycle | 4-step mode        | 5-step mode        |
+    /// |-------|--------------------|--------------------|
+    /// | 7457  | quarter            | quarter            |
+    /// | 14913 | quarter, half      | quarter, half      |
+    /// | 22371 | quarter            | quarter            |
+    /// | 29829 | quarter, half, IRQ | -                  |
+    /// | 37281 | -                  | quarter, half      |
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+
+        match (self.frame_cycle, self.five_step_mode) {
+            (7457, _) | (22371, _) => self.clock_quarter_frame(),
+            (14913, _) | (29829, false) | (37281, true) => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            _ => {}
+        }
+
+        if !self.five_step_mode && self.frame_cycle == 29829 && !self.irq_inhibit {
+            self.frame_irq = true;
+        }
+
+        let sequence_length = if self.five_step_mode { 37282 } else { 29830 };
+        if self.frame_cycle >= sequence_length {
+            self.frame_cycle = 0;
+        }
+    }
+
+    /// Clocks the envelopes and the triangle's linear counter (~240 Hz)
+    fn clock_quarter_frame(&mut self) {
+        self.pulse1.envelope.clock();
+        self.pulse2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_quarter_frame();
+    }
+
+    /// Clocks the length counters and sweep units (~120 Hz)
+    fn clock_half_frame(&mut self) {
+        self.pulse1.clock_half_frame();
+        self.pulse2.clock_half_frame();
+        self.triangle.length.clock();
+        self.noise.length.clock();
+    }
+
+    /// Current output of the mixer in the range 0.0-1.0
+    /// 
+    /// Uses the nonlinear mixing formula from http://wiki.nesdev.com/w/index.php/APU_Mixer
+    pub fn output(&self) -> f32 {
+        let pulse = (self.pulse1.output() + self.pulse2.output()) as f32;
+        let triangle = self.triangle.output() as f32;
+        let noise = self.noise.output() as f32;
+
+        let pulse_out = if pulse == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulse + 100.0)
+        };
+
+        let tnd = triangle / 8227.0 + noise / 12241.0;
+        let tnd_out = if tnd == 0.0 {
+            0.0
+        } else {
+            159.79 / (1.0 / tnd + 100.0)
+        };
+
+        pulse_out + tnd_out
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Length counter values selected by the upper 5 bits of the length counter load registers
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Silences a channel after a programmable number of half frames
+/// (http://wiki.nesdev.com/w/index.php/APU_Length_Counter)
+struct LengthCounter {
+    enabled: bool,
+    halt: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            halt: false,
+            counter: 0,
+        }
+    }
+
+    /// Enables or disables the channel through 0x4015, disabling clears the counter
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    /// Loads the counter from [`LENGTH_TABLE`], ignored while the channel is disabled
+    fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.enabled);
+        w.write_bool(self.halt);
+        w.write_u8(self.counter);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.enabled = r.read_bool()?;
+        self.halt = r.read_bool()?;
+        self.counter = r.read_u8()?;
+        Ok(())
+    }
+}
+
+/// Generates a decaying volume or a constant volume
+/// (http://wiki.nesdev.com/w/index.php/APU_Envelope)
+struct Envelope {
+    start: bool,
+    looping: bool,
+    constant_volume: bool,
+    /// Constant volume or the reload value of the divider
+    volume: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Self {
+            start: false,
+            looping: false,
+            constant_volume: false,
+            volume: 0,
+            divider: 0,
+            decay: 0,
+        }
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.start);
+        w.write_bool(self.looping);
+        w.write_bool(self.constant_volume);
+        w.write_u8(self.volume);
+        w.write_u8(self.divider);
+        w.write_u8(self.decay);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.start = r.read_bool()?;
+        self.looping = r.read_bool()?;
+        self.constant_volume = r.read_bool()?;
+        self.volume = r.read_u8()?;
+        self.divider = r.read_u8()?;
+        self.decay = r.read_u8()?;
+        Ok(())
+    }
+
+    /// Handles a write to the channel's control register (--LC VVVV)
+    fn write_control(&mut self, val: u8) {
+        self.looping = (val & 0x20) != 0;
+        self.constant_volume = (val & 0x10) != 0;
+        self.volume = val & 0x0F;
+    }
+
+    fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+mod pulse;
+use pulse::Pulse;
+
+mod triangle;
+use triangle::Triangle;
+
+mod noise;
+use noise::Noise;
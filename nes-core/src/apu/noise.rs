@@ -0,0 +1,101 @@
+use crate::state::{StateError, StateReader, StateWriter};
+
+use super::{Envelope, LengthCounter};
+
+/// Timer periods selected by the lower 4 bits of reg 2 (NTSC)
+const PERIOD_TABLE: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+/// Noise channel (http://wiki.nesdev.com/w/index.php/APU_Noise)
+/// 
+/// - reg 0: --LC VVVV (length counter halt/envelope loop, constant volume, volume/envelope period)
+/// - reg 2: M--- PPPP (mode, period index)
+/// - reg 3: LLLL L--- (length counter load)
+pub(super) struct Noise {
+    pub(super) envelope: Envelope,
+    pub(super) length: LengthCounter,
+
+    /// Short mode, feedback is taken from bit 6 instead of bit 1
+    mode: bool,
+    timer_period: u16,
+    timer: u16,
+    /// 15-Bit linear feedback shift register
+    shift: u16,
+}
+
+impl Noise {
+    pub(super) fn new() -> Self {
+        Self {
+            envelope: Envelope::new(),
+            length: LengthCounter::new(),
+
+            mode: false,
+            timer_period: PERIOD_TABLE[0],
+            timer: 0,
+            shift: 1,
+        }
+    }
+
+    pub(super) fn save_state(&self, w: &mut StateWriter) {
+        self.envelope.save_state(w);
+        self.length.save_state(w);
+        w.write_bool(self.mode);
+        w.write_u16(self.timer_period);
+        w.write_u16(self.timer);
+        w.write_u16(self.shift);
+    }
+
+    pub(super) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.envelope.load_state(r)?;
+        self.length.load_state(r)?;
+        self.mode = r.read_bool()?;
+        self.timer_period = r.read_u16()?;
+        self.timer = r.read_u16()?;
+        self.shift = r.read_u16()?;
+        Ok(())
+    }
+
+    pub(super) fn write_register(&mut self, reg: u16, val: u8) {
+        match reg {
+            0 => {
+                self.length.halt = (val & 0x20) != 0;
+                self.envelope.write_control(val);
+            }
+            2 => {
+                self.mode = (val & 0x80) != 0;
+                self.timer_period = PERIOD_TABLE[(val & 0x0F) as usize];
+            }
+            3 => {
+                self.length.load(val >> 3);
+                self.envelope.start = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Clocks the timer, called every APU cycle (every other CPU cycle)
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.clock_shift();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_shift(&mut self) {
+        let tap = if self.mode { 6 } else { 1 };
+        let feedback = (self.shift ^ (self.shift >> tap)) & 0x01;
+        self.shift = (self.shift >> 1) | (feedback << 14);
+    }
+
+    /// Current output level (0-15), silent while bit 0 of the shift register is set
+    pub(super) fn output(&self) -> u8 {
+        if (self.shift & 0x01) != 0 || !self.length.is_active() {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::state::{StateError, StateReader, StateWriter};
+
+use super::{Envelope, LengthCounter};
+
+/// Waveforms selected by the duty bits, one bit per sequencer step
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// Pulse (square wave) channel (http://wiki.nesdev.com/w/index.php/APU_Pulse)
+/// 
+/// - reg 0: DDLC VVVV (duty, length counter halt/envelope loop, constant volume, volume/envelope period)
+/// - reg 1: EPPP NSSS (sweep enable, period, negate, shift)
+/// - reg 2: timer low
+/// - reg 3: LLLL LHHH (length counter load, timer high)
+pub(super) struct Pulse {
+    pub(super) envelope: Envelope,
+    pub(super) length: LengthCounter,
+
+    duty: u8,
+    sequence_pos: u8,
+    timer_period: u16,
+    timer: u16,
+
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+    /// Pulse 1 negates the sweep change with one's complement, pulse 2 with two's complement
+    ones_complement: bool,
+}
+
+impl Pulse {
+    pub(super) fn new(ones_complement: bool) -> Self {
+        Self {
+            envelope: Envelope::new(),
+            length: LengthCounter::new(),
+
+            duty: 0,
+            sequence_pos: 0,
+            timer_period: 0,
+            timer: 0,
+
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+            ones_complement,
+        }
+    }
+
+    pub(super) fn save_state(&self, w: &mut StateWriter) {
+        self.envelope.save_state(w);
+        self.length.save_state(w);
+        w.write_u8(self.duty);
+        w.write_u8(self.sequence_pos);
+        w.write_u16(self.timer_period);
+        w.write_u16(self.timer);
+        w.write_bool(self.sweep_enabled);
+        w.write_u8(self.sweep_period);
+        w.write_bool(self.sweep_negate);
+        w.write_u8(self.sweep_shift);
+        w.write_u8(self.sweep_divider);
+        w.write_bool(self.sweep_reload);
+    }
+
+    pub(super) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.envelope.load_state(r)?;
+        self.length.load_state(r)?;
+        self.duty = r.read_u8()? & 0x03;
+        self.sequence_pos = r.read_u8()? & 0x07;
+        self.timer_period = r.read_u16()? & 0x07FF;
+        self.timer = r.read_u16()?;
+        self.sweep_enabled = r.read_bool()?;
+        self.sweep_period = r.read_u8()? & 0x07;
+        self.sweep_negate = r.read_bool()?;
+        self.sweep_shift = r.read_u8()? & 0x07;
+        self.sweep_divider = r.read_u8()?;
+        self.sweep_reload = r.read_bool()?;
+        Ok(())
+    }
+
+    pub(super) fn write_register(&mut self, reg: u16, val: u8) {
+        match reg {
+            0 => {
+                self.duty = val >> 6;
+                self.length.halt = (val & 0x20) != 0;
+                self.envelope.write_control(val);
+            }
+            1 => {
+                self.sweep_enabled = (val & 0x80) != 0;
+                self.sweep_period = (val >> 4) & 0x07;
+                self.sweep_negate = (val & 0x08) != 0;
+                self.sweep_shift = val & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.timer_period = (self.timer_period & 0x0700) | val as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((val & 0x07) as u16) << 8);
+                self.length.load(val >> 3);
+                self.sequence_pos = 0;
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    /// Clocks the timer, called every APU cycle (every other CPU cycle)
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_pos = (self.sequence_pos + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn clock_half_frame(&mut self) {
+        self.length.clock();
+
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.sweep_muting() {
+            self.timer_period = self.sweep_target();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// The period the sweep unit would change the timer period to
+    fn sweep_target(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+
+        if self.sweep_negate {
+            let change = if self.ones_complement { change + 1 } else { change };
+            self.timer_period.saturating_sub(change)
+        } else {
+            self.timer_period + change
+        }
+    }
+
+    /// The sweep unit mutes the channel if the period is too low or the target period overflows,
+    /// even while sweeping is disabled
+    fn sweep_muting(&self) -> bool {
+        self.timer_period < 8 || self.sweep_target() > 0x7FF
+    }
+
+    /// Current output level (0-15)
+    pub(super) fn output(&self) -> u8 {
+        if DUTY_TABLE[self.duty as usize][self.sequence_pos as usize] == 0
+            || !self.length.is_active()
+            || self.sweep_muting()
+        {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::state::{StateError, StateReader, StateWriter};
+
+use super::LengthCounter;
+
+/// Output levels of the 32 sequencer steps
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// Triangle channel (http://wiki.nesdev.com/w/index.php/APU_Triangle)
+/// 
+/// - reg 0: CRRR RRRR (length counter halt/linear counter control, linear counter reload value)
+/// - reg 2: timer low
+/// - reg 3: LLLL LHHH (length counter load, timer high)
+pub(super) struct Triangle {
+    pub(super) length: LengthCounter,
+
+    control: bool,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+
+    sequence_pos: u8,
+    timer_period: u16,
+    timer: u16,
+}
+
+impl Triangle {
+    pub(super) fn new() -> Self {
+        Self {
+            length: LengthCounter::new(),
+
+            control: false,
+            linear_reload_value: 0,
+            linear_counter: 0,
+            linear_reload: false,
+
+            sequence_pos: 0,
+            timer_period: 0,
+            timer: 0,
+        }
+    }
+
+    pub(super) fn save_state(&self, w: &mut StateWriter) {
+        self.length.save_state(w);
+        w.write_bool(self.control);
+        w.write_u8(self.linear_reload_value);
+        w.write_u8(self.linear_counter);
+        w.write_bool(self.linear_reload);
+        w.write_u8(self.sequence_pos);
+        w.write_u16(self.timer_period);
+        w.write_u16(self.timer);
+    }
+
+    pub(super) fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.length.load_state(r)?;
+        self.control = r.read_bool()?;
+        self.linear_reload_value = r.read_u8()?;
+        self.linear_counter = r.read_u8()?;
+        self.linear_reload = r.read_bool()?;
+        self.sequence_pos = r.read_u8()? & 0x1F;
+        self.timer_period = r.read_u16()?;
+        self.timer = r.read_u16()?;
+        Ok(())
+    }
+
+    pub(super) fn write_register(&mut self, reg: u16, val: u8) {
+        match reg {
+            0 => {
+                self.control = (val & 0x80) != 0;
+                self.length.halt = self.control;
+                self.linear_reload_value = val & 0x7F;
+            }
+            2 => self.timer_period = (self.timer_period & 0x0700) | val as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((val & 0x07) as u16) << 8);
+                self.length.load(val >> 3);
+                self.linear_reload = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Clocks the timer, called every CPU cycle
+    /// 
+    /// The sequencer only advances while both the linear counter and the length counter are non-zero
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            if self.linear_counter > 0 && self.length.is_active() {
+                self.sequence_pos = (self.sequence_pos + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocks the linear counter
+    pub(super) fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    /// Current output level (0-15)
+    /// 
+    /// Silencing the channel only stops the sequencer, the output stays at the current level
+    pub(super) fn output(&self) -> u8 {
+        SEQUENCE[self.sequence_pos as usize]
+    }
+}
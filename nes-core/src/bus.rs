@@ -0,0 +1,255 @@
+use alloc::{boxed::Box, collections::BTreeSet};
+
+use crate::{apu::Apu, controller::Controller, mappers::Mapper, memory::{AccessKind, Memory, WatchpointHit}, ppu::Ppu, state::{StateError, StateReader, StateWriter}};
+
+/// Contents of the CPU RAM at power on, see [`Bus::fill_ram`]
+///
+/// Real RAM powers on with semi-random contents, which some games (accidentally) depend on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RamInit {
+    /// Every byte is 0x00
+    #[default]
+    Zeroed,
+    /// Every byte is set to the given value
+    Filled(u8),
+    /// Pseudo random bytes, the same seed always produces the same contents
+    Seeded(u64),
+}
+
+/// The console's CPU bus
+/// 
+/// - 0x0000-0x1FFF: 2 KB internal RAM, mirrored every 0x800 bytes
+/// - 0x2000-0x3FFF: PPU registers, mirrored every 8 bytes
+/// - 0x4000-0x4013, 0x4015: [`Apu`] registers, 0x4015 is the only readable one
+/// - 0x4014: OAM DMA (write), copies 0xXX00-0xXXFF into PPU OAM
+/// - 0x4016: controller strobe (write), player 1 [`Controller`] (read)
+/// - 0x4017: APU frame counter (write), player 2 [`Controller`] (read)
+/// - 0x4018-0x401F: unused
+/// - 0x4020-0xFFFF: cartridge space, handled by the [`Mapper`]
+/// 
+/// Reads from addresses without any backing return the last value that was on the data bus (open bus),
+/// the controller ports only drive bit 0 and return open bus in the upper bits
+pub struct Bus {
+    cpu_ram: [u8; 0x800],
+    ppu: Ppu,
+    apu: Apu,
+    controllers: [Controller; 2],
+    mapper: Box<dyn Mapper>,
+    /// Last value read from or written to the data bus
+    last_bus_value: u8,
+    /// Set when an OAM DMA was performed, the CPU still has to account for the stall
+    dma_requested: bool,
+    /// Addresses whose reads are reported through [`Memory::take_watchpoint_hit`]
+    read_watchpoints: BTreeSet<u16>,
+    /// Addresses whose writes are reported through [`Memory::take_watchpoint_hit`]
+    write_watchpoints: BTreeSet<u16>,
+    watchpoint_hit: Option<WatchpointHit>,
+}
+
+impl Bus {
+    pub fn new(mapper: Box<dyn Mapper>) -> Self {
+        Self {
+            cpu_ram: [0; 0x800],
+            ppu: Ppu::new(),
+            apu: Apu::new(),
+            controllers: [Controller::new(), Controller::new()],
+            mapper,
+            last_bus_value: 0,
+            dma_requested: false,
+            read_watchpoints: BTreeSet::new(),
+            write_watchpoints: BTreeSet::new(),
+            watchpoint_hit: None,
+        }
+    }
+
+    /// Overwrites the whole CPU RAM according to `pattern`
+    pub fn fill_ram(&mut self, pattern: RamInit) {
+        match pattern {
+            RamInit::Zeroed => self.cpu_ram.fill(0),
+            RamInit::Filled(val) => self.cpu_ram.fill(val),
+            RamInit::Seeded(seed) => {
+                // splitmix64 (http://prng.di.unimi.it/splitmix64.c)
+                let mut state = seed;
+                for chunk in self.cpu_ram.chunks_exact_mut(8) {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    z ^= z >> 31;
+                    chunk.copy_from_slice(&z.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    /// Writes the RAM and the state of all devices on the bus into a save state
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.cpu_ram);
+        w.write_u8(self.last_bus_value);
+        self.ppu.save_state(w);
+        self.apu.save_state(w);
+        for controller in &self.controllers {
+            controller.save_state(w);
+        }
+        self.mapper.save_state(w);
+    }
+
+    /// Restores the state written by [`Bus::save_state`]
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        r.read_bytes(&mut self.cpu_ram)?;
+        self.last_bus_value = r.read_u8()?;
+        self.ppu.load_state(r)?;
+        self.apu.load_state(r)?;
+        for controller in &mut self.controllers {
+            controller.load_state(r)?;
+        }
+        self.mapper.load_state(r)
+    }
+
+    /// Watches reads (`on_read`) and/or writes (`on_write`) of the CPU address `addr`
+    ///
+    /// Mirrors are not watched, an access has to use exactly `addr`.
+    /// Hits are reported through [`Memory::take_watchpoint_hit`]
+    pub fn add_watchpoint(&mut self, addr: u16, on_read: bool, on_write: bool) {
+        if on_read {
+            self.read_watchpoints.insert(addr);
+        }
+        if on_write {
+            self.write_watchpoints.insert(addr);
+        }
+    }
+
+    /// Removes all watchpoints at `addr`
+    pub fn remove_watchpoint(&mut self, addr: u16) {
+        self.read_watchpoints.remove(&addr);
+        self.write_watchpoints.remove(&addr);
+    }
+
+    /// Records a watchpoint hit, unless an earlier one is still pending
+    fn check_watchpoint(&mut self, addr: u16, access: AccessKind) {
+        let watchpoints = match access {
+            AccessKind::Read => &self.read_watchpoints,
+            AccessKind::Write => &self.write_watchpoints,
+        };
+
+        if self.watchpoint_hit.is_none() && watchpoints.contains(&addr) {
+            self.watchpoint_hit = Some(WatchpointHit { addr, access });
+        }
+    }
+
+    /// Copies the 256 bytes at 0xXX00-0xXXFF (XX = `page`) into PPU OAM
+    fn oam_dma(&mut self, page: u8) {
+        let base = (page as u16) << 8;
+        for i in 0..0x100 {
+            let val = self.cpu_load8(base + i);
+            self.ppu.write_oam(val);
+        }
+
+        self.dma_requested = true;
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.ppu
+    }
+
+    /// Controller of player `player` (0 or 1)
+    pub fn controller(&self, player: usize) -> &Controller {
+        &self.controllers[player]
+    }
+
+    /// Controller of player `player` (0 or 1)
+    pub fn controller_mut(&mut self, player: usize) -> &mut Controller {
+        &mut self.controllers[player]
+    }
+
+    /// Last value read from or written to the data bus, returned by reads from unmapped addresses
+    pub fn last_bus_value(&self) -> u8 {
+        self.last_bus_value
+    }
+
+    /// Advances the PPU by a single dot
+    pub fn step_ppu(&mut self) {
+        self.ppu.step(self.mapper.as_mut());
+    }
+
+    pub fn apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub fn apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    pub fn mapper(&self) -> &dyn Mapper {
+        self.mapper.as_ref()
+    }
+
+    pub fn mapper_mut(&mut self) -> &mut dyn Mapper {
+        self.mapper.as_mut()
+    }
+}
+
+impl Memory for Bus {
+    fn cpu_load8(&mut self, addr: u16) -> u8 {
+        self.check_watchpoint(addr, AccessKind::Read);
+
+        let val = match addr {
+            0x0000..=0x1FFF => self.cpu_ram[(addr & 0x7FF) as usize],
+            0x2000..=0x3FFF => self.ppu.read_register((addr & 0x07) as u8, self.mapper.as_mut()),
+            // bit 5 is not driven by the APU
+            0x4015 => (self.last_bus_value & 0x20) | self.apu.read_status(),
+            0x4016 => (self.last_bus_value & 0xE0) | self.controllers[0].read(),
+            0x4017 => (self.last_bus_value & 0xE0) | self.controllers[1].read(),
+            0x4000..=0x401F => self.last_bus_value,
+            _ => self.mapper.cpu_load8(addr).unwrap_or(self.last_bus_value),
+        };
+
+        self.last_bus_value = val;
+        val
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        self.check_watchpoint(addr, AccessKind::Write);
+        self.last_bus_value = val;
+
+        match addr {
+            0x0000..=0x1FFF => self.cpu_ram[(addr & 0x7FF) as usize] = val,
+            0x2000..=0x3FFF => self.ppu.write_register((addr & 0x07) as u8, val, self.mapper.as_mut()),
+            0x4014 => self.oam_dma(val),
+            0x4016 => {
+                for controller in &mut self.controllers {
+                    controller.write(val);
+                }
+            }
+            0x4000..=0x4017 => self.apu.write_register(addr, val),
+            0x4018..=0x401F => {}
+            _ => self.mapper.cpu_store8(addr, val),
+        }
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.cpu_ram[(addr & 0x7FF) as usize],
+            0x2000..=0x3FFF => self.ppu.peek_register((addr & 0x07) as u8),
+            0x4015 => (self.last_bus_value & 0x20) | self.apu.peek_status(),
+            0x4016 => (self.last_bus_value & 0xE0) | self.controllers[0].peek(),
+            0x4017 => (self.last_bus_value & 0xE0) | self.controllers[1].peek(),
+            0x4000..=0x401F => self.last_bus_value,
+            _ => self.mapper.cpu_peek8(addr).unwrap_or(self.last_bus_value),
+        }
+    }
+
+    fn take_dma_request(&mut self) -> bool {
+        let res = self.dma_requested;
+        self.dma_requested = false;
+        res
+    }
+
+    fn take_watchpoint_hit(&mut self) -> Option<WatchpointHit> {
+        self.watchpoint_hit.take()
+    }
+}
@@ -0,0 +1,103 @@
+use crate::state::{StateError, StateReader, StateWriter};
+
+/// Buttons of a standard NES controller, in the order they are shifted out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    A = 0,
+    B = 1,
+    Select = 2,
+    Start = 3,
+    Up = 4,
+    Down = 5,
+    Left = 6,
+    Right = 7,
+}
+
+/// Standard NES controller (http://wiki.nesdev.com/w/index.php/Standard_controller)
+/// 
+/// Connected to 0x4016 (player 1) and 0x4017 (player 2) by the [`Bus`](crate::bus::Bus)
+/// 
+/// - Writing 1 to bit 0 of 0x4016 sets the strobe, which continuously reloads the shift register
+///   with the current button state
+/// - Each read returns the next button in bit 0, in the order of [`Button`],
+///   after all eight buttons have been read, further reads return 1
+pub struct Controller {
+    /// Current button state, one bit per [`Button`]
+    buttons: u8,
+    shift: u8,
+    strobe: bool,
+}
+
+impl Controller {
+    pub fn new() -> Self {
+        Self {
+            buttons: 0,
+            shift: 0,
+            strobe: false,
+        }
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.buttons);
+        w.write_u8(self.shift);
+        w.write_bool(self.strobe);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.buttons = r.read_u8()?;
+        self.shift = r.read_u8()?;
+        self.strobe = r.read_bool()?;
+        Ok(())
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        let mask = 1 << button as u8;
+        if pressed {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        (self.buttons & (1 << button as u8)) != 0
+    }
+
+    /// Handles a CPU write to 0x4016, only bit 0 (the strobe) is used
+    pub fn write(&mut self, val: u8) {
+        let strobe = (val & 0x01) != 0;
+        // the shift register follows the buttons while the strobe is set,
+        // so releasing it latches the state of that moment
+        if self.strobe || strobe {
+            self.shift = self.buttons;
+        }
+        self.strobe = strobe;
+    }
+
+    /// Handles a CPU read from this controller's port and returns the next button bit
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            return self.buttons & 0x01;
+        }
+
+        let res = self.shift & 0x01;
+        // the shift register is filled with 1s from the top
+        self.shift = (self.shift >> 1) | 0x80;
+        res
+    }
+
+    /// Returns the bit the next [`Controller::read`] would return, without shifting
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            self.buttons & 0x01
+        } else {
+            self.shift & 0x01
+        }
+    }
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
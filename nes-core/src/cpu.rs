@@ -1,1184 +1,2406 @@
-use crate::{cpu_ops::{CPU_OPS, CpuOp}, mappers::Mapper};
-
-pub const CPU_CLOCK_DIV: u64 = 12;
-
-pub struct Cpu {
-    reg_a: u8,
-    reg_x: u8,
-    reg_y: u8,
-    reg_pc: u16,
-    reg_s: u8,
-    reg_p: u8,
-
-    opmap: [CpuOp; 0x100],
-
-    master_clock: u64,
-}
-
-impl Cpu {
-    pub fn new() -> Self {
-        let mut opmap = [CpuOp{ name: "???", opcode: 0x00, addr_mode: AddressingMode::Implicit, func: Self::op_invalid}; 0x100];
-
-        for op in &CPU_OPS {
-            opmap[op.opcode as usize] = *op;
-        }
-        
-        Self {
-            reg_a: 0,
-            reg_x: 0,
-            reg_y: 0,
-            reg_pc: 0,
-            reg_s: 0,
-            reg_p: 0,
-
-            opmap,
-
-            master_clock: 0
-        }
-    }
-
-    /// Resets the CPU to the following state
-    /// - P: InterruptDisable
-    /// - A, X, Y: 0
-    /// - S: 0xFD
-    /// - PC: loaded from reset vector (0xFFFC)
-    ///
-    /// The reset will take 7 cpu cycles
-    pub fn reset(&mut self, memory: &mut dyn Mapper) {
-        self.master_clock = 7 * CPU_CLOCK_DIV;
-
-        self.reg_p = Flags::InterruptDisable as u8;
-        self.reg_a = 0;
-        self.reg_x = 0;
-        self.reg_y = 0;
-        self.reg_s = 0xFD;
-        
-        let pc_low = memory.cpu_load8(0xFFFC);
-        let pc_high = memory.cpu_load8(0xFFFD);
-        self.reg_pc = ((pc_high as u16) << 8) | (pc_low as u16);
-    }
-
-    /// Performs a single CPU Instruction
-    pub fn execute_single_instruction(&mut self, memory: &mut dyn Mapper) {
-        // cycle 0: load opcode, increment PC
-        let opcode = memory.cpu_load8(self.reg_pc);
-        let op = self.opmap[opcode as usize];
-
-        println!("{:0>4X}  {}  A:{:0>2X} X:{:0>2X} Y:{:0>2X} P:{:0>2X} SP:{:0>2X}  CYC:{}", self.reg_pc, op.name, self.reg_a, self.reg_x, self.reg_y, self.reg_p | 0x20, self.reg_s, self.master_clock / CPU_CLOCK_DIV as u64);
-    
-        self.reg_pc += 1;
-        self.master_clock += CPU_CLOCK_DIV;
-
-        (op.func)(self, op.addr_mode, memory);
-    }
-
-    /// Instruction that is executed when an unofficial opcode is encountered
-    pub(crate) fn op_invalid(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.op_nop(addr_mode, memory)
-    }
-
-    /// Sets the given flag to `value`.
-    /// See [`Flags`]
-    fn set_flag(&mut self, flag: Flags, value: bool) {
-        if value {
-            self.reg_p |= flag as u8;
-        } else {
-            self.reg_p &= !(flag as u8);
-        }
-    }
-    /// Gets the value of the given flag.
-    /// See [`Flags`]
-    fn get_flag(&self, flag: Flags) -> bool {
-        (self.reg_p & flag as u8) != 0
-    }
-
-    /// Returns the operand address for [`AddressingModes`](AddressingMode) that
-    /// load an operand from memory
-    /// # Returns
-    /// (addr, extra_cycle)
-    /// - `addr`: the resolved address of the instruction operand
-    /// - `extra_cycle`: whether the addressing mode caused an extra cycle on a reading instruction
-    fn get_operand_addr(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper, is_read: bool) -> u16 {
-        match addr_mode {
-            AddressingMode::Implicit => {
-                // cycle 1: read next instruction byte and throw it away
-                memory.cpu_load8(self.reg_pc);
-                self.master_clock += CPU_CLOCK_DIV;
-                0
-            }
-            AddressingMode::ZeroPage => {
-                // cycle 1: load immediate 1 byte address
-                let arg = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-                arg as u16
-            }
-            AddressingMode::ZeroPageX => {
-                // cycle 1: load immediate 1 byte address
-                let mut arg = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: dummy read from unindexed address, add X to address
-                memory.cpu_load8(arg as u16);
-                self.master_clock += CPU_CLOCK_DIV;
-                // add x
-                arg = arg.wrapping_add(self.reg_x);
-                arg as u16
-            }
-            AddressingMode::ZeroPageY => {
-                // cycle 1: load immediate 1 byte address
-                let mut arg = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: dummy read from unindexed address, add Y to address
-                memory.cpu_load8(arg as u16);
-                self.master_clock += CPU_CLOCK_DIV;
-                // add y
-                arg = arg.wrapping_add(self.reg_y);
-                arg as u16
-            }
-            AddressingMode::Absolute => {
-                // cycle 1: load low address byte
-                let addr_low = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: load high address byte
-                let addr_high = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                let addr = ((addr_high as u16) << 8) | (addr_low as u16);
-                addr
-            }
-            AddressingMode::AbsoluteX => {
-                // cycle 1: load low addr byte
-                let mut base_addr = memory.cpu_load8(self.reg_pc) as u16;
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: load high addr byte
-                base_addr |= (memory.cpu_load8(self.reg_pc) as u16) << 8;
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                let real_addr = base_addr + self.reg_x as u16;
-
-                // write and read-modify-write instructions always read the unfixed effective addr once without using the value,
-                // read instructions only have this wasted read on a page crossing
-                if !is_read || ((real_addr & 0xFF00) != (base_addr & 0xFF00)) {
-                    memory.cpu_load8((base_addr & 0xFF00) | (real_addr & 0x00FF));
-                    self.master_clock += CPU_CLOCK_DIV;
-                }
-
-                real_addr
-            }
-            AddressingMode::AbsoluteY => {
-                // cycle 1: load low addr byte
-                let mut base_addr = memory.cpu_load8(self.reg_pc) as u16;
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: load high addr byte
-                base_addr |= (memory.cpu_load8(self.reg_pc) as u16) << 8;
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                let real_addr = base_addr.wrapping_add(self.reg_y as u16);
-
-                // write and read-modify-write instructions always read the unfixed effective addr once without using the value,
-                // read instructions only have this wasted read on a page crossing
-                if !is_read || ((real_addr & 0xFF00) != (base_addr & 0xFF00)) {
-                    memory.cpu_load8((base_addr & 0xFF00) | (real_addr & 0x00FF));
-                    self.master_clock += CPU_CLOCK_DIV;
-                }
-
-                real_addr
-            }
-            AddressingMode::Immediate | AddressingMode::Relative => {
-                // cycle 1: read immediate operand
-                let addr = self.reg_pc;
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                // note: no clock increment because whichever instruction uses this function
-                // will load the value on its own
-                //self.master_clock += CPU_CLOCK_DIV;
-
-                addr
-            }
-            AddressingMode::Indirect => {
-                // cycle 1: load ptr low
-                let ptr_low = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: load ptr high
-                let ptr_high = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 3: load addr low
-                let addr_low = memory.cpu_load8(((ptr_high as u16) << 8) | (ptr_low as u16));
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 4: load addr high
-                // note: if ptr_low is 0xFF, no page crossing will be handled
-                let addr_high = memory.cpu_load8(((ptr_high as u16) << 8) | (ptr_low.wrapping_add(1) as u16));
-                self.master_clock += CPU_CLOCK_DIV;
-                
-                ((addr_high as u16) << 8) | (addr_low as u16)
-            }
-            AddressingMode::IndexedIndirect => {
-                // cycle 1: load ptr
-                let mut ptr = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: dummy read address, add X
-                memory.cpu_load8(ptr as u16);
-                ptr = ptr.wrapping_add(self.reg_x);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 3: load addr low
-                let addr_low = memory.cpu_load8(ptr as u16);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 4: load addr high
-                // note: no page crossing will be handled
-                let addr_high = memory.cpu_load8(ptr.wrapping_add(1) as u16);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                ((addr_high as u16) << 8) | (addr_low as u16)
-            }
-            AddressingMode::IndirectIndexed => {
-                // cycle 1: load ptr
-                let ptr = memory.cpu_load8(self.reg_pc);
-                self.reg_pc = self.reg_pc.wrapping_add(1);
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 2: load addr low
-                let mut base_addr = memory.cpu_load8(ptr as u16) as u16;
-                self.master_clock += CPU_CLOCK_DIV;
-
-                // cycle 3: load addr high
-                base_addr |= (memory.cpu_load8(ptr.wrapping_add(1) as u16) as u16) << 8;
-                self.master_clock += CPU_CLOCK_DIV;
-
-                let real_addr = base_addr.wrapping_add(self.reg_y as u16);
-
-                // write and read-modify-write instructions always do a useless read of the unfixed addr,
-                // read instructions only when a page is crossed by adding y
-                if !is_read || ((real_addr & 0xFF00) != (base_addr & 0xFF00)) {
-                    memory.cpu_load8((base_addr & 0xFF00) | (real_addr & 0x00FF));
-                    self.master_clock += CPU_CLOCK_DIV;
-                }
-
-                real_addr
-            }
-        }
-    }
-
-    pub(crate) fn op_adc(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let carry_in: u16 = if self.get_flag(Flags::Carry) { 1 } else { 0 };
-
-        let res = (op as u16).wrapping_add(self.reg_a as u16).wrapping_add(carry_in);
-
-        self.set_flag(Flags::Carry, (res & 0x100) != 0);
-        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        let overflow = (!(self.reg_a ^ op)) & (self.reg_a ^ (res & 0xFF) as u8) & 0x80;
-        self.set_flag(Flags::Overflow, overflow != 0);
-
-        self.reg_a = (res & 0xFF) as u8;
-
-        0
-    }
-
-    pub(crate) fn op_and(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let res = self.reg_a & op;
-
-        self.set_flag(Flags::Zero, res == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        self.reg_a = res;
-
-        0
-    }
-
-    pub(crate) fn op_asl_a(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        let res = (self.reg_a as u16) << 1;
-
-        self.set_flag(Flags::Carry, (res & 0x100) != 0);
-        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        self.reg_a = (res & 0xFF) as u8;
-        0
-    }
-
-    pub(crate) fn op_asl_m(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-
-        // read operand
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        // dummy write value back
-        memory.cpu_store8(op_addr, op);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let res = (op as u16) << 1;
-
-        self.set_flag(Flags::Carry, (res & 0x100) != 0);
-        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        // write result
-        memory.cpu_store8(op_addr, (res & 0xFF) as u8);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    /// Performs a relative branch with `op` as signed 8-Bit Offset
-    /// # Cycles
-    /// - A branch instruction that does not branch takes 2 Cycles
-    /// - If a branch is taken, add one cycle
-    /// - If the branch crosses a page (e.g. 0x01xx -> 0x02xx), add another cycle
-    fn relative_branch(&mut self, op: u8, memory: &mut dyn Mapper) -> u8 {
-        // on a taken branch, the next instruction is read and discarded
-        memory.cpu_load8(self.reg_pc);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let mut offs = op as u16;
-        // perform sign extension
-        if (offs & 0x80) != 0 {
-            offs |= 0xFF00;
-        }
-
-        let new_pc = self.reg_pc.wrapping_add(offs);
-
-        if (new_pc & 0xFF00) != (self.reg_pc & 0xFF00) {
-            // on page cross add another dummy read at the unfixed new pc
-            memory.cpu_load8((self.reg_pc & 0xFF00) | (new_pc & 0x00FF));
-            self.master_clock += CPU_CLOCK_DIV;
-        }
-
-        self.reg_pc = new_pc;
-        0
-    }
-
-    pub(crate) fn op_bcc(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if !self.get_flag(Flags::Carry) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_bcs(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if self.get_flag(Flags::Carry) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_beq(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if self.get_flag(Flags::Zero) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_bit(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let res = self.reg_a & op;
-
-        self.set_flag(Flags::Zero, res == 0);
-        self.set_flag(Flags::Overflow, (op & 0x40) != 0);
-        self.set_flag(Flags::Negative, (op & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_bmi(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if self.get_flag(Flags::Negative) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_bne(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if !self.get_flag(Flags::Zero) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_bpl(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if !self.get_flag(Flags::Negative) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_brk(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let ret_addr_low = (self.reg_pc & 0xFF) as u8;
-        let ret_addr_high = (self.reg_pc.wrapping_shr(8)) as u8;
-        let p = self.reg_p | 0x30;
-
-        self.push(ret_addr_high, memory);
-        self.push(ret_addr_low, memory);
-        self.push(p, memory);
-
-        self.set_flag(Flags::InterruptDisable, true);
-
-        let vect_low = memory.cpu_load8(0xFFFE);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let vect_high = memory.cpu_load8(0xFFFF);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_pc = ((vect_high as u16) << 8) | (vect_low as u16);
-        0
-    }
-
-    pub(crate) fn op_bvc(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if !self.get_flag(Flags::Overflow) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_bvs(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(AddressingMode::Relative, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        if self.get_flag(Flags::Overflow) {
-            self.relative_branch(op, memory)
-        } else {
-            0
-        }
-    }
-
-    pub(crate) fn op_clc(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::Carry, false);
-        0
-    }
-
-    pub(crate) fn op_cld(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::Decimal, false);
-        0
-    }
-
-    pub(crate) fn op_cli(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::InterruptDisable, false);
-        0
-    }
-
-    pub(crate) fn op_clv(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::Overflow, false);
-        0
-    }
-
-    pub(crate) fn op_cmp(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.set_flag(Flags::Carry, self.reg_a >= op);
-        self.set_flag(Flags::Zero, self.reg_a == op);
-
-        let tmp = (self.reg_a as u16).wrapping_sub(op as u16);
-        self.set_flag(Flags::Negative, (tmp & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_cpx(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.set_flag(Flags::Carry, self.reg_x >= op);
-        self.set_flag(Flags::Zero, self.reg_x == op);
-
-        let tmp = (self.reg_x as u16).wrapping_sub(op as u16);
-        self.set_flag(Flags::Negative, (tmp & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_cpy(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.set_flag(Flags::Carry, self.reg_y >= op);
-        self.set_flag(Flags::Zero, self.reg_y == op);
-
-        let tmp = (self.reg_y as u16).wrapping_sub(op as u16);
-        self.set_flag(Flags::Negative, (tmp & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_dec(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        memory.cpu_store8(op_addr, op);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let res = op.wrapping_sub(1);
-
-        self.set_flag(Flags::Zero, res == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        memory.cpu_store8(op_addr, res);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_dex(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_x = self.reg_x.wrapping_sub(1);
-
-        self.set_flag(Flags::Zero, self.reg_x == 0);
-        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_dey(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_y = self.reg_y.wrapping_sub(1);
-
-        self.set_flag(Flags::Zero, self.reg_y == 0);
-        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_eor(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_a ^= op;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_inc(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        memory.cpu_store8(op_addr, op);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let res = op.wrapping_add(1);
-
-        self.set_flag(Flags::Zero, res == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        memory.cpu_store8(op_addr, res);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_inx(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-        
-        self.reg_x = self.reg_x.wrapping_add(1);
-
-        self.set_flag(Flags::Zero, self.reg_x == 0);
-        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_iny(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-        
-        self.reg_y = self.reg_y.wrapping_add(1);
-
-        self.set_flag(Flags::Zero, self.reg_y == 0);
-        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_jmp(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-
-        self.reg_pc = op_addr;
-
-        0
-    }
-
-    pub(crate) fn op_jsr(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        // note: no self.get_operand_addr here because this instruction
-        // has an unusual cycle layout that does not match absolute addressing
-        let addr_low = memory.cpu_load8(self.reg_pc);
-        self.reg_pc = self.reg_pc.wrapping_add(1);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        // dummy read from stack
-        memory.cpu_load8(0x0100 | self.reg_s as u16);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.push((self.reg_pc >> 8) as u8, memory);
-        self.push((self.reg_pc & 0xFF) as u8, memory);
-
-        let addr_high = memory.cpu_load8(self.reg_pc);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_pc = ((addr_high as u16) << 8) | (addr_low as u16);
-
-        0
-    }
-
-    pub(crate) fn op_lda(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_a = op;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_ldx(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_x = op;
-
-        self.set_flag(Flags::Zero, self.reg_x == 0);
-        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_ldy(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_y = op;
-
-        self.set_flag(Flags::Zero, self.reg_y == 0);
-        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_lsr_a(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        let res = self.reg_a.wrapping_shr(1);
-
-        self.set_flag(Flags::Carry, (self.reg_a & 0x01) != 0);
-        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        self.reg_a = res;
-        0
-    }
-
-    pub(crate) fn op_lsr_m(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        memory.cpu_store8(op_addr, op);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let res = op.wrapping_shr(1);
-
-        self.set_flag(Flags::Carry, (op & 0x01) != 0);
-        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        memory.cpu_store8(op_addr, res);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_nop(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        0
-    }
-
-    pub(crate) fn op_ora(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        self.reg_a |= op;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    /// Pushes a byte onto the stack.
-    /// 
-    /// The value is pushed by
-    /// 1. writing `val` to `0x0100 + reg_s`
-    /// 2. decrementing `reg_s`
-    /// 
-    /// # Overflow
-    /// The CPU does not do anything special when `reg_s` overflows,
-    /// meaning the stack will loop around
-    fn push(&mut self, val: u8, memory: &mut dyn Mapper) {
-        let addr = 0x0100 | (self.reg_s as u16);
-        memory.cpu_store8(addr, val);
-        self.master_clock += CPU_CLOCK_DIV;
-        self.reg_s = self.reg_s.wrapping_sub(1);
-    }
-
-    /// Pulls a byte from the stack and returns it
-    /// 
-    /// The value is pulled by
-    /// 1. incrementing `reg_s`
-    /// 2. reading from `0x0100 + reg_s`
-    /// 
-    /// # Returns
-    /// The byte pulled from the stack
-    /// 
-    /// # Overflow
-    /// The CPU does not do anything special when `reg_s` underflows,
-    /// meaning the stack will loop around
-    fn pull(&mut self, memory: &mut dyn Mapper) -> u8 {
-        self.reg_s = self.reg_s.wrapping_add(1);
-
-        let addr = 0x0100 | (self.reg_s as u16);
-        let res = memory.cpu_load8(addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        res
-    }
-
-    pub(crate) fn op_pha(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.push(self.reg_a, memory);
-        0
-    }
-
-    pub(crate) fn op_php(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        let val = self.reg_p | 0x30;
-        self.push(val, memory);
-        0
-    }
-
-    pub(crate) fn op_pla(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        memory.cpu_load8(0x0100 | (self.reg_s as u16));
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let val = self.pull(memory);
-        self.reg_a = val;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_plp(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        memory.cpu_load8(0x0100 | (self.reg_s as u16));
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let val = self.pull(memory);
-        self.reg_p = val & 0xCF;
-
-        0
-    }
-
-    pub(crate) fn op_rol_a(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        let mut res = (self.reg_a as u16) << 1;
-        if self.get_flag(Flags::Carry) {
-            res |= 0x01;
-        }
-
-        self.set_flag(Flags::Carry, (res & 0x100) != 0);
-
-        self.reg_a = (res & 0xFF) as u8;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_rol_m(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        memory.cpu_store8(op_addr, op);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let mut res = (op as u16) << 1;
-        if self.get_flag(Flags::Carry) {
-            res |= 0x01;
-        }
-
-        self.set_flag(Flags::Carry, (res & 0x100) != 0);
-
-        let res = (res & 0xFF) as u8;
-
-        self.set_flag(Flags::Zero, res == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        memory.cpu_store8(op_addr, res);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_ror_a(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        let mut res = self.reg_a.wrapping_shr(1);
-        if self.get_flag(Flags::Carry) {
-            res |= 0x80;
-        }
-
-        self.set_flag(Flags::Carry, (self.reg_a & 0x01) != 0);
-
-        self.reg_a = (res & 0xFF) as u8;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_ror_m(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        let op = memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        memory.cpu_store8(op_addr, op);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let mut res = op.wrapping_shr(1);
-        if self.get_flag(Flags::Carry) {
-            res |= 0x80;
-        }
-
-        self.set_flag(Flags::Carry, (op & 0x01) != 0);
-
-        let res = (res & 0xFF) as u8;
-
-        self.set_flag(Flags::Zero, res == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        memory.cpu_store8(op_addr, res);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_rti(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        memory.cpu_load8(0x0100 | (self.reg_s as u16));
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let p = self.pull(memory);
-        let ret_addr_low = self.pull(memory);
-        let ret_addr_high = self.pull(memory);
-
-        let ret_addr = ((ret_addr_high as u16) << 8) | (ret_addr_low as u16);
-
-        self.reg_p = p & 0xCF;
-        self.reg_pc = ret_addr;
-
-        0
-    }
-
-    pub(crate) fn op_rts(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        memory.cpu_load8(0x0100 | (self.reg_s as u16));
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let ret_addr_low = self.pull(memory);
-        let ret_addr_high = self.pull(memory);
-
-        let ret_addr = ((ret_addr_high as u16) << 8) | (ret_addr_low as u16);
-
-        self.reg_pc = ret_addr.wrapping_add(1);
-
-        memory.cpu_load8(ret_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_sbc(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, true);
-        let op = !memory.cpu_load8(op_addr);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        let carry_in: u16 = self.get_flag(Flags::Carry) as u16;
-
-        let res = (op as u16).wrapping_add(self.reg_a as u16).wrapping_add(carry_in);
-
-        self.set_flag(Flags::Carry, (res & 0x100) != 0);
-        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
-        self.set_flag(Flags::Negative, (res & 0x80) != 0);
-
-        let overflow = (!(self.reg_a ^ op)) & (self.reg_a ^ (res & 0xFF) as u8) & 0x80;
-        self.set_flag(Flags::Overflow, overflow != 0);
-
-        self.reg_a = (res & 0xFF) as u8;
-
-        0
-    }
-
-    pub(crate) fn op_sec(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::Carry, true);
-        0
-    }
-
-    pub(crate) fn op_sed(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::Decimal, true);
-        0
-    }
-
-    pub(crate) fn op_sei(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.set_flag(Flags::InterruptDisable, true);
-        0
-    }
-
-    pub(crate) fn op_sta(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        
-        memory.cpu_store8(op_addr, self.reg_a);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_stx(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        
-        memory.cpu_store8(op_addr, self.reg_x);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_sty(&mut self, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        let op_addr = self.get_operand_addr(addr_mode, memory, false);
-        
-        memory.cpu_store8(op_addr, self.reg_y);
-        self.master_clock += CPU_CLOCK_DIV;
-
-        0
-    }
-
-    pub(crate) fn op_tax(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_x = self.reg_a;
-
-        self.set_flag(Flags::Zero, self.reg_x == 0);
-        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_tay(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_y = self.reg_a;
-
-        self.set_flag(Flags::Zero, self.reg_y == 0);
-        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_tsx(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_x = self.reg_s;
-
-        self.set_flag(Flags::Zero, self.reg_x == 0);
-        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_txa(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_a = self.reg_x;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-    pub(crate) fn op_txs(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_s = self.reg_x;
-
-        0
-    }
-
-    pub(crate) fn op_tya(&mut self, _: AddressingMode, memory: &mut dyn Mapper) -> u8 {
-        self.get_operand_addr(AddressingMode::Implicit, memory, false);
-
-        self.reg_a = self.reg_y;
-
-        self.set_flag(Flags::Zero, self.reg_a == 0);
-        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
-
-        0
-    }
-
-}
-
-/// Addressing Modes for Cpu Instructions
-#[derive(Debug, Clone, Copy)]
-pub(crate) enum AddressingMode {
-    /// No explicit operand (e.g. INX)
-    Implicit,
-    /// Single byte address (e.g. ADC $7F)
-    ZeroPage,
-    /// Single byte address + x register (e.g. ADC $7F,X),
-    /// wraps around to stay in zero page
-    ZeroPageX,
-    /// Single byte address + y register (e.g. ADC $7F,Y),
-    /// wraps around to stay in zero page
-    ZeroPageY,
-    /// Two byte address (e.g. ADC $5f70)
-    Absolute,
-    /// Two byte address + x register (e.g. ADC $5f70,X)
-    AbsoluteX,
-    /// Two byte address + y register (e.g. ADC $5f70,Y)
-    AbsoluteY,
-    /// Immediate operand (e.g. ADC #$64)
-    Immediate,
-    /// Signed relative offset from the next instruction (e.g. BNE label, where label is in the range +129/-126)
-    Relative,
-    /// Two byte address to memory location holding a two byte address
-    /// (e.g. JMP ($f0f0))
-    Indirect,
-    /// Single byte address + x register point to memory location holding a two byte address,
-    /// first address wraps around to zero page (e.g. ADC ($34,X))
-    IndexedIndirect,
-    /// Single byte address pointing to two byte address, add y register to two byte address
-    /// (e.g. ADC ($f0),Y)
-    IndirectIndexed,
-}
-
-/// Flags in the P register
-#[derive(Debug)]
-enum Flags {
-    Carry = 0x01,
-    Zero = 0x02,
-    InterruptDisable = 0x04,
-    Decimal = 0x08,
-    Overflow = 0x40,
-    Negative = 0x80,
-}
+use alloc::{boxed::Box, collections::BTreeSet, format, string::{String, ToString}};
+use core::fmt;
+
+use crate::{cpu_ops, memory::{Memory, WatchpointHit}, state::{StateError, StateReader, StateWriter}};
+
+/// Television standard of the console, determines its timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// NTSC (2A03 CPU), 21.477272 MHz master clock
+    Ntsc,
+    /// PAL (2A07 CPU), 26.601712 MHz master clock
+    Pal,
+}
+
+impl Region {
+    /// Returns the number of master clock ticks one cpu cycle takes
+    pub fn cpu_clock_div(self) -> u64 {
+        match self {
+            Region::Ntsc => 12,
+            Region::Pal => 16,
+        }
+    }
+
+    /// Returns the number of master clock ticks one PPU dot takes
+    pub fn ppu_clock_div(self) -> u64 {
+        match self {
+            Region::Ntsc => 4,
+            Region::Pal => 5,
+        }
+    }
+}
+
+/// Address of the NMI vector, the handler address is stored little endian
+pub const NMI_VECTOR: u16 = 0xFFFA;
+/// Address of the reset vector, the address execution starts at after power on or reset
+pub const RESET_VECTOR: u16 = 0xFFFC;
+/// Address of the IRQ/BRK vector
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+/// Default magic constant of the unstable XAA instruction, see [`Cpu::set_xaa_magic`]
+pub const DEFAULT_XAA_MAGIC: u8 = 0xEE;
+
+/// Callback that is invoked with a [`CpuTrace`] before each executed instruction
+pub type TraceHook = Box<dyn FnMut(&CpuTrace)>;
+
+/// Callback that is invoked with the PC of an instruction that jumps or branches to itself,
+/// see [`Cpu::set_infinite_loop_hook`]
+pub type InfiniteLoopHook = Box<dyn FnMut(u16)>;
+
+/// Callback that is invoked with the PC and the opcode of each instruction before it is executed,
+/// see [`Cpu::set_pre_step_hook`]
+pub type PreStepHook = Box<dyn FnMut(u16, u8)>;
+
+/// Snapshot of the CPU state right before an instruction is executed
+#[derive(Debug, Clone, Copy)]
+pub struct CpuTrace {
+    /// Address of the instruction
+    pub pc: u16,
+    /// Opcode byte of the instruction
+    pub opcode: u8,
+    /// Mnemonic of the instruction
+    pub name: &'static str,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    /// P register, bit 5 (unused) is always set and bit 4 (B) always cleared
+    pub p: u8,
+    pub s: u8,
+    /// Number of cpu cycles executed before this instruction
+    pub cycle: u64,
+}
+
+impl fmt::Display for CpuTrace {
+    /// Formats the trace in a Nintendulator compatible way
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:0>4X}  {}  A:{:0>2X} X:{:0>2X} Y:{:0>2X} P:{:0>2X} SP:{:0>2X}  CYC:{}", self.pc, self.name, self.a, self.x, self.y, self.p, self.s, self.cycle)
+    }
+}
+
+/// Reason why [`Cpu::run_until_breakpoint`] or [`Cpu::step_over`] stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// [`Cpu::step_over`] completed the instruction, including the subroutine a JSR called
+    Stepped,
+    /// PC reached the breakpoint at the contained address
+    Breakpoint(u16),
+    /// The last instruction accessed a watched address
+    Watchpoint(WatchpointHit),
+    /// The CPU was halted by a JAM instruction
+    Halted,
+}
+
+/// Stack pointer wrap around detected by the stack guard, see [`Cpu::set_stack_guard`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackFault {
+    /// A push wrapped S from 0x00 to 0xFF
+    Overflow,
+    /// A pull wrapped S from 0xFF to 0x00
+    Underflow,
+}
+
+/// Emulated 6502 (2A03) CPU core
+///
+/// All functions accessing memory are generic over the [`Memory`] type, so the accesses of a concrete
+/// memory type (e.g. [`Bus`](crate::bus::Bus)) can be inlined. Passing a `&mut dyn Memory` still works.
+pub struct Cpu {
+    reg_a: u8,
+    reg_x: u8,
+    reg_y: u8,
+    reg_pc: u16,
+    reg_s: u8,
+    reg_p: u8,
+
+    region: Region,
+    /// Master clock ticks per cpu cycle, see [`Region::cpu_clock_div`]
+    clock_div: u64,
+    master_clock: u64,
+
+    /// Set by a JAM instruction, cleared by reset
+    halted: bool,
+
+    /// Chip dependent constant ORed into A by XAA
+    xaa_magic: u8,
+
+    /// InterruptDisable as seen by the IRQ poll of the last instruction,
+    /// set by CLI, SEI and PLP because their change of the flag only takes effect one instruction later
+    delayed_interrupt_disable: Option<bool>,
+
+    /// Level of the external IRQ line, see [`Cpu::set_irq_line`]
+    irq_line: bool,
+    /// Level of the external NMI line, see [`Cpu::set_nmi_line`]
+    nmi_line: bool,
+    /// Set on a rising edge of `nmi_line`, cleared when the NMI is serviced
+    nmi_pending: bool,
+
+    trace_hook: Option<TraceHook>,
+    pre_step_hook: Option<PreStepHook>,
+
+    infinite_loop_hook: Option<InfiniteLoopHook>,
+    /// Number of consecutive self jumps after which `infinite_loop_hook` is invoked
+    infinite_loop_threshold: u32,
+    /// Number of consecutive instructions that jumped to their own address
+    self_jump_count: u32,
+
+    /// Addresses at which [`Cpu::run_until_breakpoint`] stops
+    breakpoints: BTreeSet<u16>,
+
+    /// Whether stack pointer wrap arounds are recorded in `stack_fault`
+    stack_guard: bool,
+    stack_fault: Option<StackFault>,
+
+    /// Whether executed opcodes are counted in `opcode_counts`
+    opcode_profiling: bool,
+    opcode_counts: Box<[u64; 256]>,
+
+    /// Whether opcode fetch addresses are recorded in `coverage`
+    coverage_tracking: bool,
+    coverage: Box<[u64; 1024]>,
+}
+
+impl Cpu {
+    /// Creates an NTSC CPU
+    pub fn new() -> Self {
+        Self::with_region(Region::Ntsc)
+    }
+
+    /// Creates a CPU with the timing of `region`
+    pub fn with_region(region: Region) -> Self {
+        Self {
+            reg_a: 0,
+            reg_x: 0,
+            reg_y: 0,
+            reg_pc: 0,
+            reg_s: 0,
+            reg_p: Flags::Unused as u8,
+
+            region,
+            clock_div: region.cpu_clock_div(),
+            master_clock: 0,
+
+            halted: false,
+
+            xaa_magic: DEFAULT_XAA_MAGIC,
+
+            delayed_interrupt_disable: None,
+
+            irq_line: false,
+            nmi_line: false,
+            nmi_pending: false,
+
+            trace_hook: None,
+            pre_step_hook: None,
+
+            infinite_loop_hook: None,
+            infinite_loop_threshold: 0,
+            self_jump_count: 0,
+
+            breakpoints: BTreeSet::new(),
+
+            stack_guard: false,
+            stack_fault: None,
+
+            opcode_profiling: false,
+            opcode_counts: Box::new([0; 256]),
+
+            coverage_tracking: false,
+            coverage: Box::new([0; 1024]),
+        }
+    }
+
+    /// Sets a hook that receives a [`CpuTrace`] before each executed instruction.
+    ///
+    /// Passing `None` disables tracing
+    pub fn set_trace_hook(&mut self, hook: Option<TraceHook>) {
+        self.trace_hook = hook;
+    }
+
+    /// Sets a hook that receives the PC and the opcode of each instruction right before it is executed.
+    ///
+    /// Unlike the trace hook no [`CpuTrace`] is built, which makes it the cheaper choice for custom
+    /// instrumentation. If an interrupt is serviced first, the hook only sees the first instruction of the handler,
+    /// use the trace hook if the interrupt sequence has to be observed (its cycle count includes the 7 cycles).
+    /// Passing `None` disables the hook
+    pub fn set_pre_step_hook(&mut self, hook: Option<PreStepHook>) {
+        self.pre_step_hook = hook;
+    }
+
+    /// Sets a hook that is invoked once an instruction has jumped or branched to its own address
+    /// `threshold` times in a row (e.g. `JMP *` or `BEQ *-2` with Z set), with the address of the instruction.
+    ///
+    /// Test ROMs commonly signal completion this way. The hook is invoked once per loop,
+    /// passing `None` disables the detection
+    pub fn set_infinite_loop_hook(&mut self, threshold: u32, hook: Option<InfiniteLoopHook>) {
+        self.infinite_loop_threshold = threshold.max(1);
+        self.infinite_loop_hook = hook;
+        self.self_jump_count = 0;
+    }
+
+    /// Returns the A register
+    pub fn a(&self) -> u8 {
+        self.reg_a
+    }
+
+    /// Returns the X register
+    pub fn x(&self) -> u8 {
+        self.reg_x
+    }
+
+    /// Returns the Y register
+    pub fn y(&self) -> u8 {
+        self.reg_y
+    }
+
+    /// Returns the program counter
+    pub fn pc(&self) -> u16 {
+        self.reg_pc
+    }
+
+    /// Returns the stack pointer (the stack lives at `0x0100 + sp`)
+    pub fn sp(&self) -> u8 {
+        self.reg_s
+    }
+
+    /// Returns the P register.
+    ///
+    /// Bit 4 (B) has no storage in the CPU and reads as 0,
+    /// bit 5 (unused) is not connected and reads as 1
+    pub fn status(&self) -> u8 {
+        self.reg_p
+    }
+
+    /// Returns the region whose timing the CPU uses
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Returns the number of cpu cycles executed so far
+    ///
+    /// [`Cpu::power_on`] seeds this to 7, the length of the reset sequence
+    pub fn cycles(&self) -> u64 {
+        self.master_clock / self.clock_div
+    }
+
+    /// Returns the number of master clock ticks executed so far,
+    /// one cpu cycle takes [`Region::cpu_clock_div`] ticks
+    pub fn master_clock(&self) -> u64 {
+        self.master_clock
+    }
+
+    /// Sets the A register
+    pub fn set_a(&mut self, v: u8) {
+        self.reg_a = v;
+    }
+
+    /// Sets the X register
+    pub fn set_x(&mut self, v: u8) {
+        self.reg_x = v;
+    }
+
+    /// Sets the Y register
+    pub fn set_y(&mut self, v: u8) {
+        self.reg_y = v;
+    }
+
+    /// Sets the program counter
+    pub fn set_pc(&mut self, pc: u16) {
+        self.reg_pc = pc;
+    }
+
+    /// Sets the stack pointer
+    pub fn set_sp(&mut self, v: u8) {
+        self.reg_s = v;
+    }
+
+    /// Sets the P register, bits 4 (B) and 5 (unused) of `v` are ignored
+    pub fn set_status(&mut self, v: u8) {
+        self.reg_p = Self::status_from_stack(v);
+    }
+
+    /// Returns whether the CPU was halted by a JAM instruction.
+    ///
+    /// A halted CPU does not execute any instructions until it is reset
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Powers on the CPU (cold boot), putting it into the following state
+    /// - P: InterruptDisable (0x24 including the unused bit)
+    /// - A, X, Y: 0
+    /// - S: 0xFD
+    /// - PC: loaded from reset vector (0xFFFC)
+    /// - no longer halted
+    ///
+    /// The power on sequence will take 7 cpu cycles
+    pub fn power_on<M: Memory + ?Sized>(&mut self, memory: &mut M) {
+        self.master_clock = 0;
+
+        self.reg_p = Flags::Unused as u8;
+        self.reg_a = 0;
+        self.reg_x = 0;
+        self.reg_y = 0;
+        self.reg_s = 0x00;
+
+        self.reset(memory);
+    }
+
+    /// Resets the CPU (warm reset, e.g. the reset button being pressed)
+    /// - P: InterruptDisable is set, all other flags are untouched
+    /// - A, X, Y: untouched
+    /// - S: decremented by 3
+    /// - PC: loaded from reset vector (0xFFFC)
+    /// - no longer halted
+    ///
+    /// The reset will take 7 cpu cycles
+    pub fn reset<M: Memory + ?Sized>(&mut self, memory: &mut M) {
+        self.master_clock += 7 * self.clock_div;
+        self.halted = false;
+        self.delayed_interrupt_disable = None;
+
+        self.set_flag(Flags::InterruptDisable, true);
+        self.reg_s = self.reg_s.wrapping_sub(3);
+        
+        self.reg_pc = memory.cpu_load16(RESET_VECTOR);
+    }
+
+    /// Performs a [`Cpu::reset`], but starts executing at `pc` instead of the address in the reset vector
+    ///
+    /// Used for debugging and tests (e.g. nestest's automated mode starts at 0xC000).
+    /// On a freshly created CPU, this results in the same state as [`Cpu::power_on`]
+    pub fn reset_to<M: Memory + ?Sized>(&mut self, memory: &mut M, pc: u16) {
+        self.reset(memory);
+        self.reg_pc = pc;
+    }
+
+    /// Performs a single CPU Instruction
+    ///
+    /// Does nothing if the CPU is halted. If an NMI was requested through [`Cpu::set_nmi_line`] or the external IRQ line
+    /// is asserted (see [`Cpu::set_irq_line`]), the interrupt sequence is performed first and the first instruction
+    /// of the handler is executed
+    /// # Returns
+    /// The number of cpu cycles the instruction took, including the stall of an OAM DMA it started
+    /// and a preceding interrupt sequence
+    pub fn execute_single_instruction<M: Memory + ?Sized>(&mut self, memory: &mut M) -> u64 {
+        if self.halted {
+            return 0;
+        }
+
+        let start_cycle = self.master_clock / self.clock_div;
+
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.nmi(memory);
+        } else if self.irq_line {
+            self.irq(memory);
+        }
+
+        // the trace reports the first instruction of an interrupt handler after the interrupt sequence
+        let start_pc = self.reg_pc;
+        let trace_cycle = self.master_clock / self.clock_div;
+
+        // cycle 0: load opcode, increment PC
+        let opcode = memory.cpu_load8(self.reg_pc);
+
+        if let Some(hook) = &mut self.trace_hook {
+            hook(&CpuTrace {
+                pc: self.reg_pc,
+                opcode,
+                name: cpu_ops::find_op(opcode).map_or("???", |op| op.name),
+                a: self.reg_a,
+                x: self.reg_x,
+                y: self.reg_y,
+                p: self.reg_p,
+                s: self.reg_s,
+                cycle: trace_cycle,
+            });
+        }
+
+        if let Some(hook) = &mut self.pre_step_hook {
+            hook(start_pc, opcode);
+        }
+
+        self.reg_pc = self.reg_pc.wrapping_add(1);
+        self.master_clock += self.clock_div;
+
+        if self.opcode_profiling {
+            self.opcode_counts[opcode as usize] += 1;
+        }
+
+        if self.coverage_tracking {
+            self.coverage[start_pc as usize / 64] |= 1 << (start_pc % 64);
+        }
+
+        self.delayed_interrupt_disable = None;
+        self.execute_op(opcode, memory);
+
+        if let Some(hook) = &mut self.infinite_loop_hook {
+            if self.reg_pc == start_pc {
+                self.self_jump_count += 1;
+                if self.self_jump_count == self.infinite_loop_threshold {
+                    hook(start_pc);
+                }
+            } else {
+                self.self_jump_count = 0;
+            }
+        }
+
+        if memory.take_dma_request() {
+            // OAM DMA takes 513 cycles plus one alignment cycle if it starts on an odd cycle
+            let stall = if self.cycles() % 2 == 1 { 514 } else { 513 };
+            self.master_clock += stall * self.clock_div;
+        }
+
+        self.master_clock / self.clock_div - start_cycle
+    }
+
+    /// Executes whole instructions until at least `cycles` cpu cycles have passed
+    ///
+    /// Stops early if the CPU gets halted
+    /// # Returns
+    /// The number of cpu cycles that were actually executed,
+    /// which may exceed `cycles` by up to one instruction
+    pub fn run_cycles<M: Memory + ?Sized>(&mut self, memory: &mut M, cycles: u64) -> u64 {
+        let mut executed = 0;
+
+        while executed < cycles && !self.halted {
+            executed += self.execute_single_instruction(memory);
+        }
+
+        executed
+    }
+
+    /// Executes `count` whole instructions without doing anything else in between
+    ///
+    /// Stops early if the CPU gets halted
+    /// # Returns
+    /// The number of cpu cycles that were executed
+    pub fn run_instructions<M: Memory + ?Sized>(&mut self, memory: &mut M, count: u64) -> u64 {
+        let mut executed = 0;
+
+        for _ in 0..count {
+            if self.halted {
+                break;
+            }
+            executed += self.execute_single_instruction(memory);
+        }
+
+        executed
+    }
+
+    /// Writes the registers and timing state into a save state
+    ///
+    /// The region and debugging state (trace hook, breakpoints, stack guard) are not part of the save state
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.reg_a);
+        w.write_u8(self.reg_x);
+        w.write_u8(self.reg_y);
+        w.write_u16(self.reg_pc);
+        w.write_u8(self.reg_s);
+        w.write_u8(self.reg_p);
+        w.write_u64(self.master_clock);
+        w.write_bool(self.halted);
+        w.write_u8(match self.delayed_interrupt_disable {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        });
+        w.write_bool(self.irq_line);
+        w.write_bool(self.nmi_line);
+        w.write_bool(self.nmi_pending);
+    }
+
+    /// Restores the state written by [`Cpu::save_state`]
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.reg_a = r.read_u8()?;
+        self.reg_x = r.read_u8()?;
+        self.reg_y = r.read_u8()?;
+        self.reg_pc = r.read_u16()?;
+        self.reg_s = r.read_u8()?;
+        self.reg_p = Self::status_from_stack(r.read_u8()?);
+        self.master_clock = r.read_u64()?;
+        self.halted = r.read_bool()?;
+        self.delayed_interrupt_disable = match r.read_u8()? {
+            1 => Some(false),
+            2 => Some(true),
+            _ => None,
+        };
+        self.irq_line = r.read_bool()?;
+        self.nmi_line = r.read_bool()?;
+        self.nmi_pending = r.read_bool()?;
+        Ok(())
+    }
+
+    /// Adds a breakpoint at `addr`, see [`Cpu::run_until_breakpoint`]
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Removes the breakpoint at `addr`, does nothing if there is none
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    /// Executes instructions until PC reaches a breakpoint or an instruction hits a watchpoint
+    /// (see [`Memory::take_watchpoint_hit`])
+    ///
+    /// At least one instruction is executed, so execution can be resumed from a breakpoint.
+    /// Watchpoints are checked after each instruction, the instruction that hit one is always completed.
+    /// Stops early if the CPU gets halted, runs forever if nothing is ever hit
+    /// # Returns
+    /// Why execution stopped. A [`StopReason`] is returned instead of the bare breakpoint address
+    /// because watchpoints and a halted CPU also end the loop, the address of a breakpoint hit is
+    /// carried by [`StopReason::Breakpoint`]
+    pub fn run_until_breakpoint<M: Memory + ?Sized>(&mut self, memory: &mut M) -> StopReason {
+        // discard hits from accesses outside of this loop
+        memory.take_watchpoint_hit();
+
+        while !self.halted {
+            self.execute_single_instruction(memory);
+
+            if let Some(hit) = memory.take_watchpoint_hit() {
+                return StopReason::Watchpoint(hit);
+            }
+            if self.breakpoints.contains(&self.reg_pc) {
+                return StopReason::Breakpoint(self.reg_pc);
+            }
+        }
+
+        StopReason::Halted
+    }
+
+    /// Executes the instruction at PC, running JSR subroutines to completion
+    ///
+    /// If the instruction is a JSR, instructions are executed until the subroutine has returned to the
+    /// instruction after the JSR. Recursive calls returning to the same address are skipped by comparing
+    /// the stack pointer. Inside the subroutine, breakpoints and watchpoints stop execution like in
+    /// [`Cpu::run_until_breakpoint`], so a subroutine that never returns does not hang the caller.
+    /// Stops early if the CPU gets halted
+    /// # Returns
+    /// [`StopReason::Stepped`] if the instruction (and its subroutine) completed, otherwise why execution stopped early
+    pub fn step_over<M: Memory + ?Sized>(&mut self, memory: &mut M) -> StopReason {
+        // discard hits from accesses outside of this step
+        memory.take_watchpoint_hit();
+
+        // JSR abs
+        let is_jsr = memory.cpu_peek8(self.reg_pc) == 0x20;
+        let ret_addr = self.reg_pc.wrapping_add(3);
+        let sp = self.reg_s;
+
+        loop {
+            self.execute_single_instruction(memory);
+
+            if let Some(hit) = memory.take_watchpoint_hit() {
+                return StopReason::Watchpoint(hit);
+            }
+            if self.halted {
+                return StopReason::Halted;
+            }
+            // returned once PC is behind the JSR with the return address pulled off the stack again,
+            // the depth is compared with wrapping arithmetic because S may wrap around inside the subroutine
+            if !is_jsr || (self.reg_pc == ret_addr && (self.reg_s.wrapping_sub(sp) as i8) >= 0) {
+                return StopReason::Stepped;
+            }
+            if self.breakpoints.contains(&self.reg_pc) {
+                return StopReason::Breakpoint(self.reg_pc);
+            }
+        }
+    }
+
+    /// Enables or disables the stack guard (disabled by default)
+    ///
+    /// The hardware lets S wrap around silently, which is usually a bug in the running program.
+    /// While enabled, the first wrap around is recorded and can be retrieved with [`Cpu::take_stack_fault`]
+    pub fn set_stack_guard(&mut self, enabled: bool) {
+        self.stack_guard = enabled;
+    }
+
+    /// Returns the first stack fault recorded by the stack guard since the last call and resets it
+    pub fn take_stack_fault(&mut self) -> Option<StackFault> {
+        self.stack_fault.take()
+    }
+
+    /// Starts counting how often each opcode is executed, see [`Cpu::opcode_counts`]
+    pub fn enable_opcode_profiling(&mut self) {
+        self.opcode_profiling = true;
+    }
+
+    /// Stops counting executed opcodes, the counts are kept
+    pub fn disable_opcode_profiling(&mut self) {
+        self.opcode_profiling = false;
+    }
+
+    /// Number of times each opcode was executed while profiling was enabled, indexed by the opcode byte
+    pub fn opcode_counts(&self) -> &[u64; 256] {
+        &self.opcode_counts
+    }
+
+    /// Sets the constant the unstable XAA instruction (0x8B) ORs into A before ANDing it with X
+    /// and the operand.
+    ///
+    /// The value depends on the individual chip and even its temperature, real hardware commonly shows
+    /// 0x00, 0xEE or 0xFF. Defaults to [`DEFAULT_XAA_MAGIC`]
+    pub fn set_xaa_magic(&mut self, magic: u8) {
+        self.xaa_magic = magic;
+    }
+
+    /// Starts recording the address of every executed opcode, see [`Cpu::coverage`]
+    pub fn enable_coverage(&mut self) {
+        self.coverage_tracking = true;
+    }
+
+    /// Stops recording opcode addresses, the coverage collected so far is kept
+    pub fn disable_coverage(&mut self) {
+        self.coverage_tracking = false;
+    }
+
+    /// Bitmap of the addresses opcodes were fetched from while coverage tracking was enabled
+    ///
+    /// Address `addr` is covered if bit `addr % 64` of word `addr / 64` is set,
+    /// see [`Cpu::is_covered`]
+    pub fn coverage(&self) -> &[u64; 1024] {
+        &self.coverage
+    }
+
+    /// Returns whether an opcode was fetched from `addr` while coverage tracking was enabled
+    pub fn is_covered(&self, addr: u16) -> bool {
+        self.coverage[addr as usize / 64] & (1 << (addr % 64)) != 0
+    }
+
+    /// Records `fault` if the stack guard is enabled and no earlier fault is pending
+    fn report_stack_fault(&mut self, fault: StackFault) {
+        if self.stack_guard && self.stack_fault.is_none() {
+            self.stack_fault = Some(fault);
+        }
+    }
+
+    /// Services a Non-Maskable Interrupt
+    /// - pushes PC and P (bit 5 set, B flag cleared) onto the stack
+    /// - sets InterruptDisable
+    /// - PC: loaded from NMI vector (0xFFFA)
+    ///
+    /// The interrupt sequence will take 7 cpu cycles
+    pub fn nmi<M: Memory + ?Sized>(&mut self, memory: &mut M) {
+        self.interrupt(NMI_VECTOR, memory);
+    }
+
+    /// Services a maskable Interrupt Request
+    ///
+    /// Does nothing if InterruptDisable is set, otherwise
+    /// - pushes PC and P (bit 5 set, B flag cleared) onto the stack
+    /// - sets InterruptDisable
+    /// - PC: loaded from IRQ vector (0xFFFE)
+    ///
+    /// The interrupt sequence will take 7 cpu cycles
+    ///
+    /// The 6502 polls for interrupts before CLI, SEI and PLP update InterruptDisable,
+    /// so directly after one of these instructions the previous value of the flag is used
+    /// (e.g. `SEI` still lets one pending IRQ through, `CLI` delays it by one instruction)
+    /// # Returns
+    /// Whether the interrupt was taken
+    pub fn irq<M: Memory + ?Sized>(&mut self, memory: &mut M) -> bool {
+        let disabled = self.delayed_interrupt_disable.unwrap_or_else(|| self.get_flag(Flags::InterruptDisable));
+        if disabled {
+            return false;
+        }
+
+        self.interrupt(IRQ_VECTOR, memory);
+        true
+    }
+
+    /// Sets the level of the external IRQ line
+    ///
+    /// The line is level triggered: while it is asserted, [`Cpu::execute_single_instruction`] performs
+    /// the IRQ sequence (see [`Cpu::irq`]) before every instruction that runs with InterruptDisable clear.
+    /// The IRQ sources of the console (mapper, APU) are serviced by the [`System`](crate::system::System) instead,
+    /// this line is meant for custom hardware
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    /// Sets the level of the external NMI line
+    ///
+    /// The line is edge triggered: only a transition from released to asserted requests an NMI,
+    /// which [`Cpu::execute_single_instruction`] services before the next instruction.
+    /// Keeping the line asserted does not trigger further NMIs, it has to be released first
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    /// Performs the hardware interrupt sequence shared by NMI and IRQ,
+    /// loading PC from the vector at `vector`
+    ///
+    /// Unlike BRK, the pushed P has the B flag (bit 4) cleared
+    fn interrupt<M: Memory + ?Sized>(&mut self, vector: u16, memory: &mut M) {
+        // cycle 0 + 1: read next instruction byte twice and throw it away
+        memory.cpu_load8(self.reg_pc);
+        self.master_clock += self.clock_div;
+        memory.cpu_load8(self.reg_pc);
+        self.master_clock += self.clock_div;
+
+        self.push16(self.reg_pc, memory);
+        self.push(self.reg_p, memory);
+
+        self.set_flag(Flags::InterruptDisable, true);
+        self.delayed_interrupt_disable = None;
+
+        // cycle 5 + 6: load vector
+        self.reg_pc = memory.cpu_load16(vector);
+        self.master_clock += 2 * self.clock_div;
+    }
+
+    /// Instruction that is executed when an unofficial opcode is encountered
+    pub(crate) fn op_invalid<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        self.op_nop(addr_mode, memory)
+    }
+
+    /// Converts a P value pulled from the stack into the internal P register,
+    /// the B flag only exists in the pushed copy and bit 5 always reads as set
+    fn status_from_stack(val: u8) -> u8 {
+        (val & !(Flags::Break as u8)) | Flags::Unused as u8
+    }
+
+    /// Sets the given flag to `value`.
+    /// See [`Flags`]
+    fn set_flag(&mut self, flag: Flags, value: bool) {
+        if value {
+            self.reg_p |= flag as u8;
+        } else {
+            self.reg_p &= !(flag as u8);
+        }
+    }
+    /// Gets the value of the given flag.
+    /// See [`Flags`]
+    fn get_flag(&self, flag: Flags) -> bool {
+        (self.reg_p & flag as u8) != 0
+    }
+
+    /// Returns the operand address of a reading instruction,
+    /// indexed modes only take an extra cycle on a page crossing
+    fn get_read_operand_addr<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u16 {
+        match addr_mode {
+            AddressingMode::AbsoluteX => {
+                let base_addr = self.load_absolute_base(memory);
+                self.index_for_read(base_addr, self.reg_x, memory)
+            }
+            AddressingMode::AbsoluteY => {
+                let base_addr = self.load_absolute_base(memory);
+                self.index_for_read(base_addr, self.reg_y, memory)
+            }
+            AddressingMode::IndirectIndexed => {
+                let base_addr = self.load_indirect_base(memory);
+                self.index_for_read(base_addr, self.reg_y, memory)
+            }
+            _ => self.get_unindexed_operand_addr(addr_mode, memory),
+        }
+    }
+
+    /// Returns the operand address of a writing or read-modify-write instruction,
+    /// indexed modes always take the extra cycle
+    fn get_write_operand_addr<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u16 {
+        match addr_mode {
+            AddressingMode::AbsoluteX => {
+                let base_addr = self.load_absolute_base(memory);
+                self.index_for_write(base_addr, self.reg_x, memory)
+            }
+            AddressingMode::AbsoluteY => {
+                let base_addr = self.load_absolute_base(memory);
+                self.index_for_write(base_addr, self.reg_y, memory)
+            }
+            AddressingMode::IndirectIndexed => {
+                let base_addr = self.load_indirect_base(memory);
+                self.index_for_write(base_addr, self.reg_y, memory)
+            }
+            _ => self.get_unindexed_operand_addr(addr_mode, memory),
+        }
+    }
+
+    /// Loads the operand of a branch instruction
+    fn fetch_relative_operand<M: Memory + ?Sized>(&mut self, memory: &mut M) -> u8 {
+        // cycle 1: read the signed branch offset
+        let op = memory.cpu_load8(self.reg_pc);
+        self.reg_pc = self.reg_pc.wrapping_add(1);
+        self.master_clock += self.clock_div;
+        op
+    }
+
+    /// Loads the two byte base address of [`AddressingMode::AbsoluteX`] and [`AddressingMode::AbsoluteY`]
+    fn load_absolute_base<M: Memory + ?Sized>(&mut self, memory: &mut M) -> u16 {
+        // cycle 1: load low addr byte
+        let mut base_addr = memory.cpu_load8(self.reg_pc) as u16;
+        self.reg_pc = self.reg_pc.wrapping_add(1);
+        self.master_clock += self.clock_div;
+
+        // cycle 2: load high addr byte
+        base_addr |= (memory.cpu_load8(self.reg_pc) as u16) << 8;
+        self.reg_pc = self.reg_pc.wrapping_add(1);
+        self.master_clock += self.clock_div;
+
+        base_addr
+    }
+
+    /// Loads the zero page pointed base address of [`AddressingMode::IndirectIndexed`]
+    fn load_indirect_base<M: Memory + ?Sized>(&mut self, memory: &mut M) -> u16 {
+        // cycle 1: load ptr
+        let ptr = memory.cpu_load8(self.reg_pc);
+        self.reg_pc = self.reg_pc.wrapping_add(1);
+        self.master_clock += self.clock_div;
+
+        // cycle 2: load addr low
+        let mut base_addr = memory.cpu_load8(ptr as u16) as u16;
+        self.master_clock += self.clock_div;
+
+        // cycle 3: load addr high
+        base_addr |= (memory.cpu_load8(ptr.wrapping_add(1) as u16) as u16) << 8;
+        self.master_clock += self.clock_div;
+
+        base_addr
+    }
+
+    /// Adds the index for a reading instruction,
+    /// the unfixed effective address is only read when a page is crossed
+    fn index_for_read<M: Memory + ?Sized>(&mut self, base_addr: u16, index: u8, memory: &mut M) -> u16 {
+        let real_addr = base_addr.wrapping_add(index as u16);
+
+        if (real_addr & 0xFF00) != (base_addr & 0xFF00) {
+            memory.cpu_load8((base_addr & 0xFF00) | (real_addr & 0x00FF));
+            self.master_clock += self.clock_div;
+        }
+
+        real_addr
+    }
+
+    /// Adds the index for a writing or read-modify-write instruction,
+    /// which always reads the unfixed effective address once without using the value
+    fn index_for_write<M: Memory + ?Sized>(&mut self, base_addr: u16, index: u8, memory: &mut M) -> u16 {
+        let real_addr = base_addr.wrapping_add(index as u16);
+
+        memory.cpu_load8((base_addr & 0xFF00) | (real_addr & 0x00FF));
+        self.master_clock += self.clock_div;
+
+        real_addr
+    }
+
+    /// Returns the operand address for the [`AddressingModes`](AddressingMode) whose timing
+    /// does not depend on whether the instruction reads or writes, performing all dummy reads on the way
+    ///
+    /// Only called through [`Cpu::get_read_operand_addr`] and [`Cpu::get_write_operand_addr`]
+    fn get_unindexed_operand_addr<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u16 {
+        match addr_mode {
+            AddressingMode::Implicit => {
+                // cycle 1: read next instruction byte and throw it away
+                memory.cpu_load8(self.reg_pc);
+                self.master_clock += self.clock_div;
+                0
+            }
+            AddressingMode::ZeroPage => {
+                // cycle 1: load immediate 1 byte address
+                let arg = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+                arg as u16
+            }
+            AddressingMode::ZeroPageX => {
+                // cycle 1: load immediate 1 byte address
+                let mut arg = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                // cycle 2: dummy read from unindexed address, add X to address
+                memory.cpu_load8(arg as u16);
+                self.master_clock += self.clock_div;
+                // add x
+                arg = arg.wrapping_add(self.reg_x);
+                arg as u16
+            }
+            AddressingMode::ZeroPageY => {
+                // cycle 1: load immediate 1 byte address
+                let mut arg = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                // cycle 2: dummy read from unindexed address, add Y to address
+                memory.cpu_load8(arg as u16);
+                self.master_clock += self.clock_div;
+                // add y
+                arg = arg.wrapping_add(self.reg_y);
+                arg as u16
+            }
+            AddressingMode::Absolute => {
+                // cycle 1: load low address byte
+                let addr_low = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                // cycle 2: load high address byte
+                let addr_high = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                ((addr_high as u16) << 8) | (addr_low as u16)
+            }
+            AddressingMode::Immediate | AddressingMode::Relative => {
+                // cycle 1: read immediate operand
+                let addr = self.reg_pc;
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                // note: no clock increment because whichever instruction uses this function
+                // will load the value on its own
+                //self.master_clock += self.clock_div;
+
+                addr
+            }
+            AddressingMode::Indirect => {
+                // cycle 1: load ptr low
+                let ptr_low = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                // cycle 2: load ptr high
+                let ptr_high = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                // cycle 3: load addr low
+                let addr_low = memory.cpu_load8(((ptr_high as u16) << 8) | (ptr_low as u16));
+                self.master_clock += self.clock_div;
+
+                // cycle 4: load addr high
+                // note: if ptr_low is 0xFF, no page crossing will be handled
+                let addr_high = memory.cpu_load8(((ptr_high as u16) << 8) | (ptr_low.wrapping_add(1) as u16));
+                self.master_clock += self.clock_div;
+                
+                ((addr_high as u16) << 8) | (addr_low as u16)
+            }
+            AddressingMode::IndexedIndirect => {
+                // cycle 1: load ptr
+                let mut ptr = memory.cpu_load8(self.reg_pc);
+                self.reg_pc = self.reg_pc.wrapping_add(1);
+                self.master_clock += self.clock_div;
+
+                // cycle 2: dummy read address, add X
+                memory.cpu_load8(ptr as u16);
+                ptr = ptr.wrapping_add(self.reg_x);
+                self.master_clock += self.clock_div;
+
+                // cycle 3: load addr low
+                let addr_low = memory.cpu_load8(ptr as u16);
+                self.master_clock += self.clock_div;
+
+                // cycle 4: load addr high
+                // note: no page crossing will be handled
+                let addr_high = memory.cpu_load8(ptr.wrapping_add(1) as u16);
+                self.master_clock += self.clock_div;
+
+                ((addr_high as u16) << 8) | (addr_low as u16)
+            }
+            AddressingMode::AbsoluteX | AddressingMode::AbsoluteY | AddressingMode::IndirectIndexed => {
+                unreachable!("indexed addressing modes are resolved by the read and write specific functions")
+            }
+        }
+    }
+
+    pub(crate) fn op_adc<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.adc_value(op);
+
+        0
+    }
+
+    /// Performs the ADC arithmetic on `op`, in packed BCD if decimal mode is enabled
+    ///
+    /// Shared by ADC and the unofficial RRA
+    fn adc_value(&mut self, op: u8) {
+        #[cfg(feature = "decimal")]
+        if self.get_flag(Flags::Decimal) {
+            self.decimal_add(op);
+            return;
+        }
+
+        self.add_with_carry(op);
+    }
+
+    /// Performs the SBC arithmetic on `op`, in packed BCD if decimal mode is enabled
+    ///
+    /// Shared by SBC and the unofficial ISC
+    fn sbc_value(&mut self, op: u8) {
+        #[cfg(feature = "decimal")]
+        if self.get_flag(Flags::Decimal) {
+            self.decimal_sub(op);
+            return;
+        }
+
+        self.add_with_carry(!op);
+    }
+
+    /// Adds `op` and the carry flag to A, setting C, Z, N and V accordingly.
+    ///
+    /// SBC is implemented by passing the inverted operand
+    fn add_with_carry(&mut self, op: u8) {
+        let carry_in: u16 = if self.get_flag(Flags::Carry) { 1 } else { 0 };
+
+        let res = (op as u16).wrapping_add(self.reg_a as u16).wrapping_add(carry_in);
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        let overflow = (!(self.reg_a ^ op)) & (self.reg_a ^ (res & 0xFF) as u8) & 0x80;
+        self.set_flag(Flags::Overflow, overflow != 0);
+
+        self.reg_a = (res & 0xFF) as u8;
+    }
+
+    /// Adds `op` and the carry flag to A in packed BCD, like the NMOS 6502 does in decimal mode
+    ///
+    /// C is set on a decimal carry, N and V are taken from the result before the high digit is adjusted
+    /// and Z from the binary sum (http://www.6502.org/tutorials/decimal_mode.html)
+    #[cfg(feature = "decimal")]
+    fn decimal_add(&mut self, op: u8) {
+        let a = self.reg_a as u16;
+        let op = op as u16;
+        let carry_in: u16 = if self.get_flag(Flags::Carry) { 1 } else { 0 };
+
+        let mut low = (a & 0x0F) + (op & 0x0F) + carry_in;
+        if low >= 0x0A {
+            low = ((low + 0x06) & 0x0F) + 0x10;
+        }
+
+        let mut res = (a & 0xF0) + (op & 0xF0) + low;
+
+        self.set_flag(Flags::Zero, ((a + op + carry_in) & 0xFF) == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+        self.set_flag(Flags::Overflow, (!(a ^ op) & (a ^ res) & 0x80) != 0);
+
+        if res >= 0xA0 {
+            res += 0x60;
+        }
+
+        self.set_flag(Flags::Carry, res >= 0x100);
+        self.reg_a = (res & 0xFF) as u8;
+    }
+
+    /// Subtracts `op` and the inverted carry flag from A in packed BCD, like the NMOS 6502 does in decimal mode
+    ///
+    /// All flags are set as by the binary subtraction, only A receives the decimal result
+    #[cfg(feature = "decimal")]
+    fn decimal_sub(&mut self, op: u8) {
+        let a = self.reg_a as i16;
+        let op = op as i16;
+        let borrow: i16 = if self.get_flag(Flags::Carry) { 0 } else { 1 };
+
+        self.add_with_carry(!(op as u8));
+
+        let mut low = (a & 0x0F) - (op & 0x0F) - borrow;
+        if low < 0 {
+            low = ((low - 0x06) & 0x0F) - 0x10;
+        }
+
+        let mut res = (a & 0xF0) - (op & 0xF0) + low;
+        if res < 0 {
+            res -= 0x60;
+        }
+
+        self.reg_a = (res & 0xFF) as u8;
+    }
+
+    /// Unofficial: ANDs the operand into A, then shifts A right like LSR
+    pub(crate) fn op_alr<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        let val = self.reg_a & op;
+        let res = val.wrapping_shr(1);
+
+        self.set_flag(Flags::Carry, (val & 0x01) != 0);
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        self.reg_a = res;
+
+        0
+    }
+
+    /// Unofficial: ANDs the operand into A, then copies bit 7 of the result into Carry
+    pub(crate) fn op_anc<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_a &= op;
+
+        self.set_flag(Flags::Carry, (self.reg_a & 0x80) != 0);
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_and<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        let res = self.reg_a & op;
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        self.reg_a = res;
+
+        0
+    }
+
+    /// Unofficial: ANDs the operand into A, then rotates A right like ROR
+    ///
+    /// # Flags
+    /// Z and N are set from the result as usual, but C and V do not reflect the rotation:
+    /// - C: bit 6 of the result
+    /// - V: bit 6 XOR bit 5 of the result
+    pub(crate) fn op_arr<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        let mut res = (self.reg_a & op).wrapping_shr(1);
+        if self.get_flag(Flags::Carry) {
+            res |= 0x80;
+        }
+
+        self.set_flag(Flags::Carry, (res & 0x40) != 0);
+        self.set_flag(Flags::Overflow, ((res >> 6) ^ (res >> 5)) & 0x01 != 0);
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        self.reg_a = res;
+
+        0
+    }
+
+    pub(crate) fn op_asl_a<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        let res = (self.reg_a as u16) << 1;
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        self.reg_a = (res & 0xFF) as u8;
+        0
+    }
+
+    pub(crate) fn op_asl_m<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+
+        // read operand
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        // dummy write value back
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = (op as u16) << 1;
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+        self.set_flag(Flags::Zero, (res & 0xFF) == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        // write result
+        memory.cpu_store8(op_addr, (res & 0xFF) as u8);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    /// Performs a relative branch with `op` as signed 8-Bit Offset
+    /// # Cycles
+    /// - A branch instruction that does not branch takes 2 Cycles
+    /// - If a branch is taken, add one cycle
+    /// - If the branch crosses a page (e.g. 0x01xx -> 0x02xx), add another cycle
+    fn relative_branch<M: Memory + ?Sized>(&mut self, op: u8, memory: &mut M) -> u8 {
+        // on a taken branch, the next instruction is read and discarded
+        memory.cpu_load8(self.reg_pc);
+        self.master_clock += self.clock_div;
+
+        let mut offs = op as u16;
+        // perform sign extension
+        if (offs & 0x80) != 0 {
+            offs |= 0xFF00;
+        }
+
+        let new_pc = self.reg_pc.wrapping_add(offs);
+
+        if (new_pc & 0xFF00) != (self.reg_pc & 0xFF00) {
+            // on page cross add another dummy read at the unfixed new pc
+            memory.cpu_load8((self.reg_pc & 0xFF00) | (new_pc & 0x00FF));
+            self.master_clock += self.clock_div;
+        }
+
+        self.reg_pc = new_pc;
+        0
+    }
+
+    pub(crate) fn op_bcc<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if !self.get_flag(Flags::Carry) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_bcs<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if self.get_flag(Flags::Carry) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_beq<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if self.get_flag(Flags::Zero) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_bit<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        let res = self.reg_a & op;
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Overflow, (op & 0x40) != 0);
+        self.set_flag(Flags::Negative, (op & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_bmi<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if self.get_flag(Flags::Negative) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_bne<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if !self.get_flag(Flags::Zero) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_bpl<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if !self.get_flag(Flags::Negative) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_brk<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.push16(self.reg_pc, memory);
+        self.push(self.reg_p | Flags::Break as u8, memory);
+
+        self.set_flag(Flags::InterruptDisable, true);
+
+        self.reg_pc = memory.cpu_load16(IRQ_VECTOR);
+        self.master_clock += 2 * self.clock_div;
+        0
+    }
+
+    pub(crate) fn op_bvc<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if !self.get_flag(Flags::Overflow) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_bvs<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        let op = self.fetch_relative_operand(memory);
+
+        if self.get_flag(Flags::Overflow) {
+            self.relative_branch(op, memory)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_clc<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.set_flag(Flags::Carry, false);
+        0
+    }
+
+    pub(crate) fn op_cld<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.set_flag(Flags::Decimal, false);
+        0
+    }
+
+    pub(crate) fn op_cli<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.delayed_interrupt_disable = Some(self.get_flag(Flags::InterruptDisable));
+        self.set_flag(Flags::InterruptDisable, false);
+        0
+    }
+
+    pub(crate) fn op_clv<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.set_flag(Flags::Overflow, false);
+        0
+    }
+
+    pub(crate) fn op_cmp<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.compare(self.reg_a, op);
+
+        0
+    }
+
+    /// Compares `reg` with `op`, setting C, Z and N as if `op` was subtracted from `reg`
+    fn compare(&mut self, reg: u8, op: u8) {
+        self.set_flag(Flags::Carry, reg >= op);
+        self.set_flag(Flags::Zero, reg == op);
+
+        let tmp = (reg as u16).wrapping_sub(op as u16);
+        self.set_flag(Flags::Negative, (tmp & 0x80) != 0);
+    }
+
+    pub(crate) fn op_cpx<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.compare(self.reg_x, op);
+
+        0
+    }
+
+    pub(crate) fn op_cpy<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.compare(self.reg_y, op);
+
+        0
+    }
+
+    /// Unofficial: decrements the operand in memory, then compares A with the result like CMP
+    pub(crate) fn op_dcp<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = op.wrapping_sub(1);
+
+        self.compare(self.reg_a, res);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_dec<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = op.wrapping_sub(1);
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_dex<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_x = self.reg_x.wrapping_sub(1);
+
+        self.set_flag(Flags::Zero, self.reg_x == 0);
+        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_dey<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_y = self.reg_y.wrapping_sub(1);
+
+        self.set_flag(Flags::Zero, self.reg_y == 0);
+        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_eor<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_a ^= op;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_inc<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = op.wrapping_add(1);
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_inx<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+        
+        self.reg_x = self.reg_x.wrapping_add(1);
+
+        self.set_flag(Flags::Zero, self.reg_x == 0);
+        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_iny<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+        
+        self.reg_y = self.reg_y.wrapping_add(1);
+
+        self.set_flag(Flags::Zero, self.reg_y == 0);
+        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
+
+        0
+    }
+
+    /// Unofficial: increments the operand in memory, then subtracts the result from A like SBC
+    pub(crate) fn op_isc<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = op.wrapping_add(1);
+
+        self.sbc_value(res);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    /// Unofficial: locks up the CPU until the next reset
+    pub(crate) fn op_jam<M: Memory + ?Sized>(&mut self, _: AddressingMode, _: &mut M) -> u8 {
+        self.halted = true;
+
+        0
+    }
+
+    pub(crate) fn op_jmp<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+
+        self.reg_pc = op_addr;
+
+        0
+    }
+
+    pub(crate) fn op_jsr<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        // note: no self.get_operand_addr here because this instruction
+        // has an unusual cycle layout that does not match absolute addressing
+        let addr_low = memory.cpu_load8(self.reg_pc);
+        self.reg_pc = self.reg_pc.wrapping_add(1);
+        self.master_clock += self.clock_div;
+
+        // dummy read from stack
+        memory.cpu_load8(0x0100 | self.reg_s as u16);
+        self.master_clock += self.clock_div;
+
+        self.push16(self.reg_pc, memory);
+
+        let addr_high = memory.cpu_load8(self.reg_pc);
+        self.master_clock += self.clock_div;
+
+        self.reg_pc = ((addr_high as u16) << 8) | (addr_low as u16);
+
+        0
+    }
+
+    /// Unofficial: loads the operand into both A and X
+    pub(crate) fn op_lax<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_a = op;
+        self.reg_x = op;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    /// Unofficial: ANDs the operand with S and stores the result into A, X and S
+    pub(crate) fn op_las<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        let res = op & self.reg_s;
+
+        self.reg_a = res;
+        self.reg_x = res;
+        self.reg_s = res;
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_lda<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_a = op;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_ldx<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_x = op;
+
+        self.set_flag(Flags::Zero, self.reg_x == 0);
+        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_ldy<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_y = op;
+
+        self.set_flag(Flags::Zero, self.reg_y == 0);
+        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_lsr_a<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        let res = self.reg_a.wrapping_shr(1);
+
+        self.set_flag(Flags::Carry, (self.reg_a & 0x01) != 0);
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        self.reg_a = res;
+        0
+    }
+
+    pub(crate) fn op_lsr_m<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = op.wrapping_shr(1);
+
+        self.set_flag(Flags::Carry, (op & 0x01) != 0);
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_nop<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        0
+    }
+
+    /// Unofficial: NOP that reads its operand and throws it away,
+    /// taking the same amount of cycles as a load using the same addressing mode
+    pub(crate) fn op_nop_read<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_ora<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_a |= op;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    /// Pushes a byte onto the stack.
+    /// 
+    /// The value is pushed by
+    /// 1. writing `val` to `0x0100 + reg_s`
+    /// 2. decrementing `reg_s`
+    /// 
+    /// # Overflow
+    /// The CPU does not do anything special when `reg_s` overflows,
+    /// meaning the stack will loop around (reported when the stack guard is enabled)
+    fn push<M: Memory + ?Sized>(&mut self, val: u8, memory: &mut M) {
+        let addr = 0x0100 | (self.reg_s as u16);
+        memory.cpu_store8(addr, val);
+        self.master_clock += self.clock_div;
+        if self.reg_s == 0x00 {
+            self.report_stack_fault(StackFault::Overflow);
+        }
+        self.reg_s = self.reg_s.wrapping_sub(1);
+    }
+
+    /// Pulls a byte from the stack and returns it
+    /// 
+    /// The value is pulled by
+    /// 1. incrementing `reg_s`
+    /// 2. reading from `0x0100 + reg_s`
+    /// 
+    /// # Returns
+    /// The byte pulled from the stack
+    /// 
+    /// # Overflow
+    /// The CPU does not do anything special when `reg_s` underflows,
+    /// meaning the stack will loop around (reported when the stack guard is enabled)
+    fn pull<M: Memory + ?Sized>(&mut self, memory: &mut M) -> u8 {
+        if self.reg_s == 0xFF {
+            self.report_stack_fault(StackFault::Underflow);
+        }
+        self.reg_s = self.reg_s.wrapping_add(1);
+
+        let addr = 0x0100 | (self.reg_s as u16);
+        let res = memory.cpu_load8(addr);
+        self.master_clock += self.clock_div;
+
+        res
+    }
+
+    /// Pushes a 16-Bit value onto the stack, high byte first,
+    /// so that it ends up little endian in memory
+    ///
+    /// Takes 2 cpu cycles
+    fn push16<M: Memory + ?Sized>(&mut self, val: u16, memory: &mut M) {
+        self.push((val >> 8) as u8, memory);
+        self.push((val & 0xFF) as u8, memory);
+    }
+
+    /// Pulls a 16-Bit value pushed by [`Cpu::push16`] from the stack, low byte first
+    ///
+    /// Takes 2 cpu cycles
+    fn pull16<M: Memory + ?Sized>(&mut self, memory: &mut M) -> u16 {
+        let low = self.pull(memory);
+        let high = self.pull(memory);
+        ((high as u16) << 8) | (low as u16)
+    }
+
+    pub(crate) fn op_pha<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.push(self.reg_a, memory);
+        0
+    }
+
+    pub(crate) fn op_php<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        let val = self.reg_p | Flags::Break as u8;
+        self.push(val, memory);
+        0
+    }
+
+    pub(crate) fn op_pla<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        memory.cpu_load8(0x0100 | (self.reg_s as u16));
+        self.master_clock += self.clock_div;
+
+        let val = self.pull(memory);
+        self.reg_a = val;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_plp<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        memory.cpu_load8(0x0100 | (self.reg_s as u16));
+        self.master_clock += self.clock_div;
+
+        let val = self.pull(memory);
+        self.delayed_interrupt_disable = Some(self.get_flag(Flags::InterruptDisable));
+        self.reg_p = Self::status_from_stack(val);
+
+        0
+    }
+
+    /// Unofficial: rotates the operand in memory left, then ANDs the result into A
+    pub(crate) fn op_rla<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let mut res = (op as u16) << 1;
+        if self.get_flag(Flags::Carry) {
+            res |= 0x01;
+        }
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+
+        let res = (res & 0xFF) as u8;
+
+        self.reg_a &= res;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_rol_a<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        let mut res = (self.reg_a as u16) << 1;
+        if self.get_flag(Flags::Carry) {
+            res |= 0x01;
+        }
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+
+        self.reg_a = (res & 0xFF) as u8;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_rol_m<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let mut res = (op as u16) << 1;
+        if self.get_flag(Flags::Carry) {
+            res |= 0x01;
+        }
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+
+        let res = (res & 0xFF) as u8;
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_ror_a<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        let mut res = self.reg_a.wrapping_shr(1);
+        if self.get_flag(Flags::Carry) {
+            res |= 0x80;
+        }
+
+        self.set_flag(Flags::Carry, (self.reg_a & 0x01) != 0);
+
+        self.reg_a = res;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_ror_m<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let mut res = op.wrapping_shr(1);
+        if self.get_flag(Flags::Carry) {
+            res |= 0x80;
+        }
+
+        self.set_flag(Flags::Carry, (op & 0x01) != 0);
+
+        self.set_flag(Flags::Zero, res == 0);
+        self.set_flag(Flags::Negative, (res & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    /// Unofficial: rotates the operand in memory right, then adds the result to A like ADC,
+    /// using the carry shifted out by the rotation
+    pub(crate) fn op_rra<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let mut res = op.wrapping_shr(1);
+        if self.get_flag(Flags::Carry) {
+            res |= 0x80;
+        }
+
+        self.set_flag(Flags::Carry, (op & 0x01) != 0);
+
+        self.adc_value(res);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_rti<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        memory.cpu_load8(0x0100 | (self.reg_s as u16));
+        self.master_clock += self.clock_div;
+
+        let p = self.pull(memory);
+        let ret_addr = self.pull16(memory);
+
+        self.reg_p = Self::status_from_stack(p);
+        self.reg_pc = ret_addr;
+
+        0
+    }
+
+    pub(crate) fn op_rts<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        memory.cpu_load8(0x0100 | (self.reg_s as u16));
+        self.master_clock += self.clock_div;
+
+        let ret_addr = self.pull16(memory);
+
+        self.reg_pc = ret_addr.wrapping_add(1);
+
+        memory.cpu_load8(ret_addr);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_sbc<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.sbc_value(op);
+
+        0
+    }
+
+    /// Unofficial: stores (A AND X) minus the operand into X, setting flags like CMP
+    pub(crate) fn op_sbx<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        let val = self.reg_a & self.reg_x;
+
+        self.compare(val, op);
+
+        self.reg_x = val.wrapping_sub(op);
+
+        0
+    }
+
+    pub(crate) fn op_sec<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.set_flag(Flags::Carry, true);
+        0
+    }
+
+    pub(crate) fn op_sed<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.set_flag(Flags::Decimal, true);
+        0
+    }
+
+    pub(crate) fn op_sei<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.delayed_interrupt_disable = Some(self.get_flag(Flags::InterruptDisable));
+        self.set_flag(Flags::InterruptDisable, true);
+        0
+    }
+
+    /// Unofficial, unstable: stores A AND X AND (H + 1), see [`Cpu::unstable_store`]
+    pub(crate) fn op_sha<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        self.unstable_store(addr_mode, self.reg_y, self.reg_a & self.reg_x, memory);
+
+        0
+    }
+
+    /// Unofficial, unstable: stores X AND (H + 1), see [`Cpu::unstable_store`]
+    pub(crate) fn op_shx<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        self.unstable_store(addr_mode, self.reg_y, self.reg_x, memory);
+
+        0
+    }
+
+    /// Unofficial, unstable: stores Y AND (H + 1), see [`Cpu::unstable_store`]
+    pub(crate) fn op_shy<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        self.unstable_store(addr_mode, self.reg_x, self.reg_y, memory);
+
+        0
+    }
+
+    /// Unofficial: shifts the operand in memory left, then ORs the result into A
+    pub(crate) fn op_slo<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = (op as u16) << 1;
+
+        self.set_flag(Flags::Carry, (res & 0x100) != 0);
+
+        let res = (res & 0xFF) as u8;
+
+        self.reg_a |= res;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    /// Unofficial: shifts the operand in memory right, then XORs the result into A
+    pub(crate) fn op_sre<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        memory.cpu_store8(op_addr, op);
+        self.master_clock += self.clock_div;
+
+        let res = op.wrapping_shr(1);
+
+        self.set_flag(Flags::Carry, (op & 0x01) != 0);
+
+        self.reg_a ^= res;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        memory.cpu_store8(op_addr, res);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    /// Performs the store of the unstable SHA/SHX/SHY/TAS instructions
+    ///
+    /// The stored value is `val & (H + 1)`, where H is the high byte of the unindexed base address.
+    /// If adding `index` to the base address crossed a page, the high byte of the target address
+    /// is replaced by the stored value.
+    ///
+    /// This models the behavior most commonly observed on real hardware (and expected by test ROMs),
+    /// other variants (e.g. the AND with H + 1 dropping out during DMA) are not emulated
+    fn unstable_store<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, index: u8, val: u8, memory: &mut M) {
+        let addr = self.get_write_operand_addr(addr_mode, memory);
+        let base_addr = addr.wrapping_sub(index as u16);
+
+        let high = (base_addr >> 8) as u8;
+        let res = val & high.wrapping_add(1);
+
+        let addr = if (addr & 0xFF00) != (base_addr & 0xFF00) {
+            ((res as u16) << 8) | (addr & 0x00FF)
+        } else {
+            addr
+        };
+
+        memory.cpu_store8(addr, res);
+        self.master_clock += self.clock_div;
+    }
+
+    pub(crate) fn op_sta<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        
+        memory.cpu_store8(op_addr, self.reg_a);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_stx<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        
+        memory.cpu_store8(op_addr, self.reg_x);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    pub(crate) fn op_sty<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_write_operand_addr(addr_mode, memory);
+        
+        memory.cpu_store8(op_addr, self.reg_y);
+        self.master_clock += self.clock_div;
+
+        0
+    }
+
+    /// Unofficial, unstable: sets S to A AND X, then stores S AND (H + 1), see [`Cpu::unstable_store`]
+    pub(crate) fn op_tas<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        self.reg_s = self.reg_a & self.reg_x;
+        self.unstable_store(addr_mode, self.reg_y, self.reg_s, memory);
+
+        0
+    }
+
+    pub(crate) fn op_tax<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_x = self.reg_a;
+
+        self.set_flag(Flags::Zero, self.reg_x == 0);
+        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_tay<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_y = self.reg_a;
+
+        self.set_flag(Flags::Zero, self.reg_y == 0);
+        self.set_flag(Flags::Negative, (self.reg_y & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_tsx<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_x = self.reg_s;
+
+        self.set_flag(Flags::Zero, self.reg_x == 0);
+        self.set_flag(Flags::Negative, (self.reg_x & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_txa<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_a = self.reg_x;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    pub(crate) fn op_txs<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_s = self.reg_x;
+
+        0
+    }
+
+    pub(crate) fn op_tya<M: Memory + ?Sized>(&mut self, _: AddressingMode, memory: &mut M) -> u8 {
+        self.get_write_operand_addr(AddressingMode::Implicit, memory);
+
+        self.reg_a = self.reg_y;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+    /// Unofficial, unstable: stores (A OR magic) AND X AND the operand into A, see [`Cpu::set_xaa_magic`]
+    pub(crate) fn op_xaa<M: Memory + ?Sized>(&mut self, addr_mode: AddressingMode, memory: &mut M) -> u8 {
+        let op_addr = self.get_read_operand_addr(addr_mode, memory);
+        let op = memory.cpu_load8(op_addr);
+        self.master_clock += self.clock_div;
+
+        self.reg_a = (self.reg_a | self.xaa_magic) & self.reg_x & op;
+
+        self.set_flag(Flags::Zero, self.reg_a == 0);
+        self.set_flag(Flags::Negative, (self.reg_a & 0x80) != 0);
+
+        0
+    }
+
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Public description of a single CPU instruction encoding, see [`decode`]
+#[derive(Debug, Clone, Copy)]
+pub struct OpInfo {
+    /// Mnemonic of the instruction
+    pub name: &'static str,
+    /// 8-Bit opcode of the instruction
+    pub opcode: u8,
+    /// [`AddressingMode`] of the instruction
+    pub addr_mode: AddressingMode,
+    /// Length of the instruction in bytes, including the opcode byte
+    pub length: u8,
+}
+
+/// Translates an opcode byte into the instruction it encodes without executing it
+/// # Returns
+/// `None` if the opcode is unknown to the emulator
+pub fn decode(opcode: u8) -> Option<OpInfo> {
+    cpu_ops::find_op(opcode).map(|op| OpInfo {
+        name: op.name,
+        opcode: op.opcode,
+        addr_mode: op.addr_mode,
+        length: op.addr_mode.length(),
+    })
+}
+
+/// Disassembles the instruction at `addr`,
+/// memory is only peeked so no read side effects are triggered
+/// # Returns
+/// (text, length)
+/// - `text`: the mnemonic and its formatted operand (e.g. `LDA $0200,X`), `???` for unknown opcodes
+/// - `length`: the length of the instruction in bytes
+pub fn disassemble<M: Memory + ?Sized>(memory: &M, addr: u16) -> (String, u8) {
+    let op = match cpu_ops::find_op(memory.cpu_peek8(addr)) {
+        Some(op) => op,
+        None => return (String::from("???"), 1),
+    };
+
+    let length = op.addr_mode.length();
+
+    // little endian operand of 0-2 bytes
+    let mut arg: u16 = 0;
+    for i in 1..length {
+        arg |= (memory.cpu_peek8(addr.wrapping_add(i as u16)) as u16) << (8 * (i - 1));
+    }
+
+    let text = match op.addr_mode {
+        AddressingMode::Implicit => op.name.to_string(),
+        AddressingMode::Immediate => format!("{} #${:0>2X}", op.name, arg),
+        AddressingMode::ZeroPage => format!("{} ${:0>2X}", op.name, arg),
+        AddressingMode::ZeroPageX => format!("{} ${:0>2X},X", op.name, arg),
+        AddressingMode::ZeroPageY => format!("{} ${:0>2X},Y", op.name, arg),
+        AddressingMode::IndexedIndirect => format!("{} (${:0>2X},X)", op.name, arg),
+        AddressingMode::IndirectIndexed => format!("{} (${:0>2X}),Y", op.name, arg),
+        AddressingMode::Relative => {
+            let mut offs = arg;
+            // perform sign extension
+            if (offs & 0x80) != 0 {
+                offs |= 0xFF00;
+            }
+            let target = addr.wrapping_add(length as u16).wrapping_add(offs);
+            format!("{} ${:0>4X}", op.name, target)
+        }
+        AddressingMode::Absolute => format!("{} ${:0>4X}", op.name, arg),
+        AddressingMode::AbsoluteX => format!("{} ${:0>4X},X", op.name, arg),
+        AddressingMode::AbsoluteY => format!("{} ${:0>4X},Y", op.name, arg),
+        AddressingMode::Indirect => format!("{} (${:0>4X})", op.name, arg),
+    };
+
+    (text, length)
+}
+
+/// Addressing Modes for Cpu Instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode {
+    /// No explicit operand (e.g. INX)
+    Implicit,
+    /// Single byte address (e.g. ADC $7F)
+    ZeroPage,
+    /// Single byte address + x register (e.g. ADC $7F,X),
+    /// wraps around to stay in zero page
+    ZeroPageX,
+    /// Single byte address + y register (e.g. ADC $7F,Y),
+    /// wraps around to stay in zero page
+    ZeroPageY,
+    /// Two byte address (e.g. ADC $5f70)
+    Absolute,
+    /// Two byte address + x register (e.g. ADC $5f70,X)
+    AbsoluteX,
+    /// Two byte address + y register (e.g. ADC $5f70,Y)
+    AbsoluteY,
+    /// Immediate operand (e.g. ADC #$64)
+    Immediate,
+    /// Signed relative offset from the next instruction (e.g. BNE label, where label is in the range +129/-126)
+    Relative,
+    /// Two byte address to memory location holding a two byte address
+    /// (e.g. JMP ($f0f0))
+    Indirect,
+    /// Single byte address + x register point to memory location holding a two byte address,
+    /// first address wraps around to zero page (e.g. ADC ($34,X))
+    IndexedIndirect,
+    /// Single byte address pointing to two byte address, add y register to two byte address
+    /// (e.g. ADC ($f0),Y)
+    IndirectIndexed,
+}
+
+impl AddressingMode {
+    /// Returns the length in bytes of an instruction using this addressing mode,
+    /// including the opcode byte
+    pub const fn length(&self) -> u8 {
+        match self {
+            AddressingMode::Implicit => 1,
+            AddressingMode::ZeroPage
+            | AddressingMode::ZeroPageX
+            | AddressingMode::ZeroPageY
+            | AddressingMode::Immediate
+            | AddressingMode::Relative
+            | AddressingMode::IndexedIndirect
+            | AddressingMode::IndirectIndexed => 2,
+            AddressingMode::Absolute
+            | AddressingMode::AbsoluteX
+            | AddressingMode::AbsoluteY
+            | AddressingMode::Indirect => 3,
+        }
+    }
+}
+
+/// Flags in the P register
+#[derive(Debug)]
+enum Flags {
+    Carry = 0x01,
+    Zero = 0x02,
+    InterruptDisable = 0x04,
+    Decimal = 0x08,
+    /// Only exists in copies of P pushed by BRK and PHP
+    Break = 0x10,
+    /// Not connected, always reads as 1
+    Unused = 0x20,
+    Overflow = 0x40,
+    Negative = 0x80,
+}
@@ -1,211 +1,367 @@
-use crate::{cpu::{AddressingMode, Cpu}, mappers::Mapper};
-
-/// A Function emulating a single CPU instruction
-/// - `addr_mode`: the concrete [`AddressingMode`] the instruction is using (allows for multiple instruction encodings using the same functions)
-/// - `memory`: a [`Memory`] object that can be used to access CPU and PPU memory
-pub(crate) type CpuOpFunc = fn (&mut Cpu, addr_mode: AddressingMode, memory: &mut dyn Mapper) -> u8;
-
-/// Describes a single CPU instruction and its encoding
-#[derive(Clone, Copy)]
-pub(crate) struct CpuOp {
-    /// Mnemonic of the instruction (used for debugging)
-    pub name: &'static str,
-    /// 8-Bit opcode of the instruction, as used by the CPU
-    pub opcode: u8,
-    /// [`AddressingMode`] of the instruction (describes which operands it takes)
-    pub addr_mode: AddressingMode,
-    /// The function that emulates this instruction, see [`CpuOpFunc`]
-    pub func: CpuOpFunc
-}
-
-/// Collection of all *official* CPU instructions
-pub(crate) const CPU_OPS: [CpuOp; 151] = [
-    CpuOp { name: "ADC", opcode: 0x69, addr_mode: AddressingMode::Immediate, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x65, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x75, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x6D, addr_mode: AddressingMode::Absolute, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x7D, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x79, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x61, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_adc },
-    CpuOp { name: "ADC", opcode: 0x71, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_adc },
-
-    CpuOp { name: "AND", opcode: 0x29, addr_mode: AddressingMode::Immediate, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x25, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x35, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x2D, addr_mode: AddressingMode::Absolute, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x3D, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x39, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x21, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_and },
-    CpuOp { name: "AND", opcode: 0x31, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_and },
-
-    CpuOp { name: "ASL", opcode: 0x0A, addr_mode: AddressingMode::Implicit, func: Cpu::op_asl_a },
-    CpuOp { name: "ASL", opcode: 0x06, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_asl_m },
-    CpuOp { name: "ASL", opcode: 0x16, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_asl_m },
-    CpuOp { name: "ASL", opcode: 0x0E, addr_mode: AddressingMode::Absolute, func: Cpu::op_asl_m },
-    CpuOp { name: "ASL", opcode: 0x1E, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_asl_m },
-
-    CpuOp { name: "BCC", opcode: 0x90, addr_mode: AddressingMode::Relative, func: Cpu::op_bcc },
-    CpuOp { name: "BCS", opcode: 0xB0, addr_mode: AddressingMode::Relative, func: Cpu::op_bcs },
-    CpuOp { name: "BEQ", opcode: 0xF0, addr_mode: AddressingMode::Relative, func: Cpu::op_beq },
-
-    CpuOp { name: "BIT", opcode: 0x24, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_bit },
-    CpuOp { name: "BIT", opcode: 0x2C, addr_mode: AddressingMode::Absolute, func: Cpu::op_bit },
-
-    CpuOp { name: "BMI", opcode: 0x30, addr_mode: AddressingMode::Relative, func: Cpu::op_bmi },
-    CpuOp { name: "BNE", opcode: 0xD0, addr_mode: AddressingMode::Relative, func: Cpu::op_bne },
-    CpuOp { name: "BPL", opcode: 0x10, addr_mode: AddressingMode::Relative, func: Cpu::op_bpl },
-
-    CpuOp { name: "BRK", opcode: 0x00, addr_mode: AddressingMode::Implicit, func: Cpu::op_brk },
-
-    CpuOp { name: "BVC", opcode: 0x50, addr_mode: AddressingMode::Relative, func: Cpu::op_bvc },
-    CpuOp { name: "BVS", opcode: 0x70, addr_mode: AddressingMode::Relative, func: Cpu::op_bvs },
-
-    CpuOp { name: "CLC", opcode: 0x18, addr_mode: AddressingMode::Implicit, func: Cpu::op_clc },
-    CpuOp { name: "CLD", opcode: 0xD8, addr_mode: AddressingMode::Implicit, func: Cpu::op_cld },
-    CpuOp { name: "CLI", opcode: 0x58, addr_mode: AddressingMode::Implicit, func: Cpu::op_cli },
-    CpuOp { name: "CLV", opcode: 0xB8, addr_mode: AddressingMode::Implicit, func: Cpu::op_clv },
-
-    CpuOp { name: "CMP", opcode: 0xC9, addr_mode: AddressingMode::Immediate, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xC5, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xD5, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xCD, addr_mode: AddressingMode::Absolute, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xDD, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xD9, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xC1, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_cmp },
-    CpuOp { name: "CMP", opcode: 0xD1, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_cmp },
-
-    CpuOp { name: "CPX", opcode: 0xE0, addr_mode: AddressingMode::Immediate, func: Cpu::op_cpx },
-    CpuOp { name: "CPX", opcode: 0xE4, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_cpx },
-    CpuOp { name: "CPX", opcode: 0xEC, addr_mode: AddressingMode::Absolute, func: Cpu::op_cpx },
-
-    CpuOp { name: "CPY", opcode: 0xC0, addr_mode: AddressingMode::Immediate, func: Cpu::op_cpy },
-    CpuOp { name: "CPY", opcode: 0xC4, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_cpy },
-    CpuOp { name: "CPY", opcode: 0xCC, addr_mode: AddressingMode::Absolute, func: Cpu::op_cpy },
-
-    CpuOp { name: "DEC", opcode: 0xC6, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_dec },
-    CpuOp { name: "DEC", opcode: 0xD6, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_dec },
-    CpuOp { name: "DEC", opcode: 0xCE, addr_mode: AddressingMode::Absolute, func: Cpu::op_dec },
-    CpuOp { name: "DEC", opcode: 0xDE, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_dec },
-
-    CpuOp { name: "DEX", opcode: 0xCA, addr_mode: AddressingMode::Implicit, func: Cpu::op_dex },
-
-    CpuOp { name: "DEY", opcode: 0x88, addr_mode: AddressingMode::Implicit, func: Cpu::op_dey },
-
-    CpuOp { name: "EOR", opcode: 0x49, addr_mode: AddressingMode::Immediate, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x45, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x55, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x4D, addr_mode: AddressingMode::Absolute, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x5D, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x59, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x41, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_eor },
-    CpuOp { name: "EOR", opcode: 0x51, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_eor },
-
-    CpuOp { name: "INC", opcode: 0xE6, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_inc },
-    CpuOp { name: "INC", opcode: 0xF6, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_inc },
-    CpuOp { name: "INC", opcode: 0xEE, addr_mode: AddressingMode::Absolute, func: Cpu::op_inc },
-    CpuOp { name: "INC", opcode: 0xFE, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_inc },
-
-    CpuOp { name: "INX", opcode: 0xE8, addr_mode: AddressingMode::Implicit, func: Cpu::op_inx },
-
-    CpuOp { name: "INY", opcode: 0xC8, addr_mode: AddressingMode::Implicit, func: Cpu::op_iny },
-
-    CpuOp { name: "JMP", opcode: 0x4C, addr_mode: AddressingMode::Absolute, func: Cpu::op_jmp },
-    CpuOp { name: "JMP", opcode: 0x6C, addr_mode: AddressingMode::Indirect, func: Cpu::op_jmp },
-
-    CpuOp { name: "JSR", opcode: 0x20, addr_mode: AddressingMode::Absolute, func: Cpu::op_jsr },
-
-    CpuOp { name: "LDA", opcode: 0xA9, addr_mode: AddressingMode::Immediate, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xA5, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xB5, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xAD, addr_mode: AddressingMode::Absolute, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xBD, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xB9, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xA1, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_lda },
-    CpuOp { name: "LDA", opcode: 0xB1, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_lda },
-
-    CpuOp { name: "LDX", opcode: 0xA2, addr_mode: AddressingMode::Immediate, func: Cpu::op_ldx },
-    CpuOp { name: "LDX", opcode: 0xA6, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_ldx },
-    CpuOp { name: "LDX", opcode: 0xB6, addr_mode: AddressingMode::ZeroPageY, func: Cpu::op_ldx },
-    CpuOp { name: "LDX", opcode: 0xAE, addr_mode: AddressingMode::Absolute, func: Cpu::op_ldx },
-    CpuOp { name: "LDX", opcode: 0xBE, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_ldx },
-
-    CpuOp { name: "LDY", opcode: 0xA0, addr_mode: AddressingMode::Immediate, func: Cpu::op_ldy },
-    CpuOp { name: "LDY", opcode: 0xA4, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_ldy },
-    CpuOp { name: "LDY", opcode: 0xB4, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_ldy },
-    CpuOp { name: "LDY", opcode: 0xAC, addr_mode: AddressingMode::Absolute, func: Cpu::op_ldy },
-    CpuOp { name: "LDY", opcode: 0xBC, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_ldy },
-
-    CpuOp { name: "LSR", opcode: 0x4A, addr_mode: AddressingMode::Implicit, func: Cpu::op_lsr_a },
-    CpuOp { name: "LSR", opcode: 0x46, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_lsr_m },
-    CpuOp { name: "LSR", opcode: 0x56, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_lsr_m },
-    CpuOp { name: "LSR", opcode: 0x4E, addr_mode: AddressingMode::Absolute, func: Cpu::op_lsr_m },
-    CpuOp { name: "LSR", opcode: 0x5E, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_lsr_m },
-
-    CpuOp { name: "NOP", opcode: 0xEA, addr_mode: AddressingMode::Implicit, func: Cpu::op_nop },
-
-    CpuOp { name: "ORA", opcode: 0x09, addr_mode: AddressingMode::Immediate, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x05, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x15, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x0D, addr_mode: AddressingMode::Absolute, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x1D, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x19, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x01, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_ora },
-    CpuOp { name: "ORA", opcode: 0x11, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_ora },
-
-    CpuOp { name: "PHA", opcode: 0x48, addr_mode: AddressingMode::Implicit, func: Cpu::op_pha },
-    CpuOp { name: "PHP", opcode: 0x08, addr_mode: AddressingMode::Implicit, func: Cpu::op_php },
-    CpuOp { name: "PLA", opcode: 0x68, addr_mode: AddressingMode::Implicit, func: Cpu::op_pla },
-    CpuOp { name: "PLP", opcode: 0x28, addr_mode: AddressingMode::Implicit, func: Cpu::op_plp },
-
-    CpuOp { name: "ROL", opcode: 0x2A, addr_mode: AddressingMode::Implicit, func: Cpu::op_rol_a },
-    CpuOp { name: "ROL", opcode: 0x26, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_rol_m },
-    CpuOp { name: "ROL", opcode: 0x36, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_rol_m },
-    CpuOp { name: "ROL", opcode: 0x2E, addr_mode: AddressingMode::Absolute, func: Cpu::op_rol_m },
-    CpuOp { name: "ROL", opcode: 0x3E, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_rol_m },
-
-    CpuOp { name: "ROR", opcode: 0x6A, addr_mode: AddressingMode::Implicit, func: Cpu::op_ror_a },
-    CpuOp { name: "ROR", opcode: 0x66, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_ror_m },
-    CpuOp { name: "ROR", opcode: 0x76, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_ror_m },
-    CpuOp { name: "ROR", opcode: 0x6E, addr_mode: AddressingMode::Absolute, func: Cpu::op_ror_m },
-    CpuOp { name: "ROR", opcode: 0x7E, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_ror_m },
-
-    CpuOp { name: "RTI", opcode: 0x40, addr_mode: AddressingMode::Implicit, func: Cpu::op_rti },
-
-    CpuOp { name: "RTS", opcode: 0x60, addr_mode: AddressingMode::Implicit, func: Cpu::op_rts },
-
-    CpuOp { name: "SBC", opcode: 0xE9, addr_mode: AddressingMode::Immediate, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xE5, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xF5, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xED, addr_mode: AddressingMode::Absolute, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xFD, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xF9, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xE1, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_sbc },
-    CpuOp { name: "SBC", opcode: 0xF1, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_sbc },
-
-    CpuOp { name: "SEC", opcode: 0x38, addr_mode: AddressingMode::Implicit, func: Cpu::op_sec },
-    CpuOp { name: "SED", opcode: 0xF8, addr_mode: AddressingMode::Implicit, func: Cpu::op_sed },
-    CpuOp { name: "SEI", opcode: 0x78, addr_mode: AddressingMode::Implicit, func: Cpu::op_sei },
-
-    CpuOp { name: "STA", opcode: 0x85, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_sta },
-    CpuOp { name: "STA", opcode: 0x95, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_sta },
-    CpuOp { name: "STA", opcode: 0x8D, addr_mode: AddressingMode::Absolute, func: Cpu::op_sta },
-    CpuOp { name: "STA", opcode: 0x9D, addr_mode: AddressingMode::AbsoluteX, func: Cpu::op_sta },
-    CpuOp { name: "STA", opcode: 0x99, addr_mode: AddressingMode::AbsoluteY, func: Cpu::op_sta },
-    CpuOp { name: "STA", opcode: 0x81, addr_mode: AddressingMode::IndexedIndirect, func: Cpu::op_sta },
-    CpuOp { name: "STA", opcode: 0x91, addr_mode: AddressingMode::IndirectIndexed, func: Cpu::op_sta },
-
-    CpuOp { name: "STX", opcode: 0x86, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_stx },
-    CpuOp { name: "STX", opcode: 0x96, addr_mode: AddressingMode::ZeroPageY, func: Cpu::op_stx },
-    CpuOp { name: "STX", opcode: 0x8E, addr_mode: AddressingMode::Absolute, func: Cpu::op_stx },
-
-    CpuOp { name: "STY", opcode: 0x84, addr_mode: AddressingMode::ZeroPage, func: Cpu::op_sty },
-    CpuOp { name: "STY", opcode: 0x94, addr_mode: AddressingMode::ZeroPageX, func: Cpu::op_sty },
-    CpuOp { name: "STY", opcode: 0x8C, addr_mode: AddressingMode::Absolute, func: Cpu::op_sty },
-
-    CpuOp { name: "TAX", opcode: 0xAA, addr_mode: AddressingMode::Implicit, func: Cpu::op_tax },
-    CpuOp { name: "TAY", opcode: 0xA8, addr_mode: AddressingMode::Implicit, func: Cpu::op_tay },
-    CpuOp { name: "TSX", opcode: 0xBA, addr_mode: AddressingMode::Implicit, func: Cpu::op_tsx },
-    CpuOp { name: "TXA", opcode: 0x8A, addr_mode: AddressingMode::Implicit, func: Cpu::op_txa },
-    CpuOp { name: "TXS", opcode: 0x9A, addr_mode: AddressingMode::Implicit, func: Cpu::op_txs },
-    CpuOp { name: "TYA", opcode: 0x98, addr_mode: AddressingMode::Implicit, func: Cpu::op_tya },
-];
+use crate::{cpu::{AddressingMode, Cpu}, memory::Memory};
+
+/// Describes a single CPU instruction and its encoding
+#[derive(Clone, Copy)]
+pub(crate) struct CpuOp {
+    /// Mnemonic of the instruction (used for debugging)
+    pub name: &'static str,
+    /// 8-Bit opcode of the instruction, as used by the CPU
+    pub opcode: u8,
+    /// [`AddressingMode`] of the instruction (describes which operands it takes)
+    pub addr_mode: AddressingMode,
+}
+
+/// Looks up the [`CpuOp`] encoded by `opcode`
+/// # Returns
+/// `None` if the opcode is not part of [`CPU_OPS`]
+pub(crate) fn find_op(opcode: u8) -> Option<&'static CpuOp> {
+    OPS_BY_OPCODE[opcode as usize].as_ref()
+}
+
+/// [`CPU_OPS`] indexed by opcode
+static OPS_BY_OPCODE: [Option<CpuOp>; 256] = index_ops();
+
+const fn index_ops() -> [Option<CpuOp>; 256] {
+    let mut ops = [None; 256];
+    let mut i = 0;
+    while i < CPU_OPS.len() {
+        let op = CPU_OPS[i];
+        assert!(ops[op.opcode as usize].is_none(), "duplicate opcode in CPU_OPS");
+        ops[op.opcode as usize] = Some(op);
+        i += 1;
+    }
+    ops
+}
+
+/// Generates [`CPU_OPS`] and [`Cpu::execute_op`] from a single list of `NAME opcode AddressingMode => function` entries,
+/// so decoding and execution can not get out of sync
+macro_rules! cpu_ops {
+    ($($name:ident $opcode:literal $addr_mode:ident => $func:ident,)*) => {
+        /// Collection of all *official* CPU instructions, followed by the supported *unofficial* ones
+        ///
+        /// Only used for decoding (names and addressing modes), execution is done by [`Cpu::execute_op`]
+        pub(crate) const CPU_OPS: &[CpuOp] = &[
+            $(CpuOp { name: stringify!($name), opcode: $opcode, addr_mode: AddressingMode::$addr_mode },)*
+        ];
+
+        impl Cpu {
+            /// Emulates the instruction encoded by `opcode`, the opcode byte itself has already been fetched
+            ///
+            /// Each instruction function takes the concrete [`AddressingMode`] of the encoding
+            /// (allows for multiple instruction encodings using the same functions).
+            /// The match is spelled out so the compiler can inline the instruction functions
+            pub(crate) fn execute_op<M: Memory + ?Sized>(&mut self, opcode: u8, memory: &mut M) -> u8 {
+                match opcode {
+                    $($opcode => self.$func(AddressingMode::$addr_mode, memory),)*
+                    _ => self.op_invalid(AddressingMode::Implicit, memory),
+                }
+            }
+        }
+    };
+}
+
+cpu_ops! {
+    ADC 0x69 Immediate => op_adc,
+    ADC 0x65 ZeroPage => op_adc,
+    ADC 0x75 ZeroPageX => op_adc,
+    ADC 0x6D Absolute => op_adc,
+    ADC 0x7D AbsoluteX => op_adc,
+    ADC 0x79 AbsoluteY => op_adc,
+    ADC 0x61 IndexedIndirect => op_adc,
+    ADC 0x71 IndirectIndexed => op_adc,
+
+    AND 0x29 Immediate => op_and,
+    AND 0x25 ZeroPage => op_and,
+    AND 0x35 ZeroPageX => op_and,
+    AND 0x2D Absolute => op_and,
+    AND 0x3D AbsoluteX => op_and,
+    AND 0x39 AbsoluteY => op_and,
+    AND 0x21 IndexedIndirect => op_and,
+    AND 0x31 IndirectIndexed => op_and,
+
+    ASL 0x0A Implicit => op_asl_a,
+    ASL 0x06 ZeroPage => op_asl_m,
+    ASL 0x16 ZeroPageX => op_asl_m,
+    ASL 0x0E Absolute => op_asl_m,
+    ASL 0x1E AbsoluteX => op_asl_m,
+
+    BCC 0x90 Relative => op_bcc,
+    BCS 0xB0 Relative => op_bcs,
+    BEQ 0xF0 Relative => op_beq,
+
+    BIT 0x24 ZeroPage => op_bit,
+    BIT 0x2C Absolute => op_bit,
+
+    BMI 0x30 Relative => op_bmi,
+    BNE 0xD0 Relative => op_bne,
+    BPL 0x10 Relative => op_bpl,
+
+    BRK 0x00 Implicit => op_brk,
+
+    BVC 0x50 Relative => op_bvc,
+    BVS 0x70 Relative => op_bvs,
+
+    CLC 0x18 Implicit => op_clc,
+    CLD 0xD8 Implicit => op_cld,
+    CLI 0x58 Implicit => op_cli,
+    CLV 0xB8 Implicit => op_clv,
+
+    CMP 0xC9 Immediate => op_cmp,
+    CMP 0xC5 ZeroPage => op_cmp,
+    CMP 0xD5 ZeroPageX => op_cmp,
+    CMP 0xCD Absolute => op_cmp,
+    CMP 0xDD AbsoluteX => op_cmp,
+    CMP 0xD9 AbsoluteY => op_cmp,
+    CMP 0xC1 IndexedIndirect => op_cmp,
+    CMP 0xD1 IndirectIndexed => op_cmp,
+
+    CPX 0xE0 Immediate => op_cpx,
+    CPX 0xE4 ZeroPage => op_cpx,
+    CPX 0xEC Absolute => op_cpx,
+
+    CPY 0xC0 Immediate => op_cpy,
+    CPY 0xC4 ZeroPage => op_cpy,
+    CPY 0xCC Absolute => op_cpy,
+
+    DEC 0xC6 ZeroPage => op_dec,
+    DEC 0xD6 ZeroPageX => op_dec,
+    DEC 0xCE Absolute => op_dec,
+    DEC 0xDE AbsoluteX => op_dec,
+
+    DEX 0xCA Implicit => op_dex,
+
+    DEY 0x88 Implicit => op_dey,
+
+    EOR 0x49 Immediate => op_eor,
+    EOR 0x45 ZeroPage => op_eor,
+    EOR 0x55 ZeroPageX => op_eor,
+    EOR 0x4D Absolute => op_eor,
+    EOR 0x5D AbsoluteX => op_eor,
+    EOR 0x59 AbsoluteY => op_eor,
+    EOR 0x41 IndexedIndirect => op_eor,
+    EOR 0x51 IndirectIndexed => op_eor,
+
+    INC 0xE6 ZeroPage => op_inc,
+    INC 0xF6 ZeroPageX => op_inc,
+    INC 0xEE Absolute => op_inc,
+    INC 0xFE AbsoluteX => op_inc,
+
+    INX 0xE8 Implicit => op_inx,
+
+    INY 0xC8 Implicit => op_iny,
+
+    JMP 0x4C Absolute => op_jmp,
+    JMP 0x6C Indirect => op_jmp,
+
+    JSR 0x20 Absolute => op_jsr,
+
+    LDA 0xA9 Immediate => op_lda,
+    LDA 0xA5 ZeroPage => op_lda,
+    LDA 0xB5 ZeroPageX => op_lda,
+    LDA 0xAD Absolute => op_lda,
+    LDA 0xBD AbsoluteX => op_lda,
+    LDA 0xB9 AbsoluteY => op_lda,
+    LDA 0xA1 IndexedIndirect => op_lda,
+    LDA 0xB1 IndirectIndexed => op_lda,
+
+    LDX 0xA2 Immediate => op_ldx,
+    LDX 0xA6 ZeroPage => op_ldx,
+    LDX 0xB6 ZeroPageY => op_ldx,
+    LDX 0xAE Absolute => op_ldx,
+    LDX 0xBE AbsoluteY => op_ldx,
+
+    LDY 0xA0 Immediate => op_ldy,
+    LDY 0xA4 ZeroPage => op_ldy,
+    LDY 0xB4 ZeroPageX => op_ldy,
+    LDY 0xAC Absolute => op_ldy,
+    LDY 0xBC AbsoluteX => op_ldy,
+
+    LSR 0x4A Implicit => op_lsr_a,
+    LSR 0x46 ZeroPage => op_lsr_m,
+    LSR 0x56 ZeroPageX => op_lsr_m,
+    LSR 0x4E Absolute => op_lsr_m,
+    LSR 0x5E AbsoluteX => op_lsr_m,
+
+    NOP 0xEA Implicit => op_nop,
+
+    ORA 0x09 Immediate => op_ora,
+    ORA 0x05 ZeroPage => op_ora,
+    ORA 0x15 ZeroPageX => op_ora,
+    ORA 0x0D Absolute => op_ora,
+    ORA 0x1D AbsoluteX => op_ora,
+    ORA 0x19 AbsoluteY => op_ora,
+    ORA 0x01 IndexedIndirect => op_ora,
+    ORA 0x11 IndirectIndexed => op_ora,
+
+    PHA 0x48 Implicit => op_pha,
+    PHP 0x08 Implicit => op_php,
+    PLA 0x68 Implicit => op_pla,
+    PLP 0x28 Implicit => op_plp,
+
+    ROL 0x2A Implicit => op_rol_a,
+    ROL 0x26 ZeroPage => op_rol_m,
+    ROL 0x36 ZeroPageX => op_rol_m,
+    ROL 0x2E Absolute => op_rol_m,
+    ROL 0x3E AbsoluteX => op_rol_m,
+
+    ROR 0x6A Implicit => op_ror_a,
+    ROR 0x66 ZeroPage => op_ror_m,
+    ROR 0x76 ZeroPageX => op_ror_m,
+    ROR 0x6E Absolute => op_ror_m,
+    ROR 0x7E AbsoluteX => op_ror_m,
+
+    RTI 0x40 Implicit => op_rti,
+
+    RTS 0x60 Implicit => op_rts,
+
+    SBC 0xE9 Immediate => op_sbc,
+    SBC 0xE5 ZeroPage => op_sbc,
+    SBC 0xF5 ZeroPageX => op_sbc,
+    SBC 0xED Absolute => op_sbc,
+    SBC 0xFD AbsoluteX => op_sbc,
+    SBC 0xF9 AbsoluteY => op_sbc,
+    SBC 0xE1 IndexedIndirect => op_sbc,
+    SBC 0xF1 IndirectIndexed => op_sbc,
+
+    SEC 0x38 Implicit => op_sec,
+    SED 0xF8 Implicit => op_sed,
+    SEI 0x78 Implicit => op_sei,
+
+    STA 0x85 ZeroPage => op_sta,
+    STA 0x95 ZeroPageX => op_sta,
+    STA 0x8D Absolute => op_sta,
+    STA 0x9D AbsoluteX => op_sta,
+    STA 0x99 AbsoluteY => op_sta,
+    STA 0x81 IndexedIndirect => op_sta,
+    STA 0x91 IndirectIndexed => op_sta,
+
+    STX 0x86 ZeroPage => op_stx,
+    STX 0x96 ZeroPageY => op_stx,
+    STX 0x8E Absolute => op_stx,
+
+    STY 0x84 ZeroPage => op_sty,
+    STY 0x94 ZeroPageX => op_sty,
+    STY 0x8C Absolute => op_sty,
+
+    TAX 0xAA Implicit => op_tax,
+    TAY 0xA8 Implicit => op_tay,
+    TSX 0xBA Implicit => op_tsx,
+    TXA 0x8A Implicit => op_txa,
+    TXS 0x9A Implicit => op_txs,
+    TYA 0x98 Implicit => op_tya,
+
+    // unofficial instructions
+
+    ALR 0x4B Immediate => op_alr,
+
+    ANC 0x0B Immediate => op_anc,
+    ANC 0x2B Immediate => op_anc,
+
+    ARR 0x6B Immediate => op_arr,
+
+    DCP 0xC7 ZeroPage => op_dcp,
+    DCP 0xD7 ZeroPageX => op_dcp,
+    DCP 0xCF Absolute => op_dcp,
+    DCP 0xDF AbsoluteX => op_dcp,
+    DCP 0xDB AbsoluteY => op_dcp,
+    DCP 0xC3 IndexedIndirect => op_dcp,
+    DCP 0xD3 IndirectIndexed => op_dcp,
+
+    ISC 0xE7 ZeroPage => op_isc,
+    ISC 0xF7 ZeroPageX => op_isc,
+    ISC 0xEF Absolute => op_isc,
+    ISC 0xFF AbsoluteX => op_isc,
+    ISC 0xFB AbsoluteY => op_isc,
+    ISC 0xE3 IndexedIndirect => op_isc,
+    ISC 0xF3 IndirectIndexed => op_isc,
+
+    JAM 0x02 Implicit => op_jam,
+    JAM 0x12 Implicit => op_jam,
+    JAM 0x22 Implicit => op_jam,
+    JAM 0x32 Implicit => op_jam,
+    JAM 0x42 Implicit => op_jam,
+    JAM 0x52 Implicit => op_jam,
+    JAM 0x62 Implicit => op_jam,
+    JAM 0x72 Implicit => op_jam,
+    JAM 0x92 Implicit => op_jam,
+    JAM 0xB2 Implicit => op_jam,
+    JAM 0xD2 Implicit => op_jam,
+    JAM 0xF2 Implicit => op_jam,
+
+    LAS 0xBB AbsoluteY => op_las,
+
+    LAX 0xA7 ZeroPage => op_lax,
+    LAX 0xB7 ZeroPageY => op_lax,
+    LAX 0xAF Absolute => op_lax,
+    LAX 0xBF AbsoluteY => op_lax,
+    LAX 0xA3 IndexedIndirect => op_lax,
+    LAX 0xB3 IndirectIndexed => op_lax,
+
+    NOP 0x80 Immediate => op_nop_read,
+    NOP 0x82 Immediate => op_nop_read,
+    NOP 0x89 Immediate => op_nop_read,
+    NOP 0xC2 Immediate => op_nop_read,
+    NOP 0xE2 Immediate => op_nop_read,
+    NOP 0x04 ZeroPage => op_nop_read,
+    NOP 0x44 ZeroPage => op_nop_read,
+    NOP 0x64 ZeroPage => op_nop_read,
+    NOP 0x14 ZeroPageX => op_nop_read,
+    NOP 0x34 ZeroPageX => op_nop_read,
+    NOP 0x54 ZeroPageX => op_nop_read,
+    NOP 0x74 ZeroPageX => op_nop_read,
+    NOP 0xD4 ZeroPageX => op_nop_read,
+    NOP 0xF4 ZeroPageX => op_nop_read,
+    NOP 0x0C Absolute => op_nop_read,
+    NOP 0x1C AbsoluteX => op_nop_read,
+    NOP 0x3C AbsoluteX => op_nop_read,
+    NOP 0x5C AbsoluteX => op_nop_read,
+    NOP 0x7C AbsoluteX => op_nop_read,
+    NOP 0xDC AbsoluteX => op_nop_read,
+    NOP 0xFC AbsoluteX => op_nop_read,
+
+    RLA 0x27 ZeroPage => op_rla,
+    RLA 0x37 ZeroPageX => op_rla,
+    RLA 0x2F Absolute => op_rla,
+    RLA 0x3F AbsoluteX => op_rla,
+    RLA 0x3B AbsoluteY => op_rla,
+    RLA 0x23 IndexedIndirect => op_rla,
+    RLA 0x33 IndirectIndexed => op_rla,
+
+    RRA 0x67 ZeroPage => op_rra,
+    RRA 0x77 ZeroPageX => op_rra,
+    RRA 0x6F Absolute => op_rra,
+    RRA 0x7F AbsoluteX => op_rra,
+    RRA 0x7B AbsoluteY => op_rra,
+    RRA 0x63 IndexedIndirect => op_rra,
+    RRA 0x73 IndirectIndexed => op_rra,
+
+    SBC 0xEB Immediate => op_sbc,
+
+    SBX 0xCB Immediate => op_sbx,
+
+    SHA 0x9F AbsoluteY => op_sha,
+    SHA 0x93 IndirectIndexed => op_sha,
+
+    SHX 0x9E AbsoluteY => op_shx,
+    SHY 0x9C AbsoluteX => op_shy,
+
+    SLO 0x07 ZeroPage => op_slo,
+    SLO 0x17 ZeroPageX => op_slo,
+    SLO 0x0F Absolute => op_slo,
+    SLO 0x1F AbsoluteX => op_slo,
+    SLO 0x1B AbsoluteY => op_slo,
+    SLO 0x03 IndexedIndirect => op_slo,
+    SLO 0x13 IndirectIndexed => op_slo,
+
+    SRE 0x47 ZeroPage => op_sre,
+    SRE 0x57 ZeroPageX => op_sre,
+    SRE 0x4F Absolute => op_sre,
+    SRE 0x5F AbsoluteX => op_sre,
+    SRE 0x5B AbsoluteY => op_sre,
+    SRE 0x43 IndexedIndirect => op_sre,
+    SRE 0x53 IndirectIndexed => op_sre,
+
+    TAS 0x9B AbsoluteY => op_tas,
+
+    XAA 0x8B Immediate => op_xaa,
+}
@@ -0,0 +1,154 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::mappers::{self, Mapper, Mirroring};
+
+/// Size of the INES header in bytes
+pub const INES_HEADER_SIZE: usize = 16;
+
+/// Size of the optional trainer section in bytes
+pub const TRAINER_SIZE: usize = 512;
+
+/// Errors that can occur while loading an INES file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InesError {
+    /// The file does not start with `NES\x1A`
+    InvalidMagic,
+    /// The file is shorter than its header claims
+    UnexpectedEof,
+    /// The file uses a mapper that is not implemented
+    UnsupportedMapper(u8),
+    /// The file contains less than one 16KB page of PRG ROM
+    MissingPrgRom,
+    /// The PRG ROM is larger than the mapper can address
+    OversizedPrgRom,
+    /// The file contains no CHR ROM, but the mapper does not support CHR RAM
+    MissingChrRom,
+    /// The CHR ROM is larger than the mapper can address
+    OversizedChrRom,
+}
+
+impl fmt::Display for InesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InesError::InvalidMagic => write!(f, "Invalid INES Magic"),
+            InesError::UnexpectedEof => write!(f, "Unexpected end of INES file"),
+            InesError::UnsupportedMapper(id) => write!(f, "No mapper with id {}", id),
+            InesError::MissingPrgRom => write!(f, "INES file contains no PRG ROM"),
+            InesError::OversizedPrgRom => write!(f, "PRG ROM is too large for the mapper"),
+            InesError::MissingChrRom => write!(f, "The mapper requires CHR ROM"),
+            InesError::OversizedChrRom => write!(f, "CHR ROM is too large for the mapper"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InesError {}
+
+/// Creates the mapper of the INES file `data` and loads its PRG and CHR ROM
+///
+/// The trainer (if present) is skipped, it is only used by some copier hardware.
+/// Battery backed RAM still has to be restored with [`Mapper::load_ram`]
+pub fn from_bytes(data: &[u8]) -> Result<Box<dyn Mapper>, InesError> {
+    let header = InesHeader::parse(data)?;
+
+    let prg_rom_start = header.prg_rom_offset();
+    let chr_rom_start = header.chr_rom_offset();
+
+    if data.len() < chr_rom_start + header.chr_rom_size() {
+        return Err(InesError::UnexpectedEof);
+    }
+
+    let mut mapper = mappers::create_mapper(header.mapper_id, &header)?;
+
+    mapper.load_prg_rom(&data[prg_rom_start..prg_rom_start + header.prg_rom_size()])?;
+    mapper.load_chr_rom(&data[chr_rom_start..chr_rom_start + header.chr_rom_size()])?;
+
+    Ok(mapper)
+}
+
+/// Header of an INES file (http://wiki.nesdev.com/w/index.php/INES)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InesHeader {
+    /// Number of 16KB PRG ROM pages
+    pub prg_rom_pages: u8,
+    /// Number of 8KB CHR ROM pages, 0 means the cartridge uses CHR RAM
+    pub chr_rom_pages: u8,
+    /// INES Mapper ID
+    pub mapper_id: u8,
+    /// Hardwired nametable mirroring
+    pub mirroring: Mirroring,
+    /// Whether the cartridge contains battery backed PRG RAM
+    pub has_battery: bool,
+    /// Whether a 512 byte trainer is present between header and PRG ROM
+    pub has_trainer: bool,
+    /// Size of PRG RAM in bytes
+    pub prg_ram_size: usize,
+}
+
+impl InesHeader {
+    /// Parses the header at the start of an INES file
+    pub fn parse(data: &[u8]) -> Result<InesHeader, InesError> {
+        if data.len() < INES_HEADER_SIZE {
+            return Err(InesError::UnexpectedEof);
+        }
+
+        if data[0] != b'N' || data[1] != b'E' || data[2] != b'S' || data[3] != 0x1A {
+            return Err(InesError::InvalidMagic);
+        }
+
+        let mirroring = if (data[6] & 0x08) != 0 {
+            Mirroring::FourScreen
+        } else if (data[6] & 0x01) != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+
+        // a value of 0 infers 8KB for compatibility
+        let prg_ram_pages = data[8].max(1) as usize;
+
+        Ok(InesHeader {
+            prg_rom_pages: data[4],
+            chr_rom_pages: data[5],
+            mapper_id: ((data[6] & 0xF0) >> 4) | (data[7] & 0xF0),
+            mirroring,
+            has_battery: (data[6] & 0x02) != 0,
+            has_trainer: (data[6] & 0x04) != 0,
+            prg_ram_size: prg_ram_pages * 0x2000,
+        })
+    }
+
+    /// File offset of the trainer section, if present
+    pub fn trainer_offset(&self) -> Option<usize> {
+        if self.has_trainer {
+            Some(INES_HEADER_SIZE)
+        } else {
+            None
+        }
+    }
+
+    /// File offset of PRG ROM, which follows the header and the optional trainer
+    pub fn prg_rom_offset(&self) -> usize {
+        if self.has_trainer {
+            INES_HEADER_SIZE + TRAINER_SIZE
+        } else {
+            INES_HEADER_SIZE
+        }
+    }
+
+    /// File offset of CHR ROM, which directly follows PRG ROM
+    pub fn chr_rom_offset(&self) -> usize {
+        self.prg_rom_offset() + self.prg_rom_size()
+    }
+
+    /// Size of PRG ROM in bytes
+    pub fn prg_rom_size(&self) -> usize {
+        self.prg_rom_pages as usize * 0x4000
+    }
+
+    /// Size of CHR ROM in bytes
+    pub fn chr_rom_size(&self) -> usize {
+        self.chr_rom_pages as usize * 0x2000
+    }
+}
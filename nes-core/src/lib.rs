@@ -1,4 +1,24 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod cpu;
 mod cpu_ops;
 
+pub mod memory;
+pub mod bus;
+
+pub mod ppu;
+pub mod palette;
+pub mod apu;
+pub mod controller;
+
 pub mod mappers;
+
+pub mod ines;
+
+pub mod system;
+pub mod state;
+
+#[cfg(feature = "testutil")]
+pub mod testutil;
@@ -1,18 +1,182 @@
-/// Interface used to load data into a Mapper by the INES Loader
+use alloc::boxed::Box;
+use core::ops::RangeInclusive;
+
+use crate::{ines::{InesError, InesHeader}, state::{StateError, StateReader, StateWriter}};
+
+/// Nametable mirroring arrangement of a cartridge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+    /// 0x2000 and 0x2400 share one nametable, 0x2800 and 0x2C00 the other
+    Horizontal,
+    /// 0x2000 and 0x2800 share one nametable, 0x2400 and 0x2C00 the other
+    Vertical,
+    /// All four nametables map to the first nametable
+    SingleScreenLower,
+    /// All four nametables map to the second nametable
+    SingleScreenUpper,
+    /// The cartridge provides memory for four distinct nametables
+    FourScreen,
+}
+
+impl Mirroring {
+    /// Translates a nametable address (0x2000-0x3EFF) into an offset into the 2 KB of console VRAM
+    pub fn nametable_offset(self, addr: u16) -> usize {
+        let table = ((addr >> 10) & 0x03) as usize;
+        let offset = (addr & 0x03FF) as usize;
+
+        let physical = match self {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 0x01,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            // the cartridge would have to provide the additional 2 KB
+            Mirroring::FourScreen => table & 0x01,
+        };
+
+        physical * 0x400 + offset
+    }
+}
+
+/// Cartridge configuration a Mapper receives at construction
+///
+/// Usually derived from an INES header with [`MapperConfig::from_header`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapperConfig {
+    /// Nametable mirroring hardwired on the cartridge
+    pub mirroring: Mirroring,
+    /// Amount of PRG RAM in bytes
+    pub prg_ram_size: usize,
+    /// Whether the cartridge has 8KB of CHR RAM instead of CHR ROM
+    pub chr_is_ram: bool,
+    /// Whether the PRG RAM is battery backed
+    pub has_battery: bool,
+}
+
+impl MapperConfig {
+    pub fn from_header(header: &InesHeader) -> Self {
+        Self {
+            mirroring: header.mirroring,
+            prg_ram_size: header.prg_ram_size,
+            chr_is_ram: header.chr_rom_pages == 0,
+            has_battery: header.has_battery,
+        }
+    }
+}
+
+impl Default for MapperConfig {
+    fn default() -> Self {
+        Self {
+            mirroring: Mirroring::Horizontal,
+            prg_ram_size: 0x2000,
+            chr_is_ram: false,
+            has_battery: false,
+        }
+    }
+}
+
+/// ROM offsets that are currently mapped into the CPU and PPU address space, see [`Mapper::bank_layout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BankLayout {
+    /// Offset into PRG ROM mapped at the 8 KB CPU windows 0x8000, 0xA000, 0xC000 and 0xE000
+    pub prg: [usize; 4],
+    /// Offset into CHR ROM (or RAM) mapped at the 1 KB PPU windows 0x0000, 0x0400, ..., 0x1C00
+    pub chr: [usize; 8],
+}
+
+impl BankLayout {
+    /// Linear mapping of 32 KB PRG ROM and 8 KB CHR without any bank switching
+    pub const NROM: BankLayout = BankLayout {
+        prg: [0x0000, 0x2000, 0x4000, 0x6000],
+        chr: [0x0000, 0x0400, 0x0800, 0x0C00, 0x1000, 0x1400, 0x1800, 0x1C00],
+    };
+
+    /// Returns the index of the 8 KB PRG bank mapped at `addr` (0x8000-0xFFFF)
+    pub fn prg_bank(&self, addr: u16) -> usize {
+        self.prg[((addr & 0x7FFF) / 0x2000) as usize] / 0x2000
+    }
+
+    /// Returns the index of the 1 KB CHR bank mapped at `addr` (0x0000-0x1FFF)
+    pub fn chr_bank(&self, addr: u16) -> usize {
+        self.chr[((addr & 0x1FFF) / 0x0400) as usize] / 0x0400
+    }
+}
+
+/// Interface to the cartridge hardware
+///
+/// Used by the INES Loader to load data into a Mapper and by the [`Bus`](crate::bus::Bus)
+/// to access cartridge space
 pub trait Mapper {
     /// Called by the INES loader to set the PRG ROM data
     /// 
     /// `prg_rom.len()` will always be a multiple of 16KB/0x4000
-    fn load_prg_rom(&mut self, prg_rom: &[u8]);
+    /// # Errors
+    /// - [`InesError::MissingPrgRom`] if `prg_rom` is shorter than 16KB
+    /// - [`InesError::OversizedPrgRom`] if the mapper cannot address that much PRG ROM
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError>;
 
     /// Called by the INES loader to set the CHR ROM data
     /// 
-    /// `chr_rom.len()` will always be a multiple of 8KB/0x2000
-    fn load_chr_rom(&mut self, chr_rom: &[u8]);
+    /// `chr_rom.len()` will always be a multiple of 8KB/0x2000,
+    /// the slice is empty if the cartridge uses CHR RAM (see [`MapperConfig::chr_is_ram`])
+    /// # Errors
+    /// - [`InesError::MissingChrRom`] if `chr_rom` is empty, but the mapper has no CHR RAM
+    /// - [`InesError::OversizedChrRom`] if the mapper cannot address that much CHR ROM
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError>;
+
+    /// Returns the currently active nametable mirroring
+    fn mirroring(&self) -> Mirroring;
+
+    /// Returns which parts of PRG and CHR memory are currently mapped
+    ///
+    /// Only used for debugging purposes (e.g. showing the active banks),
+    /// defaults to [`BankLayout::NROM`]
+    fn bank_layout(&self) -> BankLayout {
+        BankLayout::NROM
+    }
+
+    /// Returns whether the cartridge has battery backed RAM that should be persisted
+    fn has_battery(&self) -> bool {
+        false
+    }
+
+    /// Returns the battery backed RAM contents that should be persisted,
+    /// `None` if the cartridge has no battery
+    fn save_ram(&self) -> Option<&[u8]> {
+        None
+    }
 
-    /// Called by the INES loader to inform the Mapper how much PRG RAM the
-    /// given INES file requested
-    fn set_ram_size(&mut self, size: u16);
+    /// Restores battery backed RAM contents previously returned by [`Mapper::save_ram`]
+    fn load_ram(&mut self, _data: &[u8]) {}
+
+    /// Called whenever PPU address line A12 rises from 0 to 1,
+    /// which happens once per scanline during normal rendering
+    ///
+    /// Used by scanline counting mappers (e.g. MMC3)
+    fn notify_a12_rising(&mut self) {}
+
+    /// Returns whether the mapper is currently asserting the CPU IRQ line
+    ///
+    /// The line is level triggered, it stays asserted until the game acknowledges the IRQ
+    /// through a mapper register or [`Mapper::clear_irq`] is called
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    /// Clears a pending IRQ, releasing the CPU IRQ line
+    fn clear_irq(&mut self) {}
+
+    /// Called when the reset button is pressed, resets the registers the hardware resets
+    ///
+    /// RAM contents (including PRG RAM) persist across a reset
+    fn reset(&mut self) {}
+
+    /// Writes the mutable state of the cartridge (bank registers, RAM, IRQ counters) into a save state
+    ///
+    /// ROM contents are not part of the save state, they are loaded from the ROM file
+    fn save_state(&self, w: &mut StateWriter);
+
+    /// Restores the state written by [`Mapper::save_state`]
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError>;
 
     /// This function should overwrite a memory cell in PRG ROM without causing any side effects
     /// (e.g. bank switching)
@@ -20,12 +184,105 @@ pub trait Mapper {
     /// Only used for debugging purposes (e.g. forcing the reset vector to a different value)
     fn overwrite_prg_rom(&mut self, addr: u16, val: u8);
 
-    fn cpu_load8(&mut self, addr: u16) -> u8;
+    /// Called by the [`Bus`](crate::bus::Bus) for CPU reads in cartridge space (0x4020-0xFFFF)
+    ///
+    /// Returns `None` if nothing on the cartridge drives the data bus at `addr` (open bus)
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8>;
+    /// Called by the [`Bus`](crate::bus::Bus) for CPU writes in cartridge space (0x4020-0xFFFF)
     fn cpu_store8(&mut self, addr: u16, val: u8);
 
+    /// Reads a byte from cartridge space without causing any side effects
+    /// (e.g. clearing flags on register reads)
+    ///
+    /// Used by debuggers to inspect memory without perturbing the emulation
+    fn cpu_peek8(&self, addr: u16) -> Option<u8>;
+
     fn ppu_load8(&mut self, addr: u16) -> u8;
     fn ppu_store8(&mut self, addr: u16, val: u8);
+
+    /// Called by the [`Ppu`](crate::ppu::Ppu) for nametable reads (0x2000-0x3EFF)
+    ///
+    /// `vram` is the 2 KB of nametable RAM inside the console. The cartridge decides how the four
+    /// nametables map onto it, by default according to [`Mapper::mirroring`].
+    /// Cartridges with their own nametable RAM (e.g. four-screen) override this
+    fn nametable_load8(&mut self, addr: u16, vram: &[u8; 0x800]) -> u8 {
+        vram[self.mirroring().nametable_offset(addr)]
+    }
+
+    /// Called by the [`Ppu`](crate::ppu::Ppu) for nametable writes (0x2000-0x3EFF),
+    /// see [`Mapper::nametable_load8`]
+    fn nametable_store8(&mut self, addr: u16, val: u8, vram: &mut [u8; 0x800]) {
+        vram[self.mirroring().nametable_offset(addr)] = val;
+    }
 }
 
+/// Creates the mapper with the INES Mapper ID `id`, configured
+/// with the information in `header`
+/// 
+/// ROM data still has to be loaded with [`Mapper::load_prg_rom`] and [`Mapper::load_chr_rom`]
+/// # Errors
+/// - [`InesError::UnsupportedMapper`] if there is no mapper with the id `id`
+/// - [`InesError::MissingPrgRom`], [`InesError::OversizedPrgRom`], [`InesError::MissingChrRom`]
+///   or [`InesError::OversizedChrRom`] if the ROM sizes in `header` do not fit the mapper
+pub fn create_mapper(id: u8, header: &InesHeader) -> Result<Box<dyn Mapper>, InesError> {
+    type Constructor = fn(&MapperConfig) -> Box<dyn Mapper>;
+
+    // supported number of 16KB PRG ROM and 8KB CHR ROM pages, 0 CHR ROM pages select CHR RAM
+    let (prg_pages, chr_pages, constructor): (RangeInclusive<u8>, RangeInclusive<u8>, Constructor) = match id {
+        0x00 => (1..=2, 0..=1, |config| Box::new(Mapper000::new(config))),
+        0x01 => (1..=u8::MAX, 0..=u8::MAX, |config| Box::new(Mapper001::new(config))),
+        0x02 => (1..=u8::MAX, 0..=1, |config| Box::new(Mapper002::new(config))),
+        0x03 => (1..=2, 1..=u8::MAX, |config| Box::new(Mapper003::new(config))),
+        0x04 => (1..=u8::MAX, 0..=u8::MAX, |config| Box::new(Mapper004::new(config))),
+        0x07 => (1..=u8::MAX, 0..=1, |config| Box::new(Mapper007::new(config))),
+        0x09 => (1..=u8::MAX, 1..=u8::MAX, |config| Box::new(Mapper009::new(config))),
+        0x0B => (1..=u8::MAX, 1..=u8::MAX, |config| Box::new(Mapper011::new(config))),
+        0x42 => (1..=u8::MAX, 1..=u8::MAX, |config| Box::new(Mapper066::new(config))),
+        _ => return Err(InesError::UnsupportedMapper(id)),
+    };
+
+    if header.prg_rom_pages < *prg_pages.start() {
+        return Err(InesError::MissingPrgRom);
+    }
+    if header.prg_rom_pages > *prg_pages.end() {
+        return Err(InesError::OversizedPrgRom);
+    }
+    if header.chr_rom_pages < *chr_pages.start() {
+        return Err(InesError::MissingChrRom);
+    }
+    if header.chr_rom_pages > *chr_pages.end() {
+        return Err(InesError::OversizedChrRom);
+    }
+
+    Ok(constructor(&MapperConfig::from_header(header)))
+}
+
+mod discrete;
+pub use discrete::{BankSelect, DiscreteMapper};
+
 mod mapper000;
-pub use mapper000::Mapper000;
\ No newline at end of file
+pub use mapper000::Mapper000;
+
+mod mapper001;
+pub use mapper001::Mapper001;
+
+mod mapper002;
+pub use mapper002::Mapper002;
+
+mod mapper003;
+pub use mapper003::Mapper003;
+
+mod mapper004;
+pub use mapper004::Mapper004;
+
+mod mapper007;
+pub use mapper007::Mapper007;
+
+mod mapper009;
+pub use mapper009::Mapper009;
+
+mod mapper011;
+pub use mapper011::{ColorDreamsBankSelect, Mapper011};
+
+mod mapper066;
+pub use mapper066::{GxRomBankSelect, Mapper066};
@@ -0,0 +1,141 @@
+use core::marker::PhantomData;
+
+use alloc::{vec, vec::Vec};
+
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{Mapper, MapperConfig, Mirroring};
+
+/// Decodes the value written to a [`DiscreteMapper`] bank select register
+pub trait BankSelect {
+    /// Splits `val` into (32 KB PRG bank, 8 KB CHR bank)
+    fn decode(val: u8) -> (usize, usize);
+}
+
+/// Discrete logic mapper with a single bank select register
+/// 
+/// Shared implementation of boards like [`Mapper011`](super::Mapper011) and [`Mapper066`](super::Mapper066)
+/// that only differ in which bits of the bank select register pick the banks
+/// 
+/// - PRG ROM: 32 KB switchable bank at 0x8000
+/// - CHR ROM: 8 KB switchable bank
+/// - Nametable mirroring: fixed vertical or horizontal
+/// - Bank select: writes to 0x8000-0xFFFF, decoded by `D`
+pub struct DiscreteMapper<D: BankSelect> {
+    prg_rom: Vec<u8>,
+    prg_bank: usize,
+    chr_rom: Vec<u8>,
+    chr_bank: usize,
+    mirroring: Mirroring,
+    bank_select: PhantomData<D>,
+}
+
+impl<D: BankSelect> DiscreteMapper<D> {
+    pub fn new(config: &MapperConfig) -> Self {
+        Self {
+            prg_rom: vec![0; 0x8000],
+            prg_bank: 0,
+            chr_rom: vec![0; 0x2000],
+            chr_bank: 0,
+            mirroring: config.mirroring,
+            bank_select: PhantomData,
+        }
+    }
+
+    /// Number of 32 KB PRG ROM banks
+    fn prg_bank_count(&self) -> usize {
+        (self.prg_rom.len() / 0x8000).max(1)
+    }
+
+    /// Number of 8 KB CHR ROM banks
+    fn chr_bank_count(&self) -> usize {
+        self.chr_rom.len() / 0x2000
+    }
+
+    /// Translates a CPU address in 0x8000-0xFFFF into an offset into `prg_rom`
+    ///
+    /// A 16 KB PRG ROM is mirrored into both halves of the bank
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        (self.prg_bank * 0x8000 + (addr & 0x7FFF) as usize) % self.prg_rom.len()
+    }
+}
+
+impl<D: BankSelect> Default for DiscreteMapper<D> {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
+impl<D: BankSelect> Mapper for DiscreteMapper<D> {
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+
+        self.prg_rom = prg_rom.to_vec();
+        self.prg_bank = 0;
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if chr_rom.is_empty() {
+            return Err(InesError::MissingChrRom);
+        }
+
+        self.chr_rom = chr_rom.to_vec();
+        self.chr_bank = 0;
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.prg_bank as u8);
+        w.write_u8(self.chr_bank as u8);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.prg_bank = r.read_u8()? as usize % self.prg_bank_count();
+        self.chr_bank = r.read_u8()? as usize % self.chr_bank_count();
+        Ok(())
+    }
+
+    fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
+        let offset = self.prg_rom_offset(addr);
+        self.prg_rom[offset] = val;
+    }
+
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[self.prg_rom_offset(addr)])
+        } else {
+            None
+        }
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        if addr >= 0x8000 {
+            let (prg_bank, chr_bank) = D::decode(val);
+            self.prg_bank = prg_bank % self.prg_bank_count();
+            self.chr_bank = chr_bank % self.chr_bank_count();
+        }
+    }
+
+    fn ppu_load8(&mut self, addr: u16) -> u8 {
+        if addr < 0x2000 {
+            self.chr_rom[self.chr_bank * 0x2000 + addr as usize]
+        } else {
+            0
+        }
+    }
+
+    fn ppu_store8(&mut self, _addr: u16, _val: u8) {
+
+    }
+}
@@ -1,71 +1,150 @@
-use super::Mapper;
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{BankLayout, Mapper, MapperConfig, Mirroring};
 
 /// NROM Mapper (http://wiki.nesdev.com/w/index.php/NROM)
 /// 
 /// INES Mapper ID: 0
 /// 
 /// - PRG ROM: 16 or 32 KB at 0x8000 as necessary mirrored to 0xFFFF, no bank switching
-/// - CHR ROM: 8 KB, no bank switching
+/// - CHR ROM: 8 KB, no bank switching (or 8 KB CHR RAM if the cartridge has no CHR ROM)
+/// - PRG RAM: up to 8 KB at 0x6000, mirrored to 0x7FFF if smaller, optionally battery backed
 /// - Nametable mirroring: fixed vertical or horizontal
 pub struct Mapper000 {
-    cpu_ram: [u8; 0x800],
     prg_rom: [u8; 0x8000],
     prg_rom_mask: u16,
-    chr_rom: [u8; 0x2000],
+    prg_ram: [u8; 0x2000],
+    prg_ram_size: usize,
+    has_battery: bool,
+    chr: [u8; 0x2000],
+    chr_is_ram: bool,
+    mirroring: Mirroring,
 }
 
 impl Mapper000 {
-    pub fn new() -> Self {
+    pub fn new(config: &MapperConfig) -> Self {
         Self {
-            cpu_ram: [0; 0x800],
             prg_rom: [0; 0x8000],
             prg_rom_mask: 0,
-            chr_rom: [0; 0x2000],
+            prg_ram: [0; 0x2000],
+            prg_ram_size: config.prg_ram_size.min(0x2000),
+            has_battery: config.has_battery,
+            chr: [0; 0x2000],
+            chr_is_ram: config.chr_is_ram,
+            mirroring: config.mirroring,
         }
     }
 }
 
+impl Default for Mapper000 {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
 impl Mapper for Mapper000 {
-    fn load_prg_rom(&mut self, prg_rom: &[u8]) {
-        let prg_rom_size = self.prg_rom.len().min(prg_rom.len());
-        self.prg_rom[..prg_rom_size].copy_from_slice(&prg_rom[..prg_rom_size]);
-        self.prg_rom_mask = if prg_rom.len() <= 0x4000 { 0x3FFF } else { 0x7FFF }
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+        if prg_rom.len() > self.prg_rom.len() {
+            return Err(InesError::OversizedPrgRom);
+        }
+
+        self.prg_rom[..prg_rom.len()].copy_from_slice(prg_rom);
+        self.prg_rom_mask = if prg_rom.len() <= 0x4000 { 0x3FFF } else { 0x7FFF };
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if chr_rom.len() > self.chr.len() {
+            return Err(InesError::OversizedChrRom);
+        }
+
+        self.chr[..chr_rom.len()].copy_from_slice(chr_rom);
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn bank_layout(&self) -> BankLayout {
+        // a 16 KB PRG ROM is mapped twice
+        let mask = self.prg_rom_mask as usize;
+        BankLayout {
+            prg: BankLayout::NROM.prg.map(|offset| offset & mask),
+            ..BankLayout::NROM
+        }
+    }
+
+    fn has_battery(&self) -> bool {
+        self.has_battery
     }
 
-    fn load_chr_rom(&mut self, chr_rom: &[u8]) {
-        assert!(chr_rom.len() <= 0x2000);
-        self.chr_rom[..chr_rom.len()].copy_from_slice(chr_rom);
+    fn save_ram(&self) -> Option<&[u8]> {
+        if self.has_battery {
+            Some(&self.prg_ram[..self.prg_ram_size])
+        } else {
+            None
+        }
     }
 
-    fn set_ram_size(&mut self, size: u16) {
-        
+    fn load_ram(&mut self, data: &[u8]) {
+        let size = self.prg_ram_size.min(data.len());
+        self.prg_ram[..size].copy_from_slice(&data[..size]);
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.prg_ram);
+        if self.chr_is_ram {
+            w.write_bytes(&self.chr);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        r.read_bytes(&mut self.prg_ram)?;
+        if self.chr_is_ram {
+            r.read_bytes(&mut self.chr)?;
+        }
+        Ok(())
     }
 
     fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
         self.prg_rom[(addr & self.prg_rom_mask) as usize] = val;
     }
 
-    fn cpu_load8(&mut self, addr: u16) -> u8 {
-        if addr < 0x2000 {
-            self.cpu_ram[(addr & 0x7FF) as usize]
-        } else if addr >= 0x8000 {
-            self.prg_rom[(addr & self.prg_rom_mask) as usize]
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[(addr & self.prg_rom_mask) as usize])
+        } else if addr >= 0x6000 && self.prg_ram_size > 0 {
+            Some(self.prg_ram[(addr - 0x6000) as usize % self.prg_ram_size])
         } else {
-            0
+            None
         }
     }
 
     fn cpu_store8(&mut self, addr: u16, val: u8) {
-        if addr < 0x2000 {
-            self.cpu_ram[(addr & 0x7FF) as usize] = val;
+        if (0x6000..0x8000).contains(&addr) && self.prg_ram_size > 0 {
+            self.prg_ram[(addr - 0x6000) as usize % self.prg_ram_size] = val;
         }
     }
 
     fn ppu_load8(&mut self, addr: u16) -> u8 {
-        todo!()
+        if addr < 0x2000 {
+            self.chr[addr as usize]
+        } else {
+            0
+        }
     }
 
     fn ppu_store8(&mut self, addr: u16, val: u8) {
-        todo!()
+        if addr < 0x2000 && self.chr_is_ram {
+            self.chr[addr as usize] = val;
+        }
     }
 }
@@ -0,0 +1,268 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{Mapper, MapperConfig, Mirroring};
+
+/// MMC1 Mapper (http://wiki.nesdev.com/w/index.php/MMC1)
+/// 
+/// INES Mapper ID: 1
+/// 
+/// - PRG ROM: up to 256 KB, switchable as one 32 KB bank or as 16 KB banks with either the first or the last bank fixed
+/// - PRG RAM: 8 KB at 0x6000, optionally battery backed
+/// - CHR ROM/RAM: switchable as one 8 KB bank or as two 4 KB banks
+/// - Nametable mirroring: switchable between horizontal, vertical and single screen
+/// 
+/// Registers are written serially through a 5-bit shift register,
+/// see [`Mapper001::write_serial`]
+pub struct Mapper001 {
+    prg_rom: Vec<u8>,
+    prg_ram: [u8; 0x2000],
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+
+    /// Serial shift register, bits are shifted in from the top
+    shift: u8,
+    /// Number of bits currently held in `shift`
+    shift_count: u8,
+
+    /// Control register
+    /// - bits 0-1: mirroring (0: single screen lower, 1: single screen upper, 2: vertical, 3: horizontal)
+    /// - bits 2-3: PRG ROM bank mode (0, 1: 32 KB, 2: first bank fixed at 0x8000, 3: last bank fixed at 0xC000)
+    /// - bit 4: CHR bank mode (0: 8 KB, 1: two 4 KB banks)
+    control: u8,
+    chr_bank0: u8,
+    chr_bank1: u8,
+    /// PRG bank register
+    /// - bits 0-3: 16 KB PRG ROM bank
+    /// - bit 4: PRG RAM disable
+    prg_bank: u8,
+}
+
+impl Mapper001 {
+    /// Mirroring is controlled by the control register, `config.mirroring` is ignored
+    pub fn new(config: &MapperConfig) -> Self {
+        Self {
+            prg_rom: vec![0; 0x4000],
+            prg_ram: [0; 0x2000],
+            has_battery: config.has_battery,
+            chr: vec![0; 0x2000],
+            chr_is_ram: config.chr_is_ram,
+
+            shift: 0,
+            shift_count: 0,
+
+            control: 0x0C,
+            chr_bank0: 0,
+            chr_bank1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    /// Handles a CPU write to 0x8000-0xFFFF
+    /// 
+    /// - If bit 7 of `val` is set, the shift register is cleared and the PRG ROM bank mode is set to 3
+    /// - Otherwise bit 0 of `val` is shifted into the shift register.
+    ///   On the fifth write the collected value is copied into the register selected by `addr`:
+    ///   - 0x8000-0x9FFF: control
+    ///   - 0xA000-0xBFFF: CHR bank 0
+    ///   - 0xC000-0xDFFF: CHR bank 1
+    ///   - 0xE000-0xFFFF: PRG bank
+    fn write_serial(&mut self, addr: u16, val: u8) {
+        if (val & 0x80) != 0 {
+            self.shift = 0;
+            self.shift_count = 0;
+            self.control |= 0x0C;
+            return;
+        }
+
+        self.shift = (self.shift >> 1) | ((val & 0x01) << 4);
+        self.shift_count += 1;
+
+        if self.shift_count == 5 {
+            let res = self.shift;
+            match addr {
+                0x8000..=0x9FFF => self.control = res,
+                0xA000..=0xBFFF => self.chr_bank0 = res,
+                0xC000..=0xDFFF => self.chr_bank1 = res,
+                _ => self.prg_bank = res,
+            }
+
+            self.shift = 0;
+            self.shift_count = 0;
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        (self.prg_bank & 0x10) == 0
+    }
+
+    /// Translates a CPU address in 0x8000-0xFFFF into an offset into `prg_rom`
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank = (self.prg_bank & 0x0F) as usize;
+        let last_bank = self.prg_rom.len() / 0x4000 - 1;
+
+        let offset = match (self.control >> 2) & 0x03 {
+            0 | 1 => (bank & !0x01) * 0x4000 + (addr & 0x7FFF) as usize,
+            2 => {
+                if addr < 0xC000 {
+                    (addr & 0x3FFF) as usize
+                } else {
+                    bank * 0x4000 + (addr & 0x3FFF) as usize
+                }
+            }
+            _ => {
+                if addr < 0xC000 {
+                    bank * 0x4000 + (addr & 0x3FFF) as usize
+                } else {
+                    last_bank * 0x4000 + (addr & 0x3FFF) as usize
+                }
+            }
+        };
+
+        offset % self.prg_rom.len()
+    }
+
+    /// Translates a PPU address in 0x0000-0x1FFF into an offset into `chr`
+    fn chr_offset(&self, addr: u16) -> usize {
+        let offset = if (self.control & 0x10) == 0 {
+            (self.chr_bank0 & !0x01) as usize * 0x1000 + addr as usize
+        } else if addr < 0x1000 {
+            self.chr_bank0 as usize * 0x1000 + addr as usize
+        } else {
+            self.chr_bank1 as usize * 0x1000 + (addr & 0x0FFF) as usize
+        };
+
+        offset % self.chr.len()
+    }
+}
+
+impl Default for Mapper001 {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
+impl Mapper for Mapper001 {
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+
+        self.prg_rom = prg_rom.to_vec();
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if !self.chr_is_ram {
+            if chr_rom.is_empty() {
+                return Err(InesError::MissingChrRom);
+            }
+            self.chr = chr_rom.to_vec();
+        }
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn has_battery(&self) -> bool {
+        self.has_battery
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        if self.has_battery {
+            Some(&self.prg_ram)
+        } else {
+            None
+        }
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        let size = self.prg_ram.len().min(data.len());
+        self.prg_ram[..size].copy_from_slice(&data[..size]);
+    }
+
+    /// Clears the shift register and sets the PRG ROM bank mode to 3, like a write with bit 7 set
+    fn reset(&mut self) {
+        self.shift = 0;
+        self.shift_count = 0;
+        self.control |= 0x0C;
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.prg_ram);
+        if self.chr_is_ram {
+            w.write_bytes(&self.chr);
+        }
+        w.write_u8(self.shift);
+        w.write_u8(self.shift_count);
+        w.write_u8(self.control);
+        w.write_u8(self.chr_bank0);
+        w.write_u8(self.chr_bank1);
+        w.write_u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        r.read_bytes(&mut self.prg_ram)?;
+        if self.chr_is_ram {
+            r.read_bytes(&mut self.chr)?;
+        }
+        self.shift = r.read_u8()?;
+        self.shift_count = r.read_u8()?;
+        self.control = r.read_u8()?;
+        self.chr_bank0 = r.read_u8()?;
+        self.chr_bank1 = r.read_u8()?;
+        self.prg_bank = r.read_u8()?;
+        Ok(())
+    }
+
+    fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
+        let offset = self.prg_rom_offset(addr);
+        self.prg_rom[offset] = val;
+    }
+
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[self.prg_rom_offset(addr)])
+        } else if addr >= 0x6000 && self.prg_ram_enabled() {
+            Some(self.prg_ram[(addr - 0x6000) as usize])
+        } else {
+            None
+        }
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        if addr >= 0x8000 {
+            self.write_serial(addr, val);
+        } else if addr >= 0x6000 && self.prg_ram_enabled() {
+            self.prg_ram[(addr - 0x6000) as usize] = val;
+        }
+    }
+
+    fn ppu_load8(&mut self, addr: u16) -> u8 {
+        if addr < 0x2000 {
+            self.chr[self.chr_offset(addr)]
+        } else {
+            0
+        }
+    }
+
+    fn ppu_store8(&mut self, addr: u16, val: u8) {
+        if addr < 0x2000 && self.chr_is_ram {
+            let offset = self.chr_offset(addr);
+            self.chr[offset] = val;
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{Mapper, MapperConfig, Mirroring};
+
+/// CNROM Mapper (http://wiki.nesdev.com/w/index.php/CNROM)
+/// 
+/// INES Mapper ID: 3
+/// 
+/// - PRG ROM: 16 or 32 KB at 0x8000 as necessary mirrored to 0xFFFF, no bank switching
+/// - CHR ROM: up to 4 switchable 8 KB banks
+/// - Nametable mirroring: fixed vertical or horizontal
+/// - Bank select: writes to 0x8000-0xFFFF select the CHR bank
+pub struct Mapper003 {
+    prg_rom: [u8; 0x8000],
+    prg_rom_mask: u16,
+    chr_rom: Vec<u8>,
+    chr_bank: usize,
+    mirroring: Mirroring,
+}
+
+impl Mapper003 {
+    pub fn new(config: &MapperConfig) -> Self {
+        Self {
+            prg_rom: [0; 0x8000],
+            prg_rom_mask: 0,
+            chr_rom: vec![0; 0x2000],
+            chr_bank: 0,
+            mirroring: config.mirroring,
+        }
+    }
+
+    /// Number of 8 KB CHR ROM banks
+    fn chr_bank_count(&self) -> usize {
+        self.chr_rom.len() / 0x2000
+    }
+}
+
+impl Default for Mapper003 {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
+impl Mapper for Mapper003 {
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+        if prg_rom.len() > self.prg_rom.len() {
+            return Err(InesError::OversizedPrgRom);
+        }
+
+        self.prg_rom[..prg_rom.len()].copy_from_slice(prg_rom);
+        self.prg_rom_mask = if prg_rom.len() <= 0x4000 { 0x3FFF } else { 0x7FFF };
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if chr_rom.is_empty() {
+            return Err(InesError::MissingChrRom);
+        }
+
+        self.chr_rom = chr_rom.to_vec();
+        self.chr_bank = 0;
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.chr_bank as u8);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.chr_bank = r.read_u8()? as usize % self.chr_bank_count();
+        Ok(())
+    }
+
+    fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
+        self.prg_rom[(addr & self.prg_rom_mask) as usize] = val;
+    }
+
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[(addr & self.prg_rom_mask) as usize])
+        } else {
+            None
+        }
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        if addr >= 0x8000 {
+            self.chr_bank = (val & 0x03) as usize % self.chr_bank_count();
+        }
+    }
+
+    fn ppu_load8(&mut self, addr: u16) -> u8 {
+        if addr < 0x2000 {
+            self.chr_rom[self.chr_bank * 0x2000 + addr as usize]
+        } else {
+            0
+        }
+    }
+
+    fn ppu_store8(&mut self, _addr: u16, _val: u8) {
+
+    }
+}
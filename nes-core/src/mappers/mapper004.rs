@@ -0,0 +1,277 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{Mapper, MapperConfig, Mirroring};
+
+/// MMC3 Mapper (http://wiki.nesdev.com/w/index.php/MMC3)
+/// 
+/// INES Mapper ID: 4
+/// 
+/// - PRG ROM: four 8 KB windows, two switchable and two fixed to the second last and last bank
+/// - PRG RAM: 8 KB at 0x6000, optionally battery backed, can be disabled and write protected
+/// - CHR ROM/RAM: two switchable 2 KB banks and four switchable 1 KB banks
+/// - Nametable mirroring: switchable between horizontal and vertical (unless hardwired to four screen)
+/// - Scanline IRQ counter clocked by rising edges of PPU A12, see [`Mapper::notify_a12_rising`]
+pub struct Mapper004 {
+    prg_rom: Vec<u8>,
+    prg_ram: [u8; 0x2000],
+    has_battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+
+    /// Bank select register
+    /// - bits 0-2: which bank register the next bank data write updates
+    /// - bit 6: PRG ROM bank mode (0: 0x8000 swappable, 1: 0xC000 swappable)
+    /// - bit 7: CHR A12 inversion
+    bank_select: u8,
+    /// Bank registers R0-R7
+    banks: [u8; 8],
+    mirroring: Mirroring,
+    four_screen: bool,
+    prg_ram_enabled: bool,
+    prg_ram_write_protect: bool,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl Mapper004 {
+    pub fn new(config: &MapperConfig) -> Self {
+        Self {
+            prg_rom: vec![0; 0x4000],
+            prg_ram: [0; 0x2000],
+            has_battery: config.has_battery,
+            chr: vec![0; 0x2000],
+            chr_is_ram: config.chr_is_ram,
+
+            bank_select: 0,
+            banks: [0; 8],
+            mirroring: config.mirroring,
+            four_screen: config.mirroring == Mirroring::FourScreen,
+            prg_ram_enabled: true,
+            prg_ram_write_protect: false,
+
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+        }
+    }
+
+    /// Handles a CPU write to one of the registers in 0x8000-0xFFFF,
+    /// even and odd addresses select different registers in each 8 KB range
+    fn write_register(&mut self, addr: u16, val: u8) {
+        let odd = (addr & 0x01) != 0;
+
+        match (addr, odd) {
+            (0x8000..=0x9FFF, false) => self.bank_select = val,
+            (0x8000..=0x9FFF, true) => self.banks[(self.bank_select & 0x07) as usize] = val,
+            (0xA000..=0xBFFF, false) => {
+                if !self.four_screen {
+                    self.mirroring = if (val & 0x01) == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+                }
+            }
+            (0xA000..=0xBFFF, true) => {
+                self.prg_ram_enabled = (val & 0x80) != 0;
+                self.prg_ram_write_protect = (val & 0x40) != 0;
+            }
+            (0xC000..=0xDFFF, false) => self.irq_latch = val,
+            (0xC000..=0xDFFF, true) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (_, false) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (_, true) => self.irq_enabled = true,
+        }
+    }
+
+    /// Translates a CPU address in 0x8000-0xFFFF into an offset into `prg_rom`
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank_count = self.prg_rom.len() / 0x2000;
+        let second_last = bank_count - 2;
+        let last = bank_count - 1;
+        let swap_c000 = (self.bank_select & 0x40) != 0;
+
+        let bank = match (addr >> 13) & 0x03 {
+            0 => if swap_c000 { second_last } else { self.banks[6] as usize },
+            1 => self.banks[7] as usize,
+            2 => if swap_c000 { self.banks[6] as usize } else { second_last },
+            _ => last,
+        };
+
+        (bank % bank_count) * 0x2000 + (addr & 0x1FFF) as usize
+    }
+
+    /// Translates a PPU address in 0x0000-0x1FFF into an offset into `chr`
+    fn chr_offset(&self, addr: u16) -> usize {
+        // A12 inversion swaps the 2 KB and 1 KB halves
+        let addr = if (self.bank_select & 0x80) != 0 { addr ^ 0x1000 } else { addr };
+
+        let bank_1k = match addr >> 10 {
+            0 => self.banks[0] & 0xFE,
+            1 => self.banks[0] | 0x01,
+            2 => self.banks[1] & 0xFE,
+            3 => self.banks[1] | 0x01,
+            n => self.banks[(n - 2) as usize],
+        };
+
+        (bank_1k as usize * 0x400 + (addr & 0x3FF) as usize) % self.chr.len()
+    }
+}
+
+impl Default for Mapper004 {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
+impl Mapper for Mapper004 {
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+
+        self.prg_rom = prg_rom.to_vec();
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if !self.chr_is_ram {
+            if chr_rom.is_empty() {
+                return Err(InesError::MissingChrRom);
+            }
+            self.chr = chr_rom.to_vec();
+        }
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn has_battery(&self) -> bool {
+        self.has_battery
+    }
+
+    fn save_ram(&self) -> Option<&[u8]> {
+        if self.has_battery {
+            Some(&self.prg_ram)
+        } else {
+            None
+        }
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        let size = self.prg_ram.len().min(data.len());
+        self.prg_ram[..size].copy_from_slice(&data[..size]);
+    }
+
+    fn notify_a12_rising(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn clear_irq(&mut self) {
+        self.irq_pending = false;
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.prg_ram);
+        if self.chr_is_ram {
+            w.write_bytes(&self.chr);
+        }
+        w.write_u8(self.bank_select);
+        w.write_bytes(&self.banks);
+        w.write_bool(self.mirroring == Mirroring::Horizontal);
+        w.write_bool(self.prg_ram_enabled);
+        w.write_bool(self.prg_ram_write_protect);
+        w.write_u8(self.irq_latch);
+        w.write_u8(self.irq_counter);
+        w.write_bool(self.irq_reload);
+        w.write_bool(self.irq_enabled);
+        w.write_bool(self.irq_pending);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        r.read_bytes(&mut self.prg_ram)?;
+        if self.chr_is_ram {
+            r.read_bytes(&mut self.chr)?;
+        }
+        self.bank_select = r.read_u8()?;
+        r.read_bytes(&mut self.banks)?;
+        let horizontal = r.read_bool()?;
+        if !self.four_screen {
+            self.mirroring = if horizontal { Mirroring::Horizontal } else { Mirroring::Vertical };
+        }
+        self.prg_ram_enabled = r.read_bool()?;
+        self.prg_ram_write_protect = r.read_bool()?;
+        self.irq_latch = r.read_u8()?;
+        self.irq_counter = r.read_u8()?;
+        self.irq_reload = r.read_bool()?;
+        self.irq_enabled = r.read_bool()?;
+        self.irq_pending = r.read_bool()?;
+        Ok(())
+    }
+
+    fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
+        let offset = self.prg_rom_offset(addr);
+        self.prg_rom[offset] = val;
+    }
+
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[self.prg_rom_offset(addr)])
+        } else if addr >= 0x6000 && self.prg_ram_enabled {
+            Some(self.prg_ram[(addr - 0x6000) as usize])
+        } else {
+            None
+        }
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        if addr >= 0x8000 {
+            self.write_register(addr, val);
+        } else if addr >= 0x6000 && self.prg_ram_enabled && !self.prg_ram_write_protect {
+            self.prg_ram[(addr - 0x6000) as usize] = val;
+        }
+    }
+
+    fn ppu_load8(&mut self, addr: u16) -> u8 {
+        if addr < 0x2000 {
+            self.chr[self.chr_offset(addr)]
+        } else {
+            0
+        }
+    }
+
+    fn ppu_store8(&mut self, addr: u16, val: u8) {
+        if addr < 0x2000 && self.chr_is_ram {
+            let offset = self.chr_offset(addr);
+            self.chr[offset] = val;
+        }
+    }
+}
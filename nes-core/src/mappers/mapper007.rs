@@ -0,0 +1,134 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{Mapper, MapperConfig, Mirroring};
+
+/// AxROM Mapper (http://wiki.nesdev.com/w/index.php/AxROM)
+/// 
+/// INES Mapper ID: 7
+/// 
+/// - PRG ROM: 32 KB switchable bank at 0x8000
+/// - CHR RAM: 8 KB, no bank switching
+/// - Nametable mirroring: switchable single screen
+/// - Bank select: writes to 0x8000-0xFFFF (---M -PPP)
+///   - bits 0-2: 32 KB PRG bank
+///   - bit 4: nametable (0: single screen lower, 1: single screen upper)
+pub struct Mapper007 {
+    prg_rom: Vec<u8>,
+    prg_bank: usize,
+    chr: [u8; 0x2000],
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+}
+
+impl Mapper007 {
+    /// Mirroring is controlled by the bank select register, `config.mirroring` is ignored
+    pub fn new(config: &MapperConfig) -> Self {
+        Self {
+            prg_rom: vec![0; 0x8000],
+            prg_bank: 0,
+            chr: [0; 0x2000],
+            chr_is_ram: config.chr_is_ram,
+            mirroring: Mirroring::SingleScreenLower,
+        }
+    }
+
+    /// Number of 32 KB PRG ROM banks
+    fn prg_bank_count(&self) -> usize {
+        (self.prg_rom.len() / 0x8000).max(1)
+    }
+
+    /// Translates a CPU address in 0x8000-0xFFFF into an offset into `prg_rom`
+    ///
+    /// A 16 KB PRG ROM is mirrored into both halves of the bank
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        (self.prg_bank * 0x8000 + (addr & 0x7FFF) as usize) % self.prg_rom.len()
+    }
+}
+
+impl Default for Mapper007 {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
+impl Mapper for Mapper007 {
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+
+        self.prg_rom = prg_rom.to_vec();
+        self.prg_bank = 0;
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if chr_rom.len() > self.chr.len() {
+            return Err(InesError::OversizedChrRom);
+        }
+
+        self.chr[..chr_rom.len()].copy_from_slice(chr_rom);
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.prg_bank as u8);
+        w.write_bool(self.mirroring == Mirroring::SingleScreenUpper);
+        if self.chr_is_ram {
+            w.write_bytes(&self.chr);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.prg_bank = r.read_u8()? as usize % self.prg_bank_count();
+        self.mirroring = if r.read_bool()? { Mirroring::SingleScreenUpper } else { Mirroring::SingleScreenLower };
+        if self.chr_is_ram {
+            r.read_bytes(&mut self.chr)?;
+        }
+        Ok(())
+    }
+
+    fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
+        let offset = self.prg_rom_offset(addr);
+        self.prg_rom[offset] = val;
+    }
+
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[self.prg_rom_offset(addr)])
+        } else {
+            None
+        }
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        if addr >= 0x8000 {
+            self.prg_bank = (val & 0x07) as usize % self.prg_bank_count();
+            self.mirroring = if (val & 0x10) == 0 { Mirroring::SingleScreenLower } else { Mirroring::SingleScreenUpper };
+        }
+    }
+
+    fn ppu_load8(&mut self, addr: u16) -> u8 {
+        if addr < 0x2000 {
+            self.chr[addr as usize]
+        } else {
+            0
+        }
+    }
+
+    fn ppu_store8(&mut self, addr: u16, val: u8) {
+        if addr < 0x2000 && self.chr_is_ram {
+            self.chr[addr as usize] = val;
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{ines::InesError, state::{StateError, StateReader, StateWriter}};
+
+use super::{Mapper, MapperConfig, Mirroring};
+
+/// MMC2 Mapper (http://wiki.nesdev.com/w/index.php/MMC2)
+/// 
+/// INES Mapper ID: 9
+/// 
+/// - PRG ROM: 8 KB switchable bank at 0x8000, the last three banks fixed at 0xA000-0xFFFF
+/// - CHR ROM: two 4 KB windows, each with two bank registers selected by a latch
+/// - Nametable mirroring: switchable between horizontal and vertical
+/// - Registers:
+///   - 0xA000-0xAFFF: PRG bank
+///   - 0xB000-0xBFFF: CHR bank for 0x0000 while latch 0 is 0xFD
+///   - 0xC000-0xCFFF: CHR bank for 0x0000 while latch 0 is 0xFE
+///   - 0xD000-0xDFFF: CHR bank for 0x1000 while latch 1 is 0xFD
+///   - 0xE000-0xEFFF: CHR bank for 0x1000 while latch 1 is 0xFE
+///   - 0xF000-0xFFFF: mirroring (0: vertical, 1: horizontal)
+/// 
+/// The latches are set by the PPU fetching tiles 0xFD and 0xFE, see [`Mapper009::update_latches`]
+pub struct Mapper009 {
+    prg_rom: Vec<u8>,
+    prg_bank: u8,
+    chr_rom: Vec<u8>,
+    /// CHR bank registers: 0x0000/0xFD, 0x0000/0xFE, 0x1000/0xFD, 0x1000/0xFE
+    chr_banks: [u8; 4],
+    /// Whether the latch of the 0x0000 and 0x1000 window is 0xFE (otherwise 0xFD)
+    latches: [bool; 2],
+    mirroring: Mirroring,
+}
+
+impl Mapper009 {
+    /// Mirroring is controlled by a register, `config.mirroring` is ignored
+    pub fn new(_config: &MapperConfig) -> Self {
+        Self {
+            prg_rom: vec![0; 0x8000],
+            prg_bank: 0,
+            chr_rom: vec![0; 0x2000],
+            chr_banks: [0; 4],
+            latches: [true; 2],
+            mirroring: Mirroring::Vertical,
+        }
+    }
+
+    /// Translates a CPU address in 0x8000-0xFFFF into an offset into `prg_rom`
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank_count = self.prg_rom.len() / 0x2000;
+
+        let bank = match (addr >> 13) & 0x03 {
+            0 => self.prg_bank as usize,
+            n => bank_count - 4 + n as usize,
+        };
+
+        (bank % bank_count) * 0x2000 + (addr & 0x1FFF) as usize
+    }
+
+    /// Translates a PPU address in 0x0000-0x1FFF into an offset into `chr_rom`
+    fn chr_offset(&self, addr: u16) -> usize {
+        let window = (addr >> 12) as usize;
+        let bank = self.chr_banks[window * 2 + self.latches[window] as usize];
+
+        (bank as usize * 0x1000 + (addr & 0x0FFF) as usize) % self.chr_rom.len()
+    }
+
+    /// Updates the latches after the PPU read `addr`
+    ///
+    /// - 0x0FD8 / 0x0FE8: latch 0 becomes 0xFD / 0xFE
+    /// - 0x1FD8-0x1FDF / 0x1FE8-0x1FEF: latch 1 becomes 0xFD / 0xFE
+    ///
+    /// These are the fetches of the upper bit plane of tiles 0xFD and 0xFE,
+    /// the new bank is used starting with the next fetch
+    fn update_latches(&mut self, addr: u16) {
+        match addr {
+            0x0FD8 => self.latches[0] = false,
+            0x0FE8 => self.latches[0] = true,
+            0x1FD8..=0x1FDF => self.latches[1] = false,
+            0x1FE8..=0x1FEF => self.latches[1] = true,
+            _ => {}
+        }
+    }
+}
+
+impl Default for Mapper009 {
+    fn default() -> Self {
+        Self::new(&MapperConfig::default())
+    }
+}
+
+impl Mapper for Mapper009 {
+    fn load_prg_rom(&mut self, prg_rom: &[u8]) -> Result<(), InesError> {
+        if prg_rom.len() < 0x4000 {
+            return Err(InesError::MissingPrgRom);
+        }
+
+        if prg_rom.len() < 0x8000 {
+            // the fixed banks need at least four 8 KB banks, mirror smaller ROMs
+            self.prg_rom = prg_rom.repeat(0x8000 / prg_rom.len());
+        } else {
+            self.prg_rom = prg_rom.to_vec();
+        }
+        Ok(())
+    }
+
+    fn load_chr_rom(&mut self, chr_rom: &[u8]) -> Result<(), InesError> {
+        if chr_rom.is_empty() {
+            return Err(InesError::MissingChrRom);
+        }
+
+        self.chr_rom = chr_rom.to_vec();
+        Ok(())
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.prg_bank);
+        w.write_bytes(&self.chr_banks);
+        w.write_bool(self.latches[0]);
+        w.write_bool(self.latches[1]);
+        w.write_bool(self.mirroring == Mirroring::Horizontal);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.prg_bank = r.read_u8()?;
+        r.read_bytes(&mut self.chr_banks)?;
+        self.latches[0] = r.read_bool()?;
+        self.latches[1] = r.read_bool()?;
+        self.mirroring = if r.read_bool()? { Mirroring::Horizontal } else { Mirroring::Vertical };
+        Ok(())
+    }
+
+    fn overwrite_prg_rom(&mut self, addr: u16, val: u8) {
+        let offset = self.prg_rom_offset(addr);
+        self.prg_rom[offset] = val;
+    }
+
+    fn cpu_load8(&mut self, addr: u16) -> Option<u8> {
+        self.cpu_peek8(addr)
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> Option<u8> {
+        if addr >= 0x8000 {
+            Some(self.prg_rom[self.prg_rom_offset(addr)])
+        } else {
+            None
+        }
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xA000..=0xAFFF => self.prg_bank = val & 0x0F,
+            0xB000..=0xBFFF => self.chr_banks[0] = val & 0x1F,
+            0xC000..=0xCFFF => self.chr_banks[1] = val & 0x1F,
+            0xD000..=0xDFFF => self.chr_banks[2] = val & 0x1F,
+            0xE000..=0xEFFF => self.chr_banks[3] = val & 0x1F,
+            0xF000..=0xFFFF => {
+                self.mirroring = if (val & 0x01) == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_load8(&mut self, addr: u16) -> u8 {
+        if addr < 0x2000 {
+            let res = self.chr_rom[self.chr_offset(addr)];
+            self.update_latches(addr);
+            res
+        } else {
+            0
+        }
+    }
+
+    fn ppu_store8(&mut self, _addr: u16, _val: u8) {
+
+    }
+}
@@ -0,0 +1,22 @@
+use super::{BankSelect, DiscreteMapper};
+
+/// Color Dreams Mapper (http://wiki.nesdev.com/w/index.php/Color_Dreams)
+/// 
+/// INES Mapper ID: 11
+/// 
+/// - PRG ROM: 32 KB switchable bank at 0x8000
+/// - CHR ROM: 8 KB switchable bank
+/// - Nametable mirroring: fixed vertical or horizontal
+/// - Bank select: writes to 0x8000-0xFFFF (CCCC --PP)
+///   - bits 0-1: 32 KB PRG bank
+///   - bits 4-7: 8 KB CHR bank
+pub type Mapper011 = DiscreteMapper<ColorDreamsBankSelect>;
+
+/// Bank select decoding of [`Mapper011`]
+pub struct ColorDreamsBankSelect;
+
+impl BankSelect for ColorDreamsBankSelect {
+    fn decode(val: u8) -> (usize, usize) {
+        ((val & 0x03) as usize, (val >> 4) as usize)
+    }
+}
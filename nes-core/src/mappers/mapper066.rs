@@ -0,0 +1,22 @@
+use super::{BankSelect, DiscreteMapper};
+
+/// GxROM Mapper (http://wiki.nesdev.com/w/index.php/GxROM)
+/// 
+/// INES Mapper ID: 66
+/// 
+/// - PRG ROM: 32 KB switchable bank at 0x8000
+/// - CHR ROM: 8 KB switchable bank
+/// - Nametable mirroring: fixed vertical or horizontal
+/// - Bank select: writes to 0x8000-0xFFFF (PPPP CCCC)
+///   - bits 4-7: 32 KB PRG bank
+///   - bits 0-3: 8 KB CHR bank
+pub type Mapper066 = DiscreteMapper<GxRomBankSelect>;
+
+/// Bank select decoding of [`Mapper066`]
+pub struct GxRomBankSelect;
+
+impl BankSelect for GxRomBankSelect {
+    fn decode(val: u8) -> (usize, usize) {
+        ((val >> 4) as usize, (val & 0x0F) as usize)
+    }
+}
@@ -0,0 +1,66 @@
+/// Kind of a memory access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A memory access that hit a watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    /// Address as seen by the CPU (before any mirroring)
+    pub addr: u16,
+    pub access: AccessKind,
+}
+
+/// The CPU's view of the address space
+pub trait Memory {
+    fn cpu_load8(&mut self, addr: u16) -> u8;
+    fn cpu_store8(&mut self, addr: u16, val: u8);
+
+    /// Reads a little endian 16-Bit value (`addr` holds the low byte)
+    fn cpu_load16(&mut self, addr: u16) -> u16 {
+        let lo = self.cpu_load8(addr);
+        let hi = self.cpu_load8(addr.wrapping_add(1));
+        ((hi as u16) << 8) | lo as u16
+    }
+
+    /// Writes a little endian 16-Bit value (`addr` receives the low byte)
+    fn cpu_store16(&mut self, addr: u16, val: u16) {
+        self.cpu_store8(addr, (val & 0xFF) as u8);
+        self.cpu_store8(addr.wrapping_add(1), (val >> 8) as u8);
+    }
+
+    /// Reads a byte without causing any side effects
+    /// (e.g. clearing flags on register reads)
+    ///
+    /// Used by debuggers to inspect memory without perturbing the emulation
+    fn cpu_peek8(&self, addr: u16) -> u8;
+
+    /// Reads `len` bytes starting at `start` into `out` without causing any side effects (see [`Memory::cpu_peek8`]),
+    /// addresses wrap around from 0xFFFF to 0x0000
+    ///
+    /// Used by debuggers to display memory (e.g. a hex dump of zero page)
+    /// # Panics
+    /// If `out` is shorter than `len`
+    fn read_range(&self, start: u16, len: usize, out: &mut [u8]) {
+        for (i, val) in out[..len].iter_mut().enumerate() {
+            *val = self.cpu_peek8(start.wrapping_add(i as u16));
+        }
+    }
+
+    /// Returns whether an OAM DMA was started since the last call and resets the request
+    ///
+    /// The CPU calls this after every instruction and stalls for 513 or 514 cycles
+    /// (depending on the cycle parity) if it returns true
+    fn take_dma_request(&mut self) -> bool {
+        false
+    }
+
+    /// Returns the first access that hit a watchpoint since the last call and resets it
+    ///
+    /// Checked by [`Cpu::run_until_breakpoint`](crate::cpu::Cpu::run_until_breakpoint) after every instruction
+    fn take_watchpoint_hit(&mut self) -> Option<WatchpointHit> {
+        None
+    }
+}
@@ -0,0 +1,662 @@
+use crate::{mappers::Mapper, palette::NES_PALETTE, state::{StateError, StateReader, StateWriter}};
+
+/// Width of the visible picture in pixels
+pub const SCREEN_WIDTH: usize = 256;
+/// Height of the visible picture in pixels
+pub const SCREEN_HEIGHT: usize = 240;
+
+/// Number of frames (roughly 600 ms) after which the open bus latch decays to 0 if it is not refreshed
+pub const OPEN_BUS_DECAY_FRAMES: u64 = 36;
+
+/// Picture Processing Unit (http://wiki.nesdev.com/w/index.php/PPU)
+///
+/// The CPU talks to the PPU through eight registers, which the [`Bus`](crate::bus::Bus)
+/// maps to 0x2000-0x2007 and mirrors every 8 bytes up to 0x3FFF:
+///
+/// | reg | name      | access |
+/// |-----|-----------|--------|
+/// | 0   | PPUCTRL   | write  |
+/// | 1   | PPUMASK   | write  |
+/// | 2   | PPUSTATUS | read   |
+/// | 3   | OAMADDR   | write  |
+/// | 4   | OAMDATA   | r/w    |
+/// | 5   | PPUSCROLL | write  |
+/// | 6   | PPUADDR   | write  |
+/// | 7   | PPUDATA   | r/w    |
+///
+/// PPU address space:
+/// - 0x0000-0x1FFF: pattern tables, handled by the [`Mapper`]
+/// - 0x2000-0x3EFF: nametables, 2 KB of VRAM routed through [`Mapper::nametable_load8`] and [`Mapper::nametable_store8`]
+/// - 0x3F00-0x3FFF: palette RAM, mirrored every 32 bytes, 0x3F10/0x3F14/0x3F18/0x3F1C mirror 0x3F00/0x3F04/0x3F08/0x3F0C
+///
+/// A frame consists of 262 scanlines of 341 dots each, see [`Ppu::step`]:
+/// - 0-239: visible scanlines
+/// - 240: idle
+/// - 241-260: vblank
+/// - 261: pre-render scanline
+pub struct Ppu {
+    /// PPUCTRL
+    /// - bits 0-1: base nametable
+    /// - bit 2: VRAM address increment per PPUDATA access (0: 1, 1: 32)
+    /// - bit 3: sprite pattern table (8x8 sprites only)
+    /// - bit 4: background pattern table
+    /// - bit 5: sprite size (0: 8x8, 1: 8x16)
+    /// - bit 7: generate NMI at the start of vblank
+    ctrl: u8,
+    /// PPUMASK
+    /// - bit 0: grayscale
+    /// - bits 1-2: show background/sprites in the leftmost 8 pixels
+    /// - bits 3-4: show background/sprites
+    /// - bits 5-7: color emphasis
+    mask: u8,
+    /// PPUSTATUS
+    /// - bit 5: sprite overflow
+    /// - bit 6: sprite 0 hit
+    /// - bit 7: vblank
+    status: u8,
+
+    /// Object Attribute Memory, 64 sprites of 4 bytes each
+    oam: [u8; 0x100],
+    oam_addr: u8,
+
+    /// Nametable memory
+    vram: [u8; 0x800],
+    palette: [u8; 0x20],
+
+    // internal registers (http://wiki.nesdev.com/w/index.php/PPU_scrolling)
+    // v and t are laid out as yyy NN YYYYY XXXXX (fine Y, nametable, coarse Y, coarse X)
+    /// Current VRAM address (v)
+    vram_addr: u16,
+    /// Temporary VRAM address (t), the top left corner of the screen
+    temp_vram_addr: u16,
+    /// Fine X scroll (x)
+    fine_x: u8,
+    /// Shared first/second write toggle of PPUSCROLL and PPUADDR (w)
+    write_latch: bool,
+    /// PPUDATA read buffer, holds the byte fetched by the previous PPUDATA read
+    read_buffer: u8,
+    /// Value last driven onto the data bus between CPU and PPU,
+    /// returned for write-only registers and unused PPUSTATUS bits
+    open_bus: u8,
+    /// Frame in which `open_bus` was last refreshed
+    open_bus_frame: u64,
+
+    scanline: u16,
+    dot: u16,
+    frame: u64,
+    /// Dot of the current scanline at which the sprite 0 hit flag gets set
+    sprite_zero_hit_dot: Option<u16>,
+
+    /// Set on a rising edge of the NMI output, cleared by [`Ppu::nmi_pending`]
+    nmi_pending: bool,
+
+    /// One palette index (0-63) per pixel
+    frame_buffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Self {
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+
+            oam: [0; 0x100],
+            oam_addr: 0,
+
+            vram: [0; 0x800],
+            palette: [0; 0x20],
+
+            vram_addr: 0,
+            temp_vram_addr: 0,
+            fine_x: 0,
+            write_latch: false,
+            read_buffer: 0,
+            open_bus: 0,
+            open_bus_frame: 0,
+
+            scanline: 0,
+            dot: 0,
+            frame: 0,
+            sprite_zero_hit_dot: None,
+
+            nmi_pending: false,
+
+            frame_buffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+
+    /// Current value of PPUCTRL
+    pub fn ctrl(&self) -> u8 {
+        self.ctrl
+    }
+
+    /// Current value of PPUMASK
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Current VRAM address (v)
+    pub fn vram_addr(&self) -> u16 {
+        self.vram_addr
+    }
+
+    /// Scanline that is currently being processed (0-261)
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    /// Dot within the current scanline (0-340)
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    /// Number of frames completed so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns whether the PPU requested an NMI since the last call and clears the request
+    ///
+    /// The NMI output is (vblank flag AND PPUCTRL bit 7), an NMI is requested on every rising edge,
+    /// so enabling NMIs while in vblank triggers one immediately
+    pub fn nmi_pending(&mut self) -> bool {
+        let res = self.nmi_pending;
+        self.nmi_pending = false;
+        res
+    }
+
+    fn nmi_output(&self) -> bool {
+        (self.status & 0x80) != 0 && (self.ctrl & 0x80) != 0
+    }
+
+    fn rendering_enabled(&self) -> bool {
+        (self.mask & 0x18) != 0
+    }
+
+    /// Mask applied to palette indices, strips the hue while PPUMASK grayscale is set
+    fn grayscale_mask(&self) -> u8 {
+        if (self.mask & 0x01) != 0 { 0x30 } else { 0x3F }
+    }
+
+    /// The last rendered picture, [`SCREEN_WIDTH`] x [`SCREEN_HEIGHT`] palette indices (0-63) in row major order
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    /// Converts the frame buffer into 24-Bit RGB colors using [`NES_PALETTE`],
+    /// `out` receives three bytes per pixel and has to be at least [`SCREEN_WIDTH`] * [`SCREEN_HEIGHT`] * 3 bytes long
+    /// 
+    /// Applies the grayscale and color emphasis bits of PPUMASK
+    pub fn render_rgb(&self, out: &mut [u8]) {
+        let grayscale_mask = self.grayscale_mask();
+        let emphasis = self.mask >> 5;
+
+        for (pixel, rgb) in self.frame_buffer.iter().zip(out.chunks_exact_mut(3)) {
+            let color = NES_PALETTE[(pixel & grayscale_mask) as usize];
+
+            for (channel, (&c, out)) in color.iter().zip(rgb.iter_mut()).enumerate() {
+                // emphasizing a color darkens the other two
+                let attenuated = emphasis != 0 && (emphasis & (1 << channel)) == 0;
+                *out = if attenuated { (c as u16 * 13 / 16) as u8 } else { c };
+            }
+        }
+    }
+
+    /// Object Attribute Memory, 64 sprites of 4 bytes each
+    pub fn oam(&self) -> &[u8; 0x100] {
+        &self.oam
+    }
+
+    /// Writes `val` to OAM at OAMADDR and increments OAMADDR,
+    /// used by OAMDATA writes and OAM DMA
+    pub fn write_oam(&mut self, val: u8) {
+        self.oam[self.oam_addr as usize] = val;
+        self.oam_addr = self.oam_addr.wrapping_add(1);
+    }
+
+    pub fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.ctrl);
+        w.write_u8(self.mask);
+        w.write_u8(self.status);
+        w.write_bytes(&self.oam);
+        w.write_u8(self.oam_addr);
+        w.write_bytes(&self.vram);
+        w.write_bytes(&self.palette);
+        w.write_u16(self.vram_addr);
+        w.write_u16(self.temp_vram_addr);
+        w.write_u8(self.fine_x);
+        w.write_bool(self.write_latch);
+        w.write_u8(self.read_buffer);
+        w.write_u8(self.open_bus);
+        w.write_u64(self.open_bus_frame);
+        w.write_u16(self.scanline);
+        w.write_u16(self.dot);
+        w.write_u64(self.frame);
+        w.write_bool(self.sprite_zero_hit_dot.is_some());
+        w.write_u16(self.sprite_zero_hit_dot.unwrap_or(0));
+        w.write_bool(self.nmi_pending);
+        w.write_bytes(&self.frame_buffer);
+    }
+
+    pub fn load_state(&mut self, r: &mut StateReader) -> Result<(), StateError> {
+        self.ctrl = r.read_u8()?;
+        self.mask = r.read_u8()?;
+        self.status = r.read_u8()?;
+        r.read_bytes(&mut self.oam)?;
+        self.oam_addr = r.read_u8()?;
+        r.read_bytes(&mut self.vram)?;
+        r.read_bytes(&mut self.palette)?;
+        self.vram_addr = r.read_u16()?;
+        self.temp_vram_addr = r.read_u16()?;
+        self.fine_x = r.read_u8()?;
+        self.write_latch = r.read_bool()?;
+        self.read_buffer = r.read_u8()?;
+        self.open_bus = r.read_u8()?;
+        self.open_bus_frame = r.read_u64()?;
+        self.scanline = r.read_u16()?;
+        self.dot = r.read_u16()?;
+        self.frame = r.read_u64()?;
+        let has_sprite_zero_hit = r.read_bool()?;
+        let sprite_zero_hit_dot = r.read_u16()?;
+        self.sprite_zero_hit_dot = if has_sprite_zero_hit { Some(sprite_zero_hit_dot) } else { None };
+        self.nmi_pending = r.read_bool()?;
+        r.read_bytes(&mut self.frame_buffer)?;
+        Ok(())
+    }
+
+    /// Returns the open bus latch, which decays to 0 after [`OPEN_BUS_DECAY_FRAMES`] without a refresh
+    fn open_bus(&self) -> u8 {
+        if self.frame.saturating_sub(self.open_bus_frame) >= OPEN_BUS_DECAY_FRAMES {
+            0
+        } else {
+            self.open_bus
+        }
+    }
+
+    /// Drives `val` onto the data bus, refreshing the open bus latch
+    fn refresh_open_bus(&mut self, val: u8) {
+        self.open_bus = val;
+        self.open_bus_frame = self.frame;
+    }
+
+    /// Handles a CPU read of register `reg` (0-7)
+    ///
+    /// Write-only registers return the open bus latch, as do the bits of PPUSTATUS and palette reads
+    /// that are not driven by the PPU
+    /// - PPUSTATUS: clears the vblank flag and resets the PPUSCROLL/PPUADDR write toggle
+    /// - PPUDATA: returns the read buffer and refills it from the VRAM address, then increments the VRAM address.
+    ///   Palette RAM is returned directly, the buffer receives the nametable byte underneath it instead
+    pub fn read_register(&mut self, reg: u8, mapper: &mut dyn Mapper) -> u8 {
+        let res = match reg {
+            2 => {
+                let res = (self.status & 0xE0) | (self.open_bus() & 0x1F);
+                self.status &= !0x80;
+                self.write_latch = false;
+                res
+            }
+            4 => self.oam[self.oam_addr as usize],
+            7 => {
+                let addr = self.vram_addr & 0x3FFF;
+                let res = if addr >= 0x3F00 {
+                    self.read_buffer = self.ppu_load8(addr & 0x2FFF, mapper);
+                    self.ppu_load8(addr, mapper) | (self.open_bus() & 0xC0)
+                } else {
+                    let res = self.read_buffer;
+                    self.read_buffer = self.ppu_load8(addr, mapper);
+                    res
+                };
+                self.increment_vram_addr();
+                res
+            }
+            _ => return self.open_bus(),
+        };
+
+        self.refresh_open_bus(res);
+        res
+    }
+
+    /// Returns the value a CPU read of register `reg` (0-7) would return, without any side effects
+    pub fn peek_register(&self, reg: u8) -> u8 {
+        match reg {
+            2 => (self.status & 0xE0) | (self.open_bus() & 0x1F),
+            4 => self.oam[self.oam_addr as usize],
+            7 if (self.vram_addr & 0x3FFF) >= 0x3F00 => {
+                (self.palette[palette_offset(self.vram_addr)] & self.grayscale_mask()) | (self.open_bus() & 0xC0)
+            }
+            7 => self.read_buffer,
+            _ => self.open_bus(),
+        }
+    }
+
+    /// Handles a CPU write of `val` to register `reg` (0-7)
+    pub fn write_register(&mut self, reg: u8, val: u8, mapper: &mut dyn Mapper) {
+        self.refresh_open_bus(val);
+
+        match reg {
+            0 => {
+                let nmi_output = self.nmi_output();
+                self.ctrl = val;
+                if !nmi_output && self.nmi_output() {
+                    self.nmi_pending = true;
+                }
+                self.temp_vram_addr = (self.temp_vram_addr & !0x0C00) | (((val & 0x03) as u16) << 10);
+            }
+            1 => self.mask = val,
+            3 => self.oam_addr = val,
+            4 => self.write_oam(val),
+            5 => {
+                if !self.write_latch {
+                    self.temp_vram_addr = (self.temp_vram_addr & !0x001F) | (val >> 3) as u16;
+                    self.fine_x = val & 0x07;
+                } else {
+                    self.temp_vram_addr = (self.temp_vram_addr & !0x73E0)
+                        | (((val & 0x07) as u16) << 12)
+                        | (((val & 0xF8) as u16) << 2);
+                }
+                self.write_latch = !self.write_latch;
+            }
+            6 => {
+                if !self.write_latch {
+                    self.temp_vram_addr = (self.temp_vram_addr & 0x00FF) | (((val & 0x3F) as u16) << 8);
+                } else {
+                    self.temp_vram_addr = (self.temp_vram_addr & 0xFF00) | val as u16;
+                    self.vram_addr = self.temp_vram_addr;
+                }
+                self.write_latch = !self.write_latch;
+            }
+            7 => {
+                self.ppu_store8(self.vram_addr, val, mapper);
+                self.increment_vram_addr();
+            }
+            _ => {}
+        }
+    }
+
+    fn increment_vram_addr(&mut self) {
+        let inc = if (self.ctrl & 0x04) != 0 { 32 } else { 1 };
+        self.vram_addr = self.vram_addr.wrapping_add(inc) & 0x7FFF;
+    }
+
+    /// Advances the PPU by a single dot
+    ///
+    /// Visible scanlines are rendered into the frame buffer as a whole at dot 1
+    pub fn step(&mut self, mapper: &mut dyn Mapper) {
+        let rendering = self.rendering_enabled();
+
+        match (self.scanline, self.dot) {
+            (0..=239, 1) => self.render_scanline(mapper),
+            (241, 1) => {
+                self.status |= 0x80;
+                if self.nmi_output() {
+                    self.nmi_pending = true;
+                }
+            }
+            (261, 1) => self.status &= !0xE0,
+            _ => {}
+        }
+
+        if self.sprite_zero_hit_dot == Some(self.dot) {
+            self.status |= 0x40;
+            self.sprite_zero_hit_dot = None;
+        }
+
+        if rendering && (self.scanline < 240 || self.scanline == 261) {
+            match self.dot {
+                256 => self.increment_y(),
+                257 => self.vram_addr = (self.vram_addr & !0x041F) | (self.temp_vram_addr & 0x041F),
+                // with the usual setup (background at 0x0000, sprites at 0x1000), A12 rises once per
+                // scanline when the sprite patterns are fetched
+                260 => mapper.notify_a12_rising(),
+                280..=304 if self.scanline == 261 => {
+                    self.vram_addr = (self.vram_addr & !0x7BE0) | (self.temp_vram_addr & 0x7BE0);
+                }
+                _ => {}
+            }
+        }
+
+        self.dot += 1;
+        // the pre-render scanline is one dot shorter on odd frames while rendering
+        let skip_dot = self.scanline == 261 && self.dot == 340 && rendering && (self.frame & 1) == 1;
+        if self.dot > 340 || skip_dot {
+            self.dot = 0;
+            self.scanline += 1;
+            if self.scanline > 261 {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    /// Renders the current scanline into the frame buffer
+    fn render_scanline(&mut self, mapper: &mut dyn Mapper) {
+        let background = if (self.mask & 0x08) != 0 {
+            self.render_background_line(mapper)
+        } else {
+            [0; SCREEN_WIDTH]
+        };
+        let sprites = if (self.mask & 0x10) != 0 {
+            self.render_sprite_line(mapper)
+        } else {
+            [SpritePixel::default(); SCREEN_WIDTH]
+        };
+
+        let line = self.scanline as usize * SCREEN_WIDTH;
+        for x in 0..SCREEN_WIDTH {
+            let bg = if x < 8 && (self.mask & 0x02) == 0 { 0 } else { background[x] };
+            let sprite = if x < 8 && (self.mask & 0x04) == 0 { SpritePixel::default() } else { sprites[x] };
+
+            let bg_opaque = (bg & 0x03) != 0;
+            let sprite_opaque = (sprite.color & 0x03) != 0;
+
+            // sprite 0 hit is never detected at x=255 and only counts once per frame
+            if sprite.sprite_zero && sprite_opaque && bg_opaque && x != 255
+                && (self.status & 0x40) == 0 && self.sprite_zero_hit_dot.is_none()
+            {
+                // pixel x is output at dot x + 1
+                self.sprite_zero_hit_dot = Some(x as u16 + 1);
+            }
+
+            let pixel = if sprite_opaque && (!bg_opaque || !sprite.behind_background) {
+                sprite.color
+            } else if bg_opaque {
+                bg
+            } else {
+                // transparent pixels show the backdrop color at 0x3F00
+                0
+            };
+            self.frame_buffer[line + x] = self.palette[pixel as usize] & 0x3F;
+        }
+    }
+
+    /// Fetches the background tiles of the current scanline starting at v
+    /// # Returns
+    /// One palette RAM offset (0-15) per pixel, 2-Bit pattern value in bits 0-1, palette in bits 2-3
+    fn render_background_line(&mut self, mapper: &mut dyn Mapper) -> [u8; SCREEN_WIDTH] {
+        let mut res = [0; SCREEN_WIDTH];
+
+        let mut v = self.vram_addr;
+        let fine_y = (v >> 12) & 0x07;
+        let pattern_table = if (self.ctrl & 0x10) != 0 { 0x1000 } else { 0x0000 };
+
+        // 33 tiles are needed when the picture is shifted by fine X
+        for tile in 0..33 {
+            let tile_index = self.ppu_load8(0x2000 | (v & 0x0FFF), mapper);
+            let attribute = self.ppu_load8(0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07), mapper);
+            // each attribute byte covers 4x4 tiles, two bits per 2x2 tile quadrant
+            let shift = ((v >> 4) & 0x04) | (v & 0x02);
+            let palette = ((attribute >> shift) & 0x03) << 2;
+
+            let pattern_addr = pattern_table + tile_index as u16 * 16 + fine_y;
+            let lo = self.ppu_load8(pattern_addr, mapper);
+            let hi = self.ppu_load8(pattern_addr + 8, mapper);
+
+            for bit in 0..8 {
+                let x = (tile * 8 + bit) as isize - self.fine_x as isize;
+                if !(0..SCREEN_WIDTH as isize).contains(&x) {
+                    continue;
+                }
+
+                let pattern = ((lo >> (7 - bit)) & 0x01) | (((hi >> (7 - bit)) & 0x01) << 1);
+                if pattern != 0 {
+                    res[x as usize] = palette | pattern;
+                }
+            }
+
+            // increment coarse X, wrapping into the horizontally adjacent nametable
+            if (v & 0x001F) == 31 {
+                v = (v & !0x001F) ^ 0x0400;
+            } else {
+                v += 1;
+            }
+        }
+
+        res
+    }
+
+    /// Height of sprites in pixels as selected by PPUCTRL
+    fn sprite_height(&self) -> u16 {
+        if (self.ctrl & 0x20) != 0 { 16 } else { 8 }
+    }
+
+    /// Evaluates and fetches the sprites on the current scanline
+    ///
+    /// Only the first eight sprites in OAM order that intersect the scanline are drawn,
+    /// further sprites set the sprite overflow flag
+    fn render_sprite_line(&mut self, mapper: &mut dyn Mapper) -> [SpritePixel; SCREEN_WIDTH] {
+        let mut res = [SpritePixel::default(); SCREEN_WIDTH];
+
+        let height = self.sprite_height();
+        let mut count = 0;
+
+        for index in 0..64 {
+            let sprite = &self.oam[index * 4..index * 4 + 4];
+            // sprite data is delayed by one scanline, so sprites are drawn one line below their Y coordinate
+            let top = sprite[0] as u16 + 1;
+            if self.scanline < top || self.scanline >= top + height {
+                continue;
+            }
+
+            if count == 8 {
+                self.status |= 0x20;
+                break;
+            }
+            count += 1;
+
+            let (tile, attributes, left) = (sprite[1], sprite[2], sprite[3]);
+
+            let mut row = self.scanline - top;
+            if (attributes & 0x80) != 0 {
+                row = height - 1 - row;
+            }
+
+            let pattern_addr = if height == 16 {
+                // 8x16 sprites select the pattern table with bit 0 of the tile index
+                let table = (tile as u16 & 0x01) * 0x1000;
+                let tile = (tile & 0xFE) as u16 + row / 8;
+                table + tile * 16 + (row & 0x07)
+            } else {
+                let table = if (self.ctrl & 0x08) != 0 { 0x1000 } else { 0x0000 };
+                table + tile as u16 * 16 + row
+            };
+            let lo = self.ppu_load8(pattern_addr, mapper);
+            let hi = self.ppu_load8(pattern_addr + 8, mapper);
+
+            let palette = 0x10 | ((attributes & 0x03) << 2);
+
+            for bit in 0..8 {
+                let x = left as usize + bit;
+                // lower OAM indices take precedence, even if they end up behind the background
+                if x >= SCREEN_WIDTH || (res[x].color & 0x03) != 0 {
+                    continue;
+                }
+
+                let shift = if (attributes & 0x40) != 0 { bit } else { 7 - bit };
+                let pattern = ((lo >> shift) & 0x01) | (((hi >> shift) & 0x01) << 1);
+                if pattern != 0 {
+                    res[x] = SpritePixel {
+                        color: palette | pattern,
+                        behind_background: (attributes & 0x20) != 0,
+                        sprite_zero: index == 0,
+                    };
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Increments fine Y in v, overflowing into coarse Y and the vertically adjacent nametable
+    fn increment_y(&mut self) {
+        let mut v = self.vram_addr;
+
+        if (v & 0x7000) != 0x7000 {
+            v += 0x1000;
+        } else {
+            v &= !0x7000;
+            let mut coarse_y = (v & 0x03E0) >> 5;
+            if coarse_y == 29 {
+                coarse_y = 0;
+                v ^= 0x0800;
+            } else if coarse_y == 31 {
+                // coarse Y can be set out of bounds, in which case it wraps without switching nametables
+                coarse_y = 0;
+            } else {
+                coarse_y += 1;
+            }
+            v = (v & !0x03E0) | (coarse_y << 5);
+        }
+
+        self.vram_addr = v;
+    }
+
+    /// Reads a byte from PPU address space
+    fn ppu_load8(&mut self, addr: u16, mapper: &mut dyn Mapper) -> u8 {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => mapper.ppu_load8(addr),
+            0x2000..=0x3EFF => mapper.nametable_load8(addr, &self.vram),
+            _ => self.palette[palette_offset(addr)] & self.grayscale_mask(),
+        }
+    }
+
+    /// Writes a byte to PPU address space
+    fn ppu_store8(&mut self, addr: u16, val: u8, mapper: &mut dyn Mapper) {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => mapper.ppu_store8(addr, val),
+            0x2000..=0x3EFF => mapper.nametable_store8(addr, val, &mut self.vram),
+            _ => self.palette[palette_offset(addr)] = val & 0x3F,
+        }
+    }
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Translates a palette address (0x3F00-0x3FFF) into an offset into the 32 bytes of palette RAM
+///
+/// The first entry of each sprite palette (0x3F10, 0x3F14, 0x3F18, 0x3F1C) is shared
+/// with the corresponding background palette
+fn palette_offset(addr: u16) -> usize {
+    let offset = (addr & 0x1F) as usize;
+    if (offset & 0x13) == 0x10 {
+        offset & 0x0F
+    } else {
+        offset
+    }
+}
+
+/// A single pixel produced by the sprite pipeline
+#[derive(Clone, Copy, Default)]
+struct SpritePixel {
+    /// Palette RAM offset (0x10-0x1F), transparent if bits 0-1 are 0
+    color: u8,
+    /// Priority bit of the sprite, the background is drawn in front if it is opaque
+    behind_background: bool,
+    /// Whether the pixel belongs to sprite 0, used for sprite 0 hit detection
+    sprite_zero: bool,
+}
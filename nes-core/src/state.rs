@@ -0,0 +1,136 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Magic bytes at the start of every save state
+pub const STATE_MAGIC: [u8; 4] = *b"NESS";
+
+/// Version of the save state format, increased whenever the layout of any component's state changes
+pub const STATE_VERSION: u16 = 5;
+
+/// Errors that can occur while loading a save state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with [`STATE_MAGIC`]
+    InvalidMagic,
+    /// The save state was written by an incompatible version of the emulator
+    UnsupportedVersion(u16),
+    /// The data is shorter than the state it describes
+    UnexpectedEof,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::InvalidMagic => write!(f, "Not a save state"),
+            StateError::UnsupportedVersion(version) => write!(f, "Unsupported save state version {} (expected {})", version, STATE_VERSION),
+            StateError::UnexpectedEof => write!(f, "Unexpected end of save state"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+/// Serializes component state into a byte buffer (little endian)
+///
+/// The components write their fields in a fixed order and read them back in the same order
+/// with a [`StateReader`], there is no per field tagging
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::new(),
+        }
+    }
+
+    pub fn write_u8(&mut self, val: u8) {
+        self.data.push(val);
+    }
+
+    pub fn write_u16(&mut self, val: u16) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, val: u32) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, val: u64) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_bool(&mut self, val: bool) {
+        self.write_u8(val as u8);
+    }
+
+    pub fn write_bytes(&mut self, val: &[u8]) {
+        self.data.extend_from_slice(val);
+    }
+
+    /// Returns the serialized state
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads state written by a [`StateWriter`]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+        }
+    }
+
+    /// Fills `out` with the next `out.len()` bytes
+    pub fn read_bytes(&mut self, out: &mut [u8]) -> Result<(), StateError> {
+        if self.data.len() < out.len() {
+            return Err(StateError::UnexpectedEof);
+        }
+
+        let (bytes, rest) = self.data.split_at(out.len());
+        out.copy_from_slice(bytes);
+        self.data = rest;
+        Ok(())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, StateError> {
+        let mut buf = [0; 1];
+        self.read_bytes(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, StateError> {
+        let mut buf = [0; 2];
+        self.read_bytes(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, StateError> {
+        let mut buf = [0; 4];
+        self.read_bytes(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, StateError> {
+        let mut buf = [0; 8];
+        self.read_bytes(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.read_u8()? != 0)
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{bus::{Bus, RamInit}, cpu::{Cpu, Region}, mappers::Mapper, state::{StateError, StateReader, StateWriter, STATE_MAGIC, STATE_VERSION}};
+
+/// The whole console, keeps the CPU and the devices on the [`Bus`] in sync
+/// 
+/// The CPU drives the schedule: after every instruction (or interrupt sequence), the PPU and the APU are advanced
+/// by the CPU cycles that passed. The PPU dots per CPU cycle follow from the master clock dividers of the region
+/// ([`Region::cpu_clock_div`] / [`Region::ppu_clock_div`]), 3 on NTSC and 3.2 on PAL
+pub struct System {
+    cpu: Cpu,
+    bus: Bus,
+}
+
+impl System {
+    /// Creates an NTSC console with the cartridge `mapper` inserted, [`System::power_on`] still has to be called
+    pub fn new(mapper: Box<dyn Mapper>) -> Self {
+        Self::with_region(Region::Ntsc, mapper)
+    }
+
+    /// Creates a console with the CPU and PPU timing of `region` and the cartridge `mapper` inserted,
+    /// [`System::power_on`] still has to be called
+    ///
+    /// The APU always uses NTSC rates
+    pub fn with_region(region: Region, mapper: Box<dyn Mapper>) -> Self {
+        Self {
+            cpu: Cpu::with_region(region),
+            bus: Bus::new(mapper),
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+
+    /// Powers on the console with zeroed RAM, see [`Cpu::power_on`]
+    pub fn power_on(&mut self) {
+        self.power_on_with_ram_pattern(RamInit::Zeroed);
+    }
+
+    /// Powers on the console with the CPU RAM filled according to `pattern`, see [`Cpu::power_on`]
+    pub fn power_on_with_ram_pattern(&mut self, pattern: RamInit) {
+        self.bus.fill_ram(pattern);
+        self.cpu.power_on(&mut self.bus);
+    }
+
+    /// Presses the reset button, see [`Cpu::reset`] and [`Mapper::reset`]
+    pub fn reset(&mut self) {
+        self.bus.mapper_mut().reset();
+        self.cpu.reset(&mut self.bus);
+    }
+
+    /// Presses the reset button, but starts executing at `pc`, see [`Cpu::reset_to`] and [`Mapper::reset`]
+    pub fn reset_to(&mut self, pc: u16) {
+        self.bus.mapper_mut().reset();
+        self.cpu.reset_to(&mut self.bus, pc);
+    }
+
+    /// Serializes the whole machine state (CPU, RAM, PPU, APU, controllers and cartridge)
+    ///
+    /// The state starts with [`STATE_MAGIC`] and [`STATE_VERSION`]. It can only be loaded into a console
+    /// with the same cartridge, ROM contents are not included
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        w.write_bytes(&STATE_MAGIC);
+        w.write_u16(STATE_VERSION);
+
+        self.cpu.save_state(&mut w);
+        self.bus.save_state(&mut w);
+
+        w.into_bytes()
+    }
+
+    /// Restores a state returned by [`System::save_state`]
+    ///
+    /// The header is checked before anything is restored, so an invalid magic or version leaves the console untouched.
+    /// If the data is truncated, the console is left partially restored
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut r = StateReader::new(data);
+
+        let mut magic = [0; 4];
+        r.read_bytes(&mut magic)?;
+        if magic != STATE_MAGIC {
+            return Err(StateError::InvalidMagic);
+        }
+
+        let version = r.read_u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        self.cpu.load_state(&mut r)?;
+        self.bus.load_state(&mut r)
+    }
+
+    /// Services pending interrupts, then executes a single CPU instruction and
+    /// catches up the PPU and APU
+    /// 
+    /// A halted CPU still lets one cycle pass, so the rest of the console keeps running
+    /// # Returns
+    /// The number of cpu cycles that passed
+    pub fn step(&mut self) -> u64 {
+        let start_cycle = self.cpu.cycles();
+
+        if self.bus.ppu_mut().nmi_pending() {
+            self.cpu.nmi(&mut self.bus);
+        } else if self.bus.mapper().irq_pending() || self.bus.apu().irq_pending() {
+            self.cpu.irq(&mut self.bus);
+        }
+
+        self.cpu.execute_single_instruction(&mut self.bus);
+
+        let cycles = (self.cpu.cycles() - start_cycle).max(1);
+        let cpu_clock_div = self.cpu.region().cpu_clock_div();
+        let ppu_clock_div = self.cpu.region().ppu_clock_div();
+        for cycle in start_cycle..start_cycle + cycles {
+            // derived from the master clock, so fractional dots per cycle (PAL) are carried over
+            let dots = (cycle + 1) * cpu_clock_div / ppu_clock_div - cycle * cpu_clock_div / ppu_clock_div;
+            for _ in 0..dots {
+                self.bus.step_ppu();
+            }
+            self.bus.apu_mut().step();
+        }
+
+        cycles
+    }
+
+    /// Performs `count` [`System::step`]s, stops early if the CPU gets halted
+    /// # Returns
+    /// The number of cpu cycles that passed
+    pub fn run_instructions(&mut self, count: u64) -> u64 {
+        let mut executed = 0;
+
+        for _ in 0..count {
+            if self.cpu.is_halted() {
+                break;
+            }
+            executed += self.step();
+        }
+
+        executed
+    }
+
+    /// Runs the console until the PPU has finished the current frame
+    /// 
+    /// The frame is complete in the PPU's frame buffer afterwards.
+    /// Since whole instructions are executed, the PPU may already be a few dots into the next frame
+    pub fn step_frame(&mut self) {
+        let frame = self.bus.ppu().frame();
+
+        while self.bus.ppu().frame() == frame {
+            self.step();
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{cpu::RESET_VECTOR, memory::Memory};
+
+/// Flat 64 KB of RAM without any mirroring or side effects, for testing the CPU in isolation
+pub struct FlatMemory {
+    ram: Box<[u8; 0x10000]>,
+}
+
+impl FlatMemory {
+    /// Creates a memory filled with zeros
+    pub fn new() -> Self {
+        Self {
+            ram: Box::new([0; 0x10000]),
+        }
+    }
+
+    /// Copies `data` to `addr`, wrapping around at 0xFFFF
+    pub fn load(&mut self, addr: u16, data: &[u8]) {
+        for (i, &val) in data.iter().enumerate() {
+            self.ram[addr.wrapping_add(i as u16) as usize] = val;
+        }
+    }
+
+    /// Points the reset vector (0xFFFC) at `addr`
+    pub fn set_reset_vector(&mut self, addr: u16) {
+        self.load(RESET_VECTOR, &addr.to_le_bytes());
+    }
+
+    /// The whole address space
+    pub fn ram(&self) -> &[u8; 0x10000] {
+        &self.ram
+    }
+
+    /// The whole address space
+    pub fn ram_mut(&mut self) -> &mut [u8; 0x10000] {
+        &mut self.ram
+    }
+}
+
+impl Default for FlatMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory for FlatMemory {
+    fn cpu_load8(&mut self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn cpu_store8(&mut self, addr: u16, val: u8) {
+        self.ram[addr as usize] = val;
+    }
+
+    fn cpu_peek8(&self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+}
+
+/// Target of a branch in an [`Assembler`] program, created by [`Assembler::label`] or [`Assembler::forward_label`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// Builds small 6502 programs for tests, without hand encoding opcodes and branch offsets
+///
+/// Only the common instructions and addressing modes are covered, anything else can be emitted
+/// with [`Assembler::bytes`]
+/// ```
+/// # use nes_core::testutil::Assembler;
+/// let mut asm = Assembler::new(0x8000);
+/// asm.ldx_imm(0x03);
+/// let lp = asm.label();
+/// asm.dex();
+/// asm.bne(lp);
+/// assert_eq!(asm.assemble(), [0xA2, 0x03, 0xCA, 0xD0, 0xFD]);
+/// ```
+pub struct Assembler {
+    origin: u16,
+    code: Vec<u8>,
+    /// Address of every label, `None` until the label is bound
+    labels: Vec<Option<u16>>,
+    /// Offsets in `code` of branch operands that still have to be resolved
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    /// Creates an empty program that will be loaded at `origin`
+    pub fn new(origin: u16) -> Self {
+        Self {
+            origin,
+            code: Vec::new(),
+            labels: Vec::new(),
+            fixups: Vec::new(),
+        }
+    }
+
+    /// Address the program will be loaded at
+    pub fn origin(&self) -> u16 {
+        self.origin
+    }
+
+    /// Address of the next emitted byte
+    pub fn addr(&self) -> u16 {
+        self.origin.wrapping_add(self.code.len() as u16)
+    }
+
+    /// Returns a label at the current address, for backward branches
+    pub fn label(&mut self) -> Label {
+        self.labels.push(Some(self.addr()));
+        Label(self.labels.len() - 1)
+    }
+
+    /// Returns a label that is placed later with [`Assembler::bind`], for forward branches
+    pub fn forward_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Places `label` at the current address
+    /// # Panics
+    /// If the label has already been placed
+    pub fn bind(&mut self, label: Label) {
+        assert!(self.labels[label.0].is_none(), "label bound twice");
+        self.labels[label.0] = Some(self.addr());
+    }
+
+    /// Resolves all branches and returns the machine code
+    /// # Panics
+    /// If a branch target was never bound or is out of range
+    pub fn assemble(mut self) -> Vec<u8> {
+        for &(offset, label) in &self.fixups {
+            let target = self.labels[label.0].expect("branch to a label that was never bound");
+            // the offset is relative to the instruction following the branch
+            let next = self.origin.wrapping_add(offset as u16 + 1);
+            let distance = target.wrapping_sub(next) as i16;
+            assert!((-128..=127).contains(&distance), "branch target out of range");
+            self.code[offset] = distance as u8;
+        }
+
+        self.code
+    }
+
+    /// Emits raw bytes
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.code.extend_from_slice(data);
+    }
+
+    fn implied(&mut self, opcode: u8) {
+        self.code.push(opcode);
+    }
+
+    fn imm(&mut self, opcode: u8, val: u8) {
+        self.code.extend_from_slice(&[opcode, val]);
+    }
+
+    fn abs(&mut self, opcode: u8, addr: u16) {
+        self.code.push(opcode);
+        self.code.extend_from_slice(&addr.to_le_bytes());
+    }
+
+    fn branch(&mut self, opcode: u8, target: Label) {
+        self.code.extend_from_slice(&[opcode, 0]);
+        self.fixups.push((self.code.len() - 1, target));
+    }
+
+    /// `LDA #val`
+    pub fn lda_imm(&mut self, val: u8) {
+        self.imm(0xA9, val);
+    }
+
+    /// `LDX #val`
+    pub fn ldx_imm(&mut self, val: u8) {
+        self.imm(0xA2, val);
+    }
+
+    /// `LDY #val`
+    pub fn ldy_imm(&mut self, val: u8) {
+        self.imm(0xA0, val);
+    }
+
+    /// `ADC #val`
+    pub fn adc_imm(&mut self, val: u8) {
+        self.imm(0x69, val);
+    }
+
+    /// `SBC #val`
+    pub fn sbc_imm(&mut self, val: u8) {
+        self.imm(0xE9, val);
+    }
+
+    /// `AND #val`
+    pub fn and_imm(&mut self, val: u8) {
+        self.imm(0x29, val);
+    }
+
+    /// `ORA #val`
+    pub fn ora_imm(&mut self, val: u8) {
+        self.imm(0x09, val);
+    }
+
+    /// `EOR #val`
+    pub fn eor_imm(&mut self, val: u8) {
+        self.imm(0x49, val);
+    }
+
+    /// `CMP #val`
+    pub fn cmp_imm(&mut self, val: u8) {
+        self.imm(0xC9, val);
+    }
+
+    /// `CPX #val`
+    pub fn cpx_imm(&mut self, val: u8) {
+        self.imm(0xE0, val);
+    }
+
+    /// `CPY #val`
+    pub fn cpy_imm(&mut self, val: u8) {
+        self.imm(0xC0, val);
+    }
+
+    /// `LDA addr`
+    pub fn lda_abs(&mut self, addr: u16) {
+        self.abs(0xAD, addr);
+    }
+
+    /// `LDX addr`
+    pub fn ldx_abs(&mut self, addr: u16) {
+        self.abs(0xAE, addr);
+    }
+
+    /// `LDY addr`
+    pub fn ldy_abs(&mut self, addr: u16) {
+        self.abs(0xAC, addr);
+    }
+
+    /// `STA addr`
+    pub fn sta_abs(&mut self, addr: u16) {
+        self.abs(0x8D, addr);
+    }
+
+    /// `STX addr`
+    pub fn stx_abs(&mut self, addr: u16) {
+        self.abs(0x8E, addr);
+    }
+
+    /// `STY addr`
+    pub fn sty_abs(&mut self, addr: u16) {
+        self.abs(0x8C, addr);
+    }
+
+    /// `ADC addr`
+    pub fn adc_abs(&mut self, addr: u16) {
+        self.abs(0x6D, addr);
+    }
+
+    /// `INC addr`
+    pub fn inc_abs(&mut self, addr: u16) {
+        self.abs(0xEE, addr);
+    }
+
+    /// `DEC addr`
+    pub fn dec_abs(&mut self, addr: u16) {
+        self.abs(0xCE, addr);
+    }
+
+    /// `LDA addr,X`
+    pub fn lda_abs_x(&mut self, addr: u16) {
+        self.abs(0xBD, addr);
+    }
+
+    /// `STA addr,X`
+    pub fn sta_abs_x(&mut self, addr: u16) {
+        self.abs(0x9D, addr);
+    }
+
+    /// `JMP addr`
+    pub fn jmp(&mut self, addr: u16) {
+        self.abs(0x4C, addr);
+    }
+
+    /// `JSR addr`
+    pub fn jsr(&mut self, addr: u16) {
+        self.abs(0x20, addr);
+    }
+
+    /// `RTS`
+    pub fn rts(&mut self) {
+        self.implied(0x60);
+    }
+
+    /// `RTI`
+    pub fn rti(&mut self) {
+        self.implied(0x40);
+    }
+
+    /// `BRK`
+    pub fn brk(&mut self) {
+        self.implied(0x00);
+    }
+
+    /// `NOP`
+    pub fn nop(&mut self) {
+        self.implied(0xEA);
+    }
+
+    /// `INX`
+    pub fn inx(&mut self) {
+        self.implied(0xE8);
+    }
+
+    /// `INY`
+    pub fn iny(&mut self) {
+        self.implied(0xC8);
+    }
+
+    /// `DEX`
+    pub fn dex(&mut self) {
+        self.implied(0xCA);
+    }
+
+    /// `DEY`
+    pub fn dey(&mut self) {
+        self.implied(0x88);
+    }
+
+    /// `TAX`
+    pub fn tax(&mut self) {
+        self.implied(0xAA);
+    }
+
+    /// `TXA`
+    pub fn txa(&mut self) {
+        self.implied(0x8A);
+    }
+
+    /// `TAY`
+    pub fn tay(&mut self) {
+        self.implied(0xA8);
+    }
+
+    /// `TYA`
+    pub fn tya(&mut self) {
+        self.implied(0x98);
+    }
+
+    /// `PHA`
+    pub fn pha(&mut self) {
+        self.implied(0x48);
+    }
+
+    /// `PLA`
+    pub fn pla(&mut self) {
+        self.implied(0x68);
+    }
+
+    /// `PHP`
+    pub fn php(&mut self) {
+        self.implied(0x08);
+    }
+
+    /// `PLP`
+    pub fn plp(&mut self) {
+        self.implied(0x28);
+    }
+
+    /// `CLC`
+    pub fn clc(&mut self) {
+        self.implied(0x18);
+    }
+
+    /// `SEC`
+    pub fn sec(&mut self) {
+        self.implied(0x38);
+    }
+
+    /// `CLI`
+    pub fn cli(&mut self) {
+        self.implied(0x58);
+    }
+
+    /// `SEI`
+    pub fn sei(&mut self) {
+        self.implied(0x78);
+    }
+
+    /// `CLV`
+    pub fn clv(&mut self) {
+        self.implied(0xB8);
+    }
+
+    /// `BNE target`
+    pub fn bne(&mut self, target: Label) {
+        self.branch(0xD0, target);
+    }
+
+    /// `BEQ target`
+    pub fn beq(&mut self, target: Label) {
+        self.branch(0xF0, target);
+    }
+
+    /// `BCC target`
+    pub fn bcc(&mut self, target: Label) {
+        self.branch(0x90, target);
+    }
+
+    /// `BCS target`
+    pub fn bcs(&mut self, target: Label) {
+        self.branch(0xB0, target);
+    }
+
+    /// `BPL target`
+    pub fn bpl(&mut self, target: Label) {
+        self.branch(0x10, target);
+    }
+
+    /// `BMI target`
+    pub fn bmi(&mut self, target: Label) {
+        self.branch(0x30, target);
+    }
+
+    /// `BVC target`
+    pub fn bvc(&mut self, target: Label) {
+        self.branch(0x50, target);
+    }
+
+    /// `BVS target`
+    pub fn bvs(&mut self, target: Label) {
+        self.branch(0x70, target);
+    }
+}
@@ -0,0 +1,201 @@
+//! Tests of the APU channels, frame counter and resampler through the public register interface
+
+use nes_core::apu::{Apu, CPU_FREQUENCY};
+
+/// Steps the APU for `cycles` CPU cycles and returns the lengths of the runs of silent and audible output
+///
+/// The idle triangle holds its DC level of 15, so silence is the output of a freshly reset APU rather than 0.
+fn output_runs(apu: &mut Apu, cycles: usize) -> Vec<(bool, usize)> {
+    let silence = Apu::new().output();
+    let mut runs: Vec<(bool, usize)> = Vec::new();
+    for _ in 0..cycles {
+        apu.step();
+        let audible = apu.output() > silence;
+        match runs.last_mut() {
+            Some((level, len)) if *level == audible => *len += 1,
+            _ => runs.push((audible, 1)),
+        }
+    }
+    runs
+}
+
+/// Plays pulse 1 with the given duty at constant volume 15 and timer period 8
+fn start_pulse(duty: u8) -> Apu {
+    let mut apu = Apu::new();
+    apu.write_register(0x4015, 0x01);
+    apu.write_register(0x4000, (duty << 6) | 0x3F);
+    apu.write_register(0x4002, 0x08);
+    apu.write_register(0x4003, 0x08);
+    apu
+}
+
+/// Plays the noise channel at constant volume 15 with the shortest period and returns bit 0 of the first `count` shift register states
+fn noise_bits(short_mode: bool, count: usize) -> Vec<u8> {
+    let silence = Apu::new().output();
+    let mut apu = Apu::new();
+    apu.write_register(0x4015, 0x08);
+    apu.write_register(0x400C, 0x3F);
+    apu.write_register(0x400E, if short_mode { 0x80 } else { 0x00 });
+    apu.write_register(0x400F, 0x08);
+
+    // The first shift happens on the first APU cycle, then every (period + 1) APU cycles = 10 CPU cycles
+    apu.step();
+    (0..count)
+        .map(|_| {
+            apu.step();
+            let bit = if apu.output() > silence { 0 } else { 1 };
+            for _ in 0..9 {
+                apu.step();
+            }
+            bit
+        })
+        .collect()
+}
+
+#[test]
+fn pulse_output_follows_duty_cycle() {
+    // Each sequencer step lasts (period + 1) APU cycles = 18 CPU cycles
+    const STEP: usize = 18;
+    for &(duty, high_steps) in &[(0, 1), (1, 2), (2, 4)] {
+        let mut apu = start_pulse(duty);
+        let runs = output_runs(&mut apu, 20 * 8 * STEP);
+
+        // The first and last run are cut off by the start and end of the measurement
+        for &(audible, len) in &runs[1..runs.len() - 1] {
+            let expected = if audible { high_steps } else { 8 - high_steps };
+            assert_eq!(len, expected * STEP, "duty {}", duty);
+        }
+    }
+
+    // Duty 3 is the negated 25% waveform
+    let mut apu = start_pulse(3);
+    let runs = output_runs(&mut apu, 20 * 8 * STEP);
+    for &(audible, len) in &runs[1..runs.len() - 1] {
+        assert_eq!(len, if audible { 6 } else { 2 } * STEP);
+    }
+}
+
+#[test]
+fn triangle_linear_counter_gates_sequencer() {
+    let mut apu = Apu::new();
+    apu.write_register(0x4015, 0x04);
+    // Linear counter reload value 2, loaded on the next quarter frame and then counted down on the following two
+    apu.write_register(0x4008, 0x02);
+    apu.write_register(0x400A, 0x10);
+    apu.write_register(0x400B, 0x08);
+
+    let outputs: Vec<f32> = (0..29000)
+        .map(|_| {
+            apu.step();
+            apu.output()
+        })
+        .collect();
+
+    // Quarter frames happen at CPU cycles 7457, 14913 and 22371
+    assert!(outputs[..7457].iter().all(|&o| o == outputs[0]));
+    assert!(outputs[7457..22371].windows(2).any(|w| w[0] != w[1]));
+    assert!(outputs[22371..].iter().all(|&o| o == outputs[22371]));
+}
+
+#[test]
+fn noise_lfsr_produces_expected_sequence() {
+    // Shift register seeded with 1, feedback from bits 0 and 1
+    let long: [u8; 48] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 0, 0,
+    ];
+    assert_eq!(noise_bits(false, 48), long);
+
+    // Short mode takes the feedback from bits 0 and 6
+    let short: [u8; 48] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 1,
+    ];
+    assert_eq!(noise_bits(true, 48), short);
+}
+
+#[test]
+fn frame_irq_is_raised_at_end_of_4_step_sequence() {
+    let mut apu = Apu::new();
+    apu.write_register(0x4017, 0x00);
+
+    for _ in 0..29828 {
+        apu.step();
+    }
+    assert!(!apu.irq_pending());
+    apu.step();
+    assert!(apu.irq_pending());
+    assert_eq!(apu.peek_status() & 0x40, 0x40);
+
+    // Reading the status clears the IRQ
+    assert_eq!(apu.read_status() & 0x40, 0x40);
+    assert!(!apu.irq_pending());
+
+    // The next IRQ follows one full sequence later
+    for _ in 0..29830 {
+        apu.step();
+    }
+    assert!(apu.irq_pending());
+
+    // Setting the inhibit flag clears a pending IRQ and suppresses further ones
+    apu.write_register(0x4017, 0x40);
+    assert!(!apu.irq_pending());
+    for _ in 0..2 * 29830 {
+        apu.step();
+        assert!(!apu.irq_pending());
+    }
+}
+
+#[test]
+fn five_step_mode_never_raises_irq() {
+    let mut apu = Apu::new();
+    apu.write_register(0x4017, 0x80);
+    for _ in 0..2 * 37282 {
+        apu.step();
+        assert!(!apu.irq_pending());
+    }
+
+    // Switching back to 4-step mode restarts the sequence
+    apu.write_register(0x4017, 0x00);
+    for _ in 0..29829 {
+        apu.step();
+    }
+    assert!(apu.irq_pending());
+}
+
+#[test]
+fn five_step_write_clocks_length_counters_immediately() {
+    let mut apu = Apu::new();
+    apu.write_register(0x4015, 0x01);
+    apu.write_register(0x4000, 0x10);
+    // Length counter index 3 loads a length of 2
+    apu.write_register(0x4003, 0x18);
+    assert_eq!(apu.peek_status() & 0x01, 0x01);
+
+    apu.write_register(0x4017, 0x80);
+    assert_eq!(apu.peek_status() & 0x01, 0x01);
+    apu.write_register(0x4017, 0x80);
+    assert_eq!(apu.peek_status() & 0x01, 0x00);
+}
+
+#[test]
+fn resampler_produces_target_rate_samples_per_second() {
+    for &rate in &[44100, 48000] {
+        let mut apu = start_pulse(2);
+        apu.set_sample_rate(rate);
+
+        let mut samples = Vec::new();
+        for _ in 0..CPU_FREQUENCY {
+            apu.step();
+        }
+        apu.drain_samples(&mut samples);
+
+        let count = samples.len() as i64;
+        assert!((count - rate as i64).abs() <= 1, "{} samples at {} Hz", count, rate);
+        assert!(samples.iter().all(|&s| (0.0..=1.0).contains(&s)));
+
+        // Draining moves the samples out of the APU
+        apu.drain_samples(&mut samples);
+        assert_eq!(samples.len() as i64, count);
+    }
+}
@@ -0,0 +1,142 @@
+//! Tests of the CPU address space decoding in the Bus
+
+use nes_core::{bus::Bus, cpu::{Cpu, StopReason}, mappers::Mapper000, memory::{AccessKind, Memory, WatchpointHit}};
+
+fn bus() -> Bus {
+    Bus::new(Box::new(Mapper000::default()))
+}
+
+/// Loads `program` into CPU RAM at 0x0300 and resets the CPU to it
+fn cpu_with_program(bus: &mut Bus, program: &[u8]) -> Cpu {
+    for (i, &val) in program.iter().enumerate() {
+        bus.cpu_store8(0x0300 + i as u16, val);
+    }
+
+    let mut cpu = Cpu::new();
+    cpu.reset_to(bus, 0x0300);
+    cpu
+}
+
+#[test]
+fn ppu_registers_are_mirrored_every_8_bytes() {
+    let mut bus = bus();
+
+    // PPUCTRL
+    bus.cpu_store8(0x3FF8, 0x80);
+    assert_eq!(bus.ppu().ctrl(), 0x80);
+
+    // OAMADDR, OAMDATA
+    bus.cpu_store8(0x200B, 0x10);
+    bus.cpu_store8(0x3FFC, 0x55);
+    assert_eq!(bus.ppu().oam()[0x10], 0x55);
+
+    bus.cpu_store8(0x2003, 0x10);
+    assert_eq!(bus.cpu_load8(0x3FF4), 0x55);
+}
+
+#[test]
+fn unmapped_reads_return_open_bus() {
+    let mut bus = bus();
+
+    bus.cpu_store8(0x0010, 0x5A);
+    assert_eq!(bus.cpu_load8(0x0010), 0x5A);
+
+    // unused I/O space and cartridge space without PRG RAM
+    assert_eq!(bus.cpu_load8(0x4018), 0x5A);
+    assert_eq!(bus.cpu_load8(0x5000), 0x5A);
+
+    // write only APU register
+    bus.cpu_store8(0x0010, 0xC3);
+    bus.cpu_load8(0x0010);
+    assert_eq!(bus.cpu_load8(0x4000), 0xC3);
+
+    // the controller port only drives bit 0
+    assert_eq!(bus.cpu_load8(0x4016) & 0xE0, 0xC0);
+}
+
+#[test]
+fn oam_dma_copies_page_and_stalls_cpu() {
+    let mut bus = bus();
+    for i in 0..0x100 {
+        bus.cpu_store8(0x0200 + i, i as u8);
+    }
+
+    // LDA #$02; STA $4014
+    let mut cpu = cpu_with_program(&mut bus, &[0xA9, 0x02, 0x8D, 0x14, 0x40]);
+    cpu.execute_single_instruction(&mut bus);
+    // the store ends on cycle 7 + 2 + 4 = 13, the odd cycle adds an alignment cycle
+    assert_eq!(cpu.execute_single_instruction(&mut bus), 4 + 514);
+    assert!(bus.ppu().oam().iter().enumerate().all(|(i, &val)| val == i as u8));
+
+    // LDA #$02; LDX $00; STA $4014
+    let mut cpu = cpu_with_program(&mut bus, &[0xA9, 0x02, 0xA6, 0x00, 0x8D, 0x14, 0x40]);
+    cpu.run_instructions(&mut bus, 2);
+    // the store ends on cycle 7 + 2 + 3 + 4 = 16
+    assert_eq!(cpu.execute_single_instruction(&mut bus), 4 + 513);
+}
+
+#[test]
+fn watchpoints_stop_execution_after_the_access() {
+    let mut bus = bus();
+    let mut cpu = cpu_with_program(
+        &mut bus,
+        &[
+            0xA9, 0x42, // LDA #$42
+            0x85, 0x10, // STA $10
+            0xA6, 0x10, // LDX $10
+            0x02, // JAM
+        ],
+    );
+    bus.add_watchpoint(0x0010, true, true);
+
+    let hit = WatchpointHit { addr: 0x0010, access: AccessKind::Write };
+    assert_eq!(cpu.run_until_breakpoint(&mut bus), StopReason::Watchpoint(hit));
+    assert_eq!(cpu.pc(), 0x0304);
+
+    let hit = WatchpointHit { addr: 0x0010, access: AccessKind::Read };
+    assert_eq!(cpu.run_until_breakpoint(&mut bus), StopReason::Watchpoint(hit));
+    assert_eq!(cpu.pc(), 0x0306);
+    assert_eq!(cpu.x(), 0x42);
+
+    assert_eq!(cpu.run_until_breakpoint(&mut bus), StopReason::Halted);
+}
+
+#[test]
+fn watchpoints_only_report_the_watched_access_kind() {
+    let mut bus = bus();
+    bus.add_watchpoint(0x0010, false, true);
+
+    bus.cpu_load8(0x0010);
+    assert_eq!(bus.take_watchpoint_hit(), None);
+
+    // Accesses are matched before mirroring
+    bus.cpu_store8(0x0810, 0x01);
+    assert_eq!(bus.take_watchpoint_hit(), None);
+
+    bus.cpu_store8(0x0010, 0x01);
+    assert_eq!(
+        bus.take_watchpoint_hit(),
+        Some(WatchpointHit { addr: 0x0010, access: AccessKind::Write })
+    );
+    assert_eq!(bus.take_watchpoint_hit(), None);
+
+    bus.remove_watchpoint(0x0010);
+    bus.cpu_store8(0x0010, 0x02);
+    assert_eq!(bus.take_watchpoint_hit(), None);
+}
+
+#[test]
+fn read_range_wraps_around_address_space() {
+    let mut bus = bus();
+    for i in 0..8 {
+        bus.cpu_store8(i, 0xA0 + i as u8);
+    }
+
+    let mut out = [0; 16];
+    bus.read_range(0xFFF8, 16, &mut out);
+
+    for (i, &val) in out[..8].iter().enumerate() {
+        assert_eq!(val, bus.cpu_peek8(0xFFF8 + i as u16));
+    }
+    assert_eq!(out[8..], [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7]);
+}
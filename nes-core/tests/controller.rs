@@ -0,0 +1,41 @@
+//! Tests of the standard controller's strobe and shift register
+
+use nes_core::controller::{Button, Controller};
+
+/// Reads `count` bits from `controller`
+fn read_bits(controller: &mut Controller, count: usize) -> Vec<u8> {
+    (0..count).map(|_| controller.read()).collect()
+}
+
+#[test]
+fn buttons_are_shifted_out_in_order() {
+    let mut controller = Controller::new();
+    controller.set_button(Button::A, true);
+    controller.set_button(Button::Start, true);
+    controller.set_button(Button::Right, true);
+
+    controller.write(1);
+    controller.write(0);
+
+    // A, B, Select, Start, Up, Down, Left, Right
+    assert_eq!(read_bits(&mut controller, 8), [1, 0, 0, 1, 0, 0, 0, 1]);
+    // the shift register is empty, further reads return 1
+    assert_eq!(read_bits(&mut controller, 4), [1, 1, 1, 1]);
+}
+
+#[test]
+fn strobe_keeps_returning_button_a() {
+    let mut controller = Controller::new();
+    controller.set_button(Button::B, true);
+
+    controller.write(1);
+    assert_eq!(read_bits(&mut controller, 3), [0, 0, 0]);
+
+    controller.set_button(Button::A, true);
+    assert_eq!(read_bits(&mut controller, 3), [1, 1, 1]);
+
+    // releasing the strobe latches the buttons again
+    controller.write(0);
+    assert_eq!(read_bits(&mut controller, 2), [1, 1]);
+    assert_eq!(controller.read(), 0);
+}
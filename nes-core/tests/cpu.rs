@@ -0,0 +1,645 @@
+//! Instruction level tests of the CPU on a flat memory
+
+use std::{cell::RefCell, rc::Rc};
+
+use nes_core::{cpu::{disassemble, Cpu, Region, StopReason}, testutil::{Assembler, FlatMemory}};
+
+const CARRY: u8 = 0x01;
+const ZERO: u8 = 0x02;
+const OVERFLOW: u8 = 0x40;
+const NEGATIVE: u8 = 0x80;
+
+/// Loads `program` at `addr` and powers on a CPU that starts executing it
+fn setup(addr: u16, program: &[u8]) -> (Cpu, FlatMemory) {
+    let mut memory = FlatMemory::new();
+    memory.load(addr, program);
+    memory.set_reset_vector(addr);
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut memory);
+
+    (cpu, memory)
+}
+
+#[test]
+fn adc_sets_carry_on_unsigned_overflow() {
+    // LDA #$FF; ADC #$01
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0xFF, 0x69, 0x01]);
+    cpu.run_instructions(&mut memory, 2);
+
+    assert_eq!(cpu.a(), 0x00);
+    assert_eq!(cpu.status() & (CARRY | ZERO | OVERFLOW | NEGATIVE), CARRY | ZERO);
+}
+
+#[test]
+fn adc_sets_overflow_on_signed_overflow() {
+    // LDA #$7F; ADC #$01
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x7F, 0x69, 0x01]);
+    cpu.run_instructions(&mut memory, 2);
+
+    assert_eq!(cpu.a(), 0x80);
+    assert_eq!(cpu.status() & (CARRY | ZERO | OVERFLOW | NEGATIVE), OVERFLOW | NEGATIVE);
+}
+
+#[test]
+fn adc_adds_carry_in() {
+    // SEC; LDA #$01; ADC #$01
+    let (mut cpu, mut memory) = setup(0x8000, &[0x38, 0xA9, 0x01, 0x69, 0x01]);
+    cpu.run_instructions(&mut memory, 3);
+
+    assert_eq!(cpu.a(), 0x03);
+    assert_eq!(cpu.status() & CARRY, 0);
+}
+
+/// The 6502 does not carry into the pointer's high byte when fetching the target of an indirect JMP,
+/// a pointer at 0xXXFF takes its high byte from 0xXX00 instead of the next page
+#[test]
+fn jmp_indirect_does_not_cross_page() {
+    // JMP ($02FF)
+    let (mut cpu, mut memory) = setup(0x8000, &[0x6C, 0xFF, 0x02]);
+    memory.load(0x02FF, &[0x34]);
+    memory.load(0x0200, &[0x12]);
+    memory.load(0x0300, &[0x56]);
+
+    cpu.execute_single_instruction(&mut memory);
+
+    assert_eq!(cpu.pc(), 0x1234, "high byte has to be read from 0x0200, not 0x0300");
+    assert_eq!(cpu.cycles(), 7 + 5);
+}
+
+#[test]
+fn branch_not_taken_takes_2_cycles() {
+    // BEQ +$10 (Z is clear after power on)
+    let (mut cpu, mut memory) = setup(0x8000, &[0xF0, 0x10]);
+
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 2);
+    assert_eq!(cpu.pc(), 0x8002);
+}
+
+#[test]
+fn branch_taken_takes_3_cycles() {
+    // BNE +$10
+    let (mut cpu, mut memory) = setup(0x8000, &[0xD0, 0x10]);
+
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 3);
+    assert_eq!(cpu.pc(), 0x8012);
+}
+
+#[test]
+fn branch_taken_across_page_takes_4_cycles() {
+    // BNE +$10, the next instruction is at 0x80FF
+    let (mut cpu, mut memory) = setup(0x80FD, &[0xD0, 0x10]);
+
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 4);
+    assert_eq!(cpu.pc(), 0x810F);
+}
+
+#[test]
+fn branch_backwards_across_page_takes_4_cycles() {
+    // BNE -$10, the next instruction is at 0x8002
+    let (mut cpu, mut memory) = setup(0x8000, &[0xD0, 0xF0]);
+
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 4);
+    assert_eq!(cpu.pc(), 0x7FF2);
+}
+
+/// BIT takes Zero from A AND operand, but Overflow and Negative directly from bits 6 and 7 of the operand
+#[test]
+fn bit_sets_n_and_v_from_operand() {
+    // LDA #$01; BIT $10
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x01, 0x24, 0x10]);
+    memory.load(0x0010, &[0xC0]);
+    cpu.run_instructions(&mut memory, 2);
+
+    assert_eq!(cpu.a(), 0x01, "BIT must not modify A");
+    assert_eq!(cpu.status() & (ZERO | OVERFLOW | NEGATIVE), ZERO | OVERFLOW | NEGATIVE);
+}
+
+#[test]
+fn bit_clears_n_and_v_from_operand() {
+    // LDA #$FF; BIT $1000
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0xFF, 0x2C, 0x00, 0x10]);
+    memory.load(0x1000, &[0x01]);
+    cpu.run_instructions(&mut memory, 2);
+
+    assert_eq!(cpu.status() & (ZERO | OVERFLOW | NEGATIVE), 0);
+}
+
+#[test]
+fn opcode_profiling_counts_executed_opcodes() {
+    // LDX #$03; loop: DEX; BNE loop
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x03, 0xCA, 0xD0, 0xFD]);
+    cpu.enable_opcode_profiling();
+    cpu.run_instructions(&mut memory, 7);
+
+    let counts = cpu.opcode_counts();
+    assert_eq!(counts[0xA2], 1);
+    assert_eq!(counts[0xCA], 3);
+    assert_eq!(counts[0xD0], 3);
+    assert_eq!(counts.iter().sum::<u64>(), 7);
+}
+
+#[test]
+fn infinite_loop_hook_reports_self_branch() {
+    // LDX #$02; loop: DEX; BNE loop; done: BEQ done
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x02, 0xCA, 0xD0, 0xFD, 0xF0, 0xFE]);
+
+    let hits = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&hits);
+    cpu.set_infinite_loop_hook(3, Some(Box::new(move |pc| sink.borrow_mut().push(pc))));
+
+    // LDX, 2x DEX + BNE, then the self branch 10 times
+    cpu.run_instructions(&mut memory, 15);
+
+    assert_eq!(*hits.borrow(), vec![0x8005]);
+}
+
+#[test]
+fn pre_step_hook_is_called_once_per_instruction() {
+    // LDX #$03; loop: DEX; BNE loop
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x03, 0xCA, 0xD0, 0xFD]);
+
+    let steps = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&steps);
+    cpu.set_pre_step_hook(Some(Box::new(move |pc, opcode| sink.borrow_mut().push((pc, opcode)))));
+    cpu.run_instructions(&mut memory, 5);
+
+    assert_eq!(*steps.borrow(), vec![(0x8000, 0xA2), (0x8002, 0xCA), (0x8003, 0xD0), (0x8002, 0xCA), (0x8003, 0xD0)]);
+}
+
+#[test]
+fn coverage_marks_executed_opcode_addresses() {
+    // LDX #$01; BNE skip; NOP; skip: DEX
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x01, 0xD0, 0x01, 0xEA, 0xCA]);
+    cpu.enable_coverage();
+    cpu.run_instructions(&mut memory, 3);
+
+    let covered: Vec<u16> = (0x8000..0x8008).filter(|&addr| cpu.is_covered(addr)).collect();
+    assert_eq!(covered, vec![0x8000, 0x8002, 0x8005], "operands and the skipped NOP are not covered");
+    assert_eq!(cpu.coverage().iter().map(|word| word.count_ones()).sum::<u32>(), 3);
+}
+
+#[test]
+fn xaa_uses_configurable_magic() {
+    // LDA #$11; LDX #$F3; XAA #$3F
+    let program = [0xA9, 0x11, 0xA2, 0xF3, 0x8B, 0x3F];
+
+    let (mut cpu, mut memory) = setup(0x8000, &program);
+    cpu.run_instructions(&mut memory, 3);
+    // (0x11 OR 0xEE) AND 0xF3 AND 0x3F
+    assert_eq!(cpu.a(), 0x33);
+
+    let (mut cpu, mut memory) = setup(0x8000, &program);
+    cpu.set_xaa_magic(0x00);
+    cpu.run_instructions(&mut memory, 3);
+    assert_eq!(cpu.a(), 0x11);
+    assert_eq!(cpu.status() & (ZERO | NEGATIVE), 0);
+}
+
+#[test]
+fn irq_line_is_level_triggered() {
+    // CLI; loop: NOP; JMP loop
+    let (mut cpu, mut memory) = setup(0x8000, &[0x58, 0xEA, 0x4C, 0x01, 0x80]);
+    // handler: INX; RTI
+    memory.load(0x9000, &[0xE8, 0x40]);
+    memory.load(0xFFFE, &[0x00, 0x90]);
+
+    cpu.run_instructions(&mut memory, 3);
+    assert_eq!(cpu.x(), 0);
+
+    // RTI clears InterruptDisable again, so the handler is entered again and again
+    cpu.set_irq_line(true);
+    cpu.run_instructions(&mut memory, 6);
+    assert_eq!(cpu.x(), 3);
+
+    cpu.set_irq_line(false);
+    cpu.run_instructions(&mut memory, 6);
+    assert_eq!(cpu.x(), 3);
+}
+
+#[test]
+fn nmi_line_is_edge_triggered() {
+    // loop: NOP; JMP loop
+    let (mut cpu, mut memory) = setup(0x8000, &[0xEA, 0x4C, 0x00, 0x80]);
+    // handler: INX; RTI
+    memory.load(0x9000, &[0xE8, 0x40]);
+    memory.load(0xFFFA, &[0x00, 0x90]);
+
+    // a held line only triggers once
+    cpu.set_nmi_line(true);
+    cpu.run_instructions(&mut memory, 6);
+    cpu.set_nmi_line(true);
+    cpu.run_instructions(&mut memory, 6);
+    assert_eq!(cpu.x(), 1);
+
+    // releasing and asserting again is a new edge
+    cpu.set_nmi_line(false);
+    cpu.run_instructions(&mut memory, 2);
+    assert_eq!(cpu.x(), 1);
+    cpu.set_nmi_line(true);
+    cpu.run_instructions(&mut memory, 2);
+    assert_eq!(cpu.x(), 2);
+}
+
+#[test]
+fn loop_sums_into_memory() {
+    // sum 1..=5 into 0x0200
+    let mut asm = Assembler::new(0x8000);
+    asm.ldx_imm(5);
+    asm.lda_imm(0);
+    asm.clc();
+    let lp = asm.label();
+    asm.stx_abs(0x0300);
+    asm.adc_abs(0x0300);
+    asm.dex();
+    asm.bne(lp);
+    asm.sta_abs(0x0200);
+    let (mut cpu, mut memory) = setup(asm.origin(), &asm.assemble());
+
+    cpu.run_instructions(&mut memory, 3 + 5 * 4 + 1);
+
+    assert_eq!(memory.ram()[0x0200], 15);
+    assert_eq!(cpu.x(), 0);
+}
+
+#[test]
+fn forward_branch_skips_code() {
+    let mut asm = Assembler::new(0x8000);
+    let skip = asm.forward_label();
+    asm.sec();
+    asm.bcs(skip);
+    asm.lda_imm(0xAA);
+    asm.bind(skip);
+    asm.ldy_imm(0x55);
+    let (mut cpu, mut memory) = setup(asm.origin(), &asm.assemble());
+
+    cpu.run_instructions(&mut memory, 3);
+
+    assert_eq!(cpu.a(), 0x00, "LDA #$AA has to be skipped");
+    assert_eq!(cpu.y(), 0x55);
+    assert_eq!(cpu.pc(), 0x8007);
+}
+
+#[test]
+fn jsr_and_rts_restore_pc_and_stack() {
+    let mut asm = Assembler::new(0x8000);
+    asm.jsr(0x9000);
+    asm.inx();
+    let (mut cpu, mut memory) = setup(asm.origin(), &asm.assemble());
+
+    let mut sub = Assembler::new(0x9000);
+    sub.lda_imm(0x42);
+    sub.pha();
+    sub.pla();
+    sub.rts();
+    memory.load(sub.origin(), &sub.assemble());
+
+    let sp = cpu.sp();
+    cpu.run_instructions(&mut memory, 6);
+
+    assert_eq!(cpu.a(), 0x42);
+    assert_eq!(cpu.x(), 1);
+    assert_eq!(cpu.sp(), sp);
+    assert_eq!(cpu.pc(), 0x8004);
+}
+
+#[test]
+fn run_until_breakpoint_stops_in_loop() {
+    let mut asm = Assembler::new(0x8000);
+    asm.ldx_imm(0);
+    let lp = asm.label();
+    asm.inx();
+    let check = asm.addr();
+    asm.cpx_imm(3);
+    asm.bne(lp);
+    // JAM
+    asm.bytes(&[0x02]);
+    let (mut cpu, mut memory) = setup(asm.origin(), &asm.assemble());
+
+    cpu.add_breakpoint(check);
+
+    assert_eq!(cpu.run_until_breakpoint(&mut memory), StopReason::Breakpoint(check));
+    assert_eq!(cpu.x(), 1);
+    // resuming from the breakpoint runs one more iteration
+    assert_eq!(cpu.run_until_breakpoint(&mut memory), StopReason::Breakpoint(check));
+    assert_eq!(cpu.x(), 2);
+
+    cpu.remove_breakpoint(check);
+    assert_eq!(cpu.run_until_breakpoint(&mut memory), StopReason::Halted);
+    assert_eq!(cpu.x(), 3);
+}
+
+#[test]
+fn execute_single_instruction_returns_cycles() {
+    // LDA #$00; LDX #$20; LDA $80F0,X
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x00, 0xA2, 0x20, 0xBD, 0xF0, 0x80]);
+
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 2);
+    cpu.execute_single_instruction(&mut memory);
+    // 0x80F0 + 0x20 crosses into page 0x81
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 5);
+}
+
+#[test]
+fn absolute_x_wraps_around_address_space() {
+    // LDX #$20; LDA $FFF0,X
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x20, 0xBD, 0xF0, 0xFF]);
+    memory.load(0x0010, &[0x5A]);
+
+    cpu.execute_single_instruction(&mut memory);
+    // the wrap crosses a page, so the dummy read takes the extra cycle
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 5);
+    assert_eq!(cpu.a(), 0x5A);
+}
+
+#[test]
+fn pc_wraps_around_on_operand_fetch() {
+    // LDA #$42 with the opcode at 0xFFFF and the operand at 0x0000
+    let (mut cpu, mut memory) = setup(0x8000, &[]);
+    memory.load(0xFFFF, &[0xA9, 0x42]);
+    cpu.set_pc(0xFFFF);
+
+    assert_eq!(cpu.execute_single_instruction(&mut memory), 2);
+    assert_eq!(cpu.a(), 0x42);
+    assert_eq!(cpu.pc(), 0x0001);
+}
+
+#[test]
+fn rra_propagates_carry_through_rotate_and_add() {
+    // LDX #$03; STX $10; SEC; LDA #$01; RRA $10
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x03, 0x86, 0x10, 0x38, 0xA9, 0x01, 0x67, 0x10]);
+    cpu.run_instructions(&mut memory, 5);
+
+    // the carry is rotated into bit 7, bit 0 becomes the carry for the ADC: 0x01 + 0x81 + 1
+    assert_eq!(memory.ram()[0x10], 0x81);
+    assert_eq!(cpu.a(), 0x83);
+    assert_eq!(cpu.status() & CARRY, 0);
+}
+
+#[test]
+fn arr_sets_carry_and_overflow_from_result_bits() {
+    // SEC; LDA #$FF; ARR #$80
+    let (mut cpu, mut memory) = setup(0x8000, &[0x38, 0xA9, 0xFF, 0x6B, 0x80]);
+    cpu.run_instructions(&mut memory, 3);
+    // 0xC0: bit 6 set, bit 5 clear
+    assert_eq!(cpu.a(), 0xC0);
+    assert_eq!(cpu.status() & (CARRY | OVERFLOW | NEGATIVE), CARRY | OVERFLOW | NEGATIVE);
+
+    // CLC; LDA #$FF; ARR #$C0
+    let (mut cpu, mut memory) = setup(0x8000, &[0x18, 0xA9, 0xFF, 0x6B, 0xC0]);
+    cpu.run_instructions(&mut memory, 3);
+    // 0x60: bit 6 and bit 5 set
+    assert_eq!(cpu.a(), 0x60);
+    assert_eq!(cpu.status() & (CARRY | OVERFLOW), CARRY);
+
+    // CLC; LDA #$FF; ARR #$40
+    let (mut cpu, mut memory) = setup(0x8000, &[0x18, 0xA9, 0xFF, 0x6B, 0x40]);
+    cpu.run_instructions(&mut memory, 3);
+    // 0x20: bit 6 clear, bit 5 set
+    assert_eq!(cpu.a(), 0x20);
+    assert_eq!(cpu.status() & (CARRY | OVERFLOW), OVERFLOW);
+}
+
+#[test]
+fn sbc_eb_alias_matches_e9() {
+    for &(carry, a, op) in &[(0x38, 0x50, 0x10), (0x18, 0x50, 0x10), (0x38, 0x00, 0x01), (0x38, 0x80, 0x01), (0x18, 0x7F, 0xFF)] {
+        // SEC/CLC; LDA #a; SBC #op
+        let (mut official, mut memory) = setup(0x8000, &[carry, 0xA9, a, 0xE9, op]);
+        official.run_instructions(&mut memory, 3);
+
+        let (mut alias, mut memory) = setup(0x8000, &[carry, 0xA9, a, 0xEB, op]);
+        alias.run_instructions(&mut memory, 3);
+
+        assert_eq!(alias.a(), official.a());
+        assert_eq!(alias.status(), official.status());
+    }
+}
+
+#[test]
+fn unstable_stores_and_with_high_byte_plus_one() {
+    // LDX #$05; LDY #$01; SHX $0210,Y
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x05, 0xA0, 0x01, 0x9E, 0x10, 0x02]);
+    cpu.run_instructions(&mut memory, 3);
+    // 0x05 AND 0x03
+    assert_eq!(memory.ram()[0x0211], 0x01);
+
+    // LDX #$05; LDY #$20; SHX $02F0,Y
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x05, 0xA0, 0x20, 0x9E, 0xF0, 0x02]);
+    cpu.run_instructions(&mut memory, 3);
+    // crossing into page 0x03 replaces the high address byte with the stored value
+    assert_eq!(memory.ram()[0x0310], 0x00);
+    assert_eq!(memory.ram()[0x0110], 0x01);
+
+    // LDY #$05; LDX #$20; SHY $02F0,X
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA0, 0x05, 0xA2, 0x20, 0x9C, 0xF0, 0x02]);
+    cpu.run_instructions(&mut memory, 3);
+    assert_eq!(memory.ram()[0x0310], 0x00);
+    assert_eq!(memory.ram()[0x0110], 0x01);
+
+    // LDA #$07; LDX #$0D; LDY #$20; SHA $02F0,Y
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x07, 0xA2, 0x0D, 0xA0, 0x20, 0x9F, 0xF0, 0x02]);
+    cpu.run_instructions(&mut memory, 4);
+    // 0x07 AND 0x0D AND 0x03
+    assert_eq!(memory.ram()[0x0310], 0x00);
+    assert_eq!(memory.ram()[0x0110], 0x01);
+
+    // LDA #$07; LDX #$0D; LDY #$20; TAS $02F0,Y
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x07, 0xA2, 0x0D, 0xA0, 0x20, 0x9B, 0xF0, 0x02]);
+    cpu.run_instructions(&mut memory, 4);
+    assert_eq!(cpu.sp(), 0x05);
+    assert_eq!(memory.ram()[0x0310], 0x00);
+    assert_eq!(memory.ram()[0x0110], 0x01);
+}
+
+#[test]
+fn run_cycles_returns_overshoot_of_last_instruction() {
+    // loop: NOP; JMP loop
+    let (mut cpu, mut memory) = setup(0x8000, &[0xEA, 0x4C, 0x00, 0x80]);
+    let start = cpu.cycles();
+
+    // one NTSC frame
+    let executed = cpu.run_cycles(&mut memory, 29780);
+
+    // the last instruction may run past the target by less than its own length (JMP takes 3 cycles)
+    assert!((29780..29780 + 3).contains(&executed), "executed {} cycles", executed);
+    assert_eq!(cpu.cycles() - start, executed);
+}
+
+#[test]
+fn disassemble_formats_operands() {
+    let mut memory = FlatMemory::new();
+    // LDA $0200,X; JMP ($1234); ... BNE $8010
+    memory.load(0x8000, &[0xBD, 0x00, 0x02, 0x6C, 0x34, 0x12]);
+    memory.load(0x800E, &[0xD0, 0x00]);
+    memory.load(0x8010, &[0xD0, 0xFC]);
+
+    assert_eq!(disassemble(&memory, 0x8000), (String::from("LDA $0200,X"), 3));
+    assert_eq!(disassemble(&memory, 0x8003), (String::from("JMP ($1234)"), 3));
+    assert_eq!(disassemble(&memory, 0x800E), (String::from("BNE $8010"), 2));
+    // backward branch
+    assert_eq!(disassemble(&memory, 0x8010), (String::from("BNE $800E"), 2));
+}
+
+#[test]
+fn run_instructions_counts_exact_cycles_and_stops_when_halted() {
+    // 10 NOPs; JAM
+    let mut program = [0xEA; 11];
+    program[10] = 0x02;
+    let (mut cpu, mut memory) = setup(0x8000, &program);
+    let start = cpu.cycles();
+
+    assert_eq!(cpu.run_instructions(&mut memory, 10), 20);
+    assert_eq!(cpu.cycles() - start, 20);
+
+    // the JAM halts the CPU, the remaining instructions are not run
+    cpu.run_instructions(&mut memory, 5);
+    assert!(cpu.is_halted());
+    let halted_at = cpu.cycles();
+    assert_eq!(cpu.run_instructions(&mut memory, 5), 0);
+    assert_eq!(cpu.cycles(), halted_at);
+}
+
+#[test]
+fn php_plp_round_trip_keeps_unused_and_drops_break() {
+    // PHP; PLA
+    let (mut cpu, mut memory) = setup(0x8000, &[0x08, 0x68]);
+    cpu.run_instructions(&mut memory, 2);
+    // the pushed copy has B and the unused bit set
+    assert_eq!(cpu.a() & 0x30, 0x30);
+
+    // LDA #$FF; PHA; PLP
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0xFF, 0x48, 0x28]);
+    cpu.run_instructions(&mut memory, 3);
+    assert_eq!(cpu.status(), 0xEF);
+
+    // LDA #$00; PHA; PLP
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x00, 0x48, 0x28]);
+    cpu.run_instructions(&mut memory, 3);
+    assert_eq!(cpu.status(), 0x20);
+}
+
+#[test]
+fn cli_sei_and_plp_affect_irq_one_instruction_late() {
+    // CLI; INY; INY
+    let (mut cpu, mut memory) = setup(0x8000, &[0x58, 0xC8, 0xC8]);
+    // handler: INX; RTI
+    memory.load(0x9000, &[0xE8, 0x40]);
+    memory.load(0xFFFE, &[0x00, 0x90]);
+    cpu.set_irq_line(true);
+    // the instruction after CLI still runs with interrupts disabled
+    cpu.run_instructions(&mut memory, 2);
+    assert_eq!((cpu.x(), cpu.y()), (0, 1));
+    cpu.run_instructions(&mut memory, 1);
+    assert_eq!((cpu.x(), cpu.y()), (1, 1));
+
+    // CLI; NOP; SEI; INY
+    let (mut cpu, mut memory) = setup(0x8000, &[0x58, 0xEA, 0x78, 0xC8]);
+    memory.load(0x9000, &[0xE8, 0x40]);
+    memory.load(0xFFFE, &[0x00, 0x90]);
+    cpu.run_instructions(&mut memory, 3);
+    // the IRQ is still taken right after SEI
+    cpu.set_irq_line(true);
+    cpu.run_instructions(&mut memory, 1);
+    assert_eq!((cpu.x(), cpu.y()), (1, 0));
+
+    // LDA #$00; PHA; PLP; INY
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA9, 0x00, 0x48, 0x28, 0xC8]);
+    memory.load(0x9000, &[0xE8, 0x40]);
+    memory.load(0xFFFE, &[0x00, 0x90]);
+    cpu.set_irq_line(true);
+    // PLP clears InterruptDisable one instruction late as well
+    cpu.run_instructions(&mut memory, 4);
+    assert_eq!((cpu.x(), cpu.y()), (0, 1));
+    cpu.run_instructions(&mut memory, 1);
+    assert_eq!((cpu.x(), cpu.y()), (1, 1));
+}
+
+#[test]
+fn reset_to_overrides_pc_and_takes_7_cycles() {
+    let mut memory = FlatMemory::new();
+    memory.set_reset_vector(0x8000);
+
+    let mut cpu = Cpu::new();
+    cpu.reset_to(&mut memory, 0xC000);
+
+    assert_eq!(cpu.pc(), 0xC000);
+    assert_eq!(cpu.cycles(), 7);
+}
+
+#[test]
+fn pal_reset_uses_pal_clock_divider() {
+    let mut memory = FlatMemory::new();
+    memory.set_reset_vector(0x8000);
+
+    let mut cpu = Cpu::with_region(Region::Pal);
+    cpu.power_on(&mut memory);
+
+    assert_eq!(cpu.master_clock(), 7 * 16);
+    assert_eq!(cpu.cycles(), 7);
+}
+
+#[test]
+fn step_over_runs_subroutine_to_completion() {
+    let mut asm = Assembler::new(0x8000);
+    asm.jsr(0x9000);
+    asm.inx();
+    let (mut cpu, mut memory) = setup(asm.origin(), &asm.assemble());
+
+    let mut sub = Assembler::new(0x9000);
+    sub.lda_imm(0x42);
+    sub.jsr(0x9100);
+    sub.rts();
+    memory.load(sub.origin(), &sub.assemble());
+
+    let mut nested = Assembler::new(0x9100);
+    nested.iny();
+    nested.rts();
+    memory.load(nested.origin(), &nested.assemble());
+
+    let sp = cpu.sp();
+    let cycles = cpu.cycles();
+    assert_eq!(cpu.step_over(&mut memory), StopReason::Stepped);
+    // JSR, LDA, JSR, INY, RTS, RTS
+    assert_eq!(cpu.cycles() - cycles, 6 + 2 + 6 + 2 + 6 + 6);
+    assert_eq!(cpu.pc(), 0x8003);
+    assert_eq!(cpu.sp(), sp);
+    assert_eq!(cpu.a(), 0x42);
+    assert_eq!(cpu.y(), 1);
+
+    // Any other instruction is executed on its own
+    assert_eq!(cpu.step_over(&mut memory), StopReason::Stepped);
+    assert_eq!(cpu.cycles() - cycles, 28 + 2);
+    assert_eq!(cpu.pc(), 0x8004);
+    assert_eq!(cpu.x(), 1);
+}
+
+#[test]
+fn step_over_stops_at_breakpoint_in_subroutine() {
+    let mut asm = Assembler::new(0x8000);
+    asm.jsr(0x9000);
+    let (mut cpu, mut memory) = setup(asm.origin(), &asm.assemble());
+
+    // loop: NOP; JMP loop, never returns
+    let mut sub = Assembler::new(0x9000);
+    sub.nop();
+    sub.jmp(0x9000);
+    memory.load(sub.origin(), &sub.assemble());
+
+    cpu.add_breakpoint(0x9001);
+    assert_eq!(cpu.step_over(&mut memory), StopReason::Breakpoint(0x9001));
+}
+
+#[test]
+fn step_over_handles_stack_pointer_wrap_around() {
+    // LDX #$01; TXS; JSR $9000; INX; RTS
+    let (mut cpu, mut memory) = setup(0x8000, &[0xA2, 0x01, 0x9A, 0x20, 0x00, 0x90, 0xE8, 0x60]);
+    // JMP $8006, reaches the return address while the JSR's return address is still on the stack
+    memory.load(0x9000, &[0x4C, 0x06, 0x80]);
+    cpu.run_instructions(&mut memory, 2);
+
+    // the JSR wraps S to 0xFF, which is deeper than 0x01 although it is numerically larger
+    assert_eq!(cpu.step_over(&mut memory), StopReason::Stepped);
+    assert_eq!(cpu.pc(), 0x8006);
+    assert_eq!(cpu.sp(), 0x01);
+    assert_eq!(cpu.x(), 0x02);
+}
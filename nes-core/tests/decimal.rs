@@ -0,0 +1,75 @@
+//! ADC, SBC and the unofficial RRA and ISC in decimal mode, only run with the `decimal` feature enabled
+#![cfg(feature = "decimal")]
+
+use nes_core::{cpu::Cpu, testutil::FlatMemory};
+
+const CARRY: u8 = 0x01;
+const ZERO: u8 = 0x02;
+
+/// Runs `program` (which starts with SED) at 0x8000 and returns the CPU afterwards
+fn run(program: &[u8], instructions: u64) -> Cpu {
+    let mut memory = FlatMemory::new();
+    memory.load(0x8000, program);
+    memory.set_reset_vector(0x8000);
+
+    let mut cpu = Cpu::new();
+    cpu.power_on(&mut memory);
+    cpu.run_instructions(&mut memory, instructions);
+    cpu
+}
+
+#[test]
+fn adc_carries_into_high_digit() {
+    // SED; CLC; LDA #$09; ADC #$01
+    let cpu = run(&[0xF8, 0x18, 0xA9, 0x09, 0x69, 0x01], 4);
+
+    assert_eq!(cpu.a(), 0x10);
+    assert_eq!(cpu.status() & CARRY, 0);
+}
+
+#[test]
+fn adc_sets_carry_on_decimal_overflow() {
+    // SED; SEC; LDA #$99; ADC #$00
+    let cpu = run(&[0xF8, 0x38, 0xA9, 0x99, 0x69, 0x00], 4);
+
+    assert_eq!(cpu.a(), 0x00);
+    assert_eq!(cpu.status() & CARRY, CARRY);
+    // Z is taken from the binary sum 0x9A
+    assert_eq!(cpu.status() & ZERO, 0);
+}
+
+#[test]
+fn sbc_borrows_from_high_digit() {
+    // SED; SEC; LDA #$10; SBC #$01
+    let cpu = run(&[0xF8, 0x38, 0xA9, 0x10, 0xE9, 0x01], 4);
+
+    assert_eq!(cpu.a(), 0x09);
+    assert_eq!(cpu.status() & CARRY, CARRY);
+}
+
+#[test]
+fn sbc_wraps_below_zero() {
+    // SED; SEC; LDA #$00; SBC #$01
+    let cpu = run(&[0xF8, 0x38, 0xA9, 0x00, 0xE9, 0x01], 4);
+
+    assert_eq!(cpu.a(), 0x99);
+    assert_eq!(cpu.status() & CARRY, 0);
+}
+
+#[test]
+fn isc_subtracts_incremented_operand_in_decimal() {
+    // SED; SEC; LDA #$10; ISC $10 ($10 holds 0x00 and is incremented to 0x01)
+    let cpu = run(&[0xF8, 0x38, 0xA9, 0x10, 0xE7, 0x10], 4);
+
+    assert_eq!(cpu.a(), 0x09);
+    assert_eq!(cpu.status() & CARRY, CARRY);
+}
+
+#[test]
+fn rra_adds_rotated_operand_in_decimal() {
+    // SED; LDX #$02; STX $10; CLC; LDA #$09; RRA $10 ($10 is rotated to 0x01)
+    let cpu = run(&[0xF8, 0xA2, 0x02, 0x86, 0x10, 0x18, 0xA9, 0x09, 0x67, 0x10], 6);
+
+    assert_eq!(cpu.a(), 0x10);
+    assert_eq!(cpu.status() & CARRY, 0);
+}
@@ -0,0 +1,172 @@
+//! Loading INES images from memory
+
+use nes_core::{ines::{self, InesError, InesHeader}, mappers::{self, Mirroring}};
+
+/// Builds an NROM image with 16 KB PRG ROM (filled with the low byte of the offset) and 8 KB CHR ROM
+fn nrom_image() -> Vec<u8> {
+    let mut data = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.extend((0..0x4000).map(|i| i as u8));
+    data.resize(data.len() + 0x2000, 0xCC);
+    data
+}
+
+/// Builds an image for `mapper` with `prg_pages` 16 KB PRG ROM pages and `chr_pages` 8 KB CHR ROM pages
+fn image(mapper: u8, prg_pages: u8, chr_pages: u8) -> Vec<u8> {
+    let mut data = vec![b'N', b'E', b'S', 0x1A, prg_pages, chr_pages, mapper << 4, mapper & 0xF0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.resize(data.len() + prg_pages as usize * 0x4000 + chr_pages as usize * 0x2000, 0);
+    data
+}
+
+#[test]
+fn from_bytes_loads_prg_rom() {
+    let mapper = ines::from_bytes(&nrom_image()).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0x00));
+    assert_eq!(mapper.cpu_peek8(0x8012), Some(0x12));
+    // 16 KB are mirrored into 0xC000-0xFFFF
+    assert_eq!(mapper.cpu_peek8(0xC0AB), Some(0xAB));
+}
+
+#[test]
+fn from_bytes_rejects_truncated_image() {
+    let image = nrom_image();
+
+    assert_eq!(ines::from_bytes(&image[..0x3000]).err(), Some(InesError::UnexpectedEof));
+}
+
+#[test]
+fn from_bytes_rejects_oversized_nrom_prg_rom() {
+    // 48 KB of PRG ROM
+    let mut data = vec![b'N', b'E', b'S', 0x1A, 3, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.resize(data.len() + 3 * 0x4000 + 0x2000, 0);
+
+    assert_eq!(ines::from_bytes(&data).err(), Some(InesError::OversizedPrgRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_3_without_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(3, 2, 0)).err(), Some(InesError::MissingChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_9_without_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(9, 2, 0)).err(), Some(InesError::MissingChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_11_without_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(11, 2, 0)).err(), Some(InesError::MissingChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_66_without_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(66, 2, 0)).err(), Some(InesError::MissingChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_0_with_oversized_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(0, 1, 2)).err(), Some(InesError::OversizedChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_2_with_oversized_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(2, 1, 2)).err(), Some(InesError::OversizedChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_7_with_oversized_chr_rom() {
+    assert_eq!(ines::from_bytes(&image(7, 1, 2)).err(), Some(InesError::OversizedChrRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_0_without_prg_rom() {
+    assert_eq!(ines::from_bytes(&image(0, 0, 1)).err(), Some(InesError::MissingPrgRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_1_without_prg_rom() {
+    assert_eq!(ines::from_bytes(&image(1, 0, 1)).err(), Some(InesError::MissingPrgRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_2_without_prg_rom() {
+    assert_eq!(ines::from_bytes(&image(2, 0, 1)).err(), Some(InesError::MissingPrgRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_4_without_prg_rom() {
+    assert_eq!(ines::from_bytes(&image(4, 0, 1)).err(), Some(InesError::MissingPrgRom));
+}
+
+#[test]
+fn from_bytes_rejects_mapper_9_without_prg_rom() {
+    assert_eq!(ines::from_bytes(&image(9, 0, 1)).err(), Some(InesError::MissingPrgRom));
+}
+
+#[test]
+fn from_bytes_accepts_mmc3_with_a_single_prg_page() {
+    let mapper = ines::from_bytes(&image(4, 1, 0)).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0xE000), Some(0));
+}
+
+#[test]
+fn create_mapper_validates_header_rom_sizes() {
+    // the header alone is enough, no ROM data has to follow
+    let header = |mapper, prg_pages, chr_pages| InesHeader::parse(&image(mapper, prg_pages, chr_pages)[..16]).unwrap();
+
+    assert_eq!(mappers::create_mapper(3, &header(3, 1, 0)).err(), Some(InesError::MissingChrRom));
+    assert_eq!(mappers::create_mapper(3, &header(3, 4, 1)).err(), Some(InesError::OversizedPrgRom));
+    assert_eq!(mappers::create_mapper(7, &header(7, 8, 2)).err(), Some(InesError::OversizedChrRom));
+    assert_eq!(mappers::create_mapper(4, &header(4, 0, 0)).err(), Some(InesError::MissingPrgRom));
+    assert!(mappers::create_mapper(1, &header(1, 16, 0)).is_ok());
+}
+
+#[test]
+fn parse_reads_nestest_header() {
+    let header = InesHeader::parse(&[b'N', b'E', b'S', 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+
+    assert_eq!(header, InesHeader {
+        prg_rom_pages: 1,
+        chr_rom_pages: 1,
+        mapper_id: 0,
+        mirroring: Mirroring::Horizontal,
+        has_battery: false,
+        has_trainer: false,
+        prg_ram_size: 0x2000,
+    });
+}
+
+#[test]
+fn parse_reads_flags_and_split_mapper_id() {
+    // vertical mirroring, battery, trainer, mapper 0x41, 16 KB PRG RAM
+    let header = InesHeader::parse(&[b'N', b'E', b'S', 0x1A, 2, 0, 0x17, 0x40, 2, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+
+    assert_eq!(header.mapper_id, 0x41);
+    assert_eq!(header.mirroring, Mirroring::Vertical);
+    assert!(header.has_battery);
+    assert!(header.has_trainer);
+    assert_eq!(header.prg_ram_size, 0x4000);
+}
+
+#[test]
+fn parse_rejects_invalid_magic() {
+    assert_eq!(InesHeader::parse(&[b'N', b'E', b'Z', 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]), Err(InesError::InvalidMagic));
+}
+
+#[test]
+fn from_bytes_skips_trainer() {
+    let mut data = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.resize(16 + 512, 0xEE);
+    data.extend((0..0x4000).map(|i| i as u8));
+    data.resize(data.len() + 0x2000, 0xCC);
+
+    let header = InesHeader::parse(&data).unwrap();
+    assert_eq!(header.trainer_offset(), Some(16));
+    assert_eq!(header.prg_rom_offset(), 16 + 512);
+
+    let mapper = ines::from_bytes(&data).unwrap();
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0x00));
+    assert_eq!(mapper.cpu_peek8(0x8012), Some(0x12));
+    assert_eq!(mapper.cpu_peek8(0xBFFF), Some(0xFF));
+}
@@ -0,0 +1,374 @@
+//! Address decoding of the mappers
+
+use nes_core::{bus::Bus, mappers::{BankLayout, Mapper, Mapper000, Mapper001, Mapper002, Mapper003, Mapper004, Mapper007, Mapper009, Mapper011, Mapper066, MapperConfig, Mirroring}, memory::Memory};
+
+/// PRG ROM of `banks` 16 KB banks, every byte holds its bank number
+fn prg_rom(banks: u8) -> Vec<u8> {
+    banked_rom(banks, 0x4000)
+}
+
+/// ROM of `banks` banks of `bank_size` bytes, every byte holds its bank number
+fn banked_rom(banks: u8, bank_size: usize) -> Vec<u8> {
+    (0..banks).flat_map(|bank| vec![bank; bank_size]).collect()
+}
+
+/// Loads `val` into the MMC1 register at `addr` with five serial writes, LSB first
+fn mmc1_write(mapper: &mut Mapper001, addr: u16, val: u8) {
+    for bit in 0..5 {
+        mapper.cpu_store8(addr, (val >> bit) & 0x01);
+    }
+}
+
+#[test]
+fn nrom_16k_is_mirrored_into_both_halves() {
+    let mut prg = prg_rom(1);
+    prg[0x0123] = 0xAB;
+    // reset vector at the end of the bank
+    prg[0x3FFC] = 0x00;
+    prg[0x3FFD] = 0xC0;
+
+    let mut mapper = Mapper000::default();
+    mapper.load_prg_rom(&prg).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0x8123), Some(0xAB));
+    assert_eq!(mapper.cpu_peek8(0xC123), Some(0xAB));
+    assert_eq!(mapper.cpu_peek8(0xFFFC), Some(0x00));
+    assert_eq!(mapper.cpu_peek8(0xFFFD), Some(0xC0));
+}
+
+#[test]
+fn nrom_32k_maps_distinct_banks() {
+    let mut prg = prg_rom(2);
+    // reset vector at the end of the second bank
+    prg[0x7FFC] = 0x34;
+    prg[0x7FFD] = 0x12;
+
+    let mut mapper = Mapper000::default();
+    mapper.load_prg_rom(&prg).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0));
+    assert_eq!(mapper.cpu_peek8(0xBFFF), Some(0));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(1));
+    assert_eq!(mapper.cpu_peek8(0xFFFC), Some(0x34));
+    assert_eq!(mapper.cpu_peek8(0xFFFD), Some(0x12));
+}
+
+#[test]
+fn nrom_without_prg_ram_leaves_bus_open() {
+    let config = MapperConfig { prg_ram_size: 0, ..MapperConfig::default() };
+    let mut mapper = Mapper000::new(&config);
+    mapper.load_prg_rom(&prg_rom(1)).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0x5000), None);
+    assert_eq!(mapper.cpu_peek8(0x6000), None);
+
+    // the bus returns the last value on the data bus instead
+    let mut bus = Bus::new(Box::new(mapper));
+    bus.cpu_store8(0x0000, 0x5A);
+    assert_eq!(bus.cpu_load8(0x6000), 0x5A);
+}
+
+#[test]
+fn axrom_switches_prg_bank_and_nametable() {
+    let config = MapperConfig { chr_is_ram: true, ..MapperConfig::default() };
+    let mut mapper = Mapper007::new(&config);
+    mapper.load_prg_rom(&banked_rom(8, 0x8000)).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0));
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+
+    mapper.cpu_store8(0x8000, 0x15);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(5));
+    assert_eq!(mapper.cpu_peek8(0xFFFF), Some(5));
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+
+    mapper.cpu_store8(0xFFFF, 0x03);
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(3));
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+}
+
+#[test]
+fn gxrom_selects_prg_and_chr_bank_with_one_write() {
+    let mut mapper = Mapper066::default();
+    mapper.load_prg_rom(&banked_rom(4, 0x8000)).unwrap();
+    mapper.load_chr_rom(&banked_rom(4, 0x2000)).unwrap();
+
+    mapper.cpu_store8(0x8000, 0x21);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(2));
+    assert_eq!(mapper.cpu_peek8(0xFFFF), Some(2));
+    assert_eq!(mapper.ppu_load8(0x0000), 1);
+    assert_eq!(mapper.ppu_load8(0x1FFF), 1);
+
+    mapper.cpu_store8(0xC000, 0x13);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(1));
+    assert_eq!(mapper.ppu_load8(0x0000), 3);
+}
+
+#[test]
+fn color_dreams_splits_prg_and_chr_bank_bits() {
+    let mut mapper = Mapper011::default();
+    mapper.load_prg_rom(&banked_rom(4, 0x8000)).unwrap();
+    mapper.load_chr_rom(&banked_rom(16, 0x2000)).unwrap();
+
+    mapper.cpu_store8(0x8000, 0xA2);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(2));
+    assert_eq!(mapper.cpu_peek8(0xFFFF), Some(2));
+    assert_eq!(mapper.ppu_load8(0x0000), 10);
+    assert_eq!(mapper.ppu_load8(0x1FFF), 10);
+
+    mapper.cpu_store8(0xC000, 0x31);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(1));
+    assert_eq!(mapper.ppu_load8(0x0000), 3);
+}
+
+#[test]
+fn nrom_bank_layout_reflects_prg_mirroring() {
+    let mut mapper = Mapper000::default();
+    mapper.load_prg_rom(&prg_rom(1)).unwrap();
+
+    let layout = mapper.bank_layout();
+    assert_eq!(layout.prg, [0x0000, 0x2000, 0x0000, 0x2000]);
+    assert_eq!(layout.chr, BankLayout::NROM.chr);
+    assert_eq!(layout.prg_bank(0xC000), 0);
+    assert_eq!(layout.prg_bank(0xE000), 1);
+
+    mapper.load_prg_rom(&prg_rom(2)).unwrap();
+    assert_eq!(mapper.bank_layout(), BankLayout::NROM);
+}
+
+#[test]
+fn nrom_routes_nametables_through_mirroring() {
+    let config = MapperConfig { mirroring: Mirroring::Horizontal, ..MapperConfig::default() };
+    let mut mapper = Mapper000::new(&config);
+    let mut vram = [0; 0x800];
+
+    mapper.nametable_store8(0x2400, 0x5A, &mut vram);
+
+    assert_eq!(mapper.nametable_load8(0x2000, &vram), 0x5A);
+    assert_eq!(mapper.nametable_load8(0x2400, &vram), 0x5A);
+    assert_eq!(mapper.nametable_load8(0x2800, &vram), 0x00);
+    // 0x3000-0x3EFF mirrors 0x2000-0x2EFF
+    assert_eq!(mapper.nametable_load8(0x3400, &vram), 0x5A);
+}
+
+#[test]
+fn mmc1_reset_restores_prg_bank_mode_3() {
+    let mut mapper = Mapper001::default();
+    mapper.load_prg_rom(&prg_rom(4)).unwrap();
+
+    // control = 0: 32 KB PRG mode, bank 0 at 0x8000-0xFFFF
+    for _ in 0..5 {
+        mapper.cpu_store8(0x8000, 0x00);
+    }
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(1));
+
+    // a partial serial write is discarded by the reset
+    mapper.cpu_store8(0x8000, 0x01);
+    mapper.reset();
+
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(3), "the last bank is fixed at 0xC000 after reset");
+
+    // five more writes select PRG bank 2, so the discarded bit does not count towards them
+    for bit in [0, 1, 0, 0, 0] {
+        mapper.cpu_store8(0xE000, bit);
+    }
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(2));
+}
+
+#[test]
+fn mmc2_tile_fetches_switch_chr_banks() {
+    let mut mapper = Mapper009::default();
+    mapper.load_prg_rom(&banked_rom(8, 0x2000)).unwrap();
+    mapper.load_chr_rom(&banked_rom(8, 0x1000)).unwrap();
+
+    mapper.cpu_store8(0xB000, 1);
+    mapper.cpu_store8(0xC000, 2);
+    mapper.cpu_store8(0xD000, 3);
+    mapper.cpu_store8(0xE000, 4);
+
+    // both latches start out as 0xFE
+    assert_eq!(mapper.ppu_load8(0x0000), 2);
+    assert_eq!(mapper.ppu_load8(0x1000), 4);
+
+    // the fetch that trips the latch still uses the old bank
+    assert_eq!(mapper.ppu_load8(0x0FD8), 2);
+    assert_eq!(mapper.ppu_load8(0x0000), 1);
+    assert_eq!(mapper.ppu_load8(0x1000), 4, "the latches are independent");
+
+    // the second window reacts to a whole range of addresses
+    mapper.ppu_load8(0x1FDC);
+    assert_eq!(mapper.ppu_load8(0x1000), 3);
+    mapper.ppu_load8(0x1FEF);
+    assert_eq!(mapper.ppu_load8(0x1000), 4);
+
+    // other addresses of the tiles leave the latch alone
+    mapper.ppu_load8(0x0FE9);
+    assert_eq!(mapper.ppu_load8(0x0000), 1);
+    mapper.ppu_load8(0x0FE8);
+    assert_eq!(mapper.ppu_load8(0x0000), 2);
+}
+
+#[test]
+fn mmc2_fixes_the_last_three_prg_banks() {
+    let mut mapper = Mapper009::default();
+    mapper.load_prg_rom(&banked_rom(16, 0x2000)).unwrap();
+
+    mapper.cpu_store8(0xA000, 5);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(5));
+    assert_eq!(mapper.cpu_peek8(0xA000), Some(13));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(14));
+    assert_eq!(mapper.cpu_peek8(0xE000), Some(15));
+}
+
+#[test]
+fn nrom_chr_ram_is_writable() {
+    let config = MapperConfig { chr_is_ram: true, ..MapperConfig::default() };
+    let mut mapper = Mapper000::new(&config);
+    mapper.load_prg_rom(&prg_rom(1)).unwrap();
+
+    mapper.ppu_store8(0x0000, 0x12);
+    mapper.ppu_store8(0x1FFF, 0x34);
+    assert_eq!(mapper.ppu_load8(0x0000), 0x12);
+    assert_eq!(mapper.ppu_load8(0x1FFF), 0x34);
+}
+
+#[test]
+fn nrom_chr_rom_ignores_writes() {
+    let mut mapper = Mapper000::default();
+    mapper.load_prg_rom(&prg_rom(1)).unwrap();
+    mapper.load_chr_rom(&[0xCC; 0x2000]).unwrap();
+
+    mapper.ppu_store8(0x0000, 0x12);
+    assert_eq!(mapper.ppu_load8(0x0000), 0xCC);
+}
+
+#[test]
+fn nrom_prg_ram_round_trip() {
+    let mut mapper = Mapper000::default();
+    mapper.load_prg_rom(&prg_rom(1)).unwrap();
+
+    mapper.cpu_store8(0x6000, 0x12);
+    mapper.cpu_store8(0x7FFF, 0x34);
+    assert_eq!(mapper.cpu_load8(0x6000), Some(0x12));
+    assert_eq!(mapper.cpu_load8(0x7FFF), Some(0x34));
+
+    // and through the CPU bus
+    let mut bus = Bus::new(Box::new(mapper));
+    bus.cpu_store8(0x6123, 0x56);
+    assert_eq!(bus.cpu_load8(0x6123), 0x56);
+}
+
+#[test]
+fn uxrom_switches_low_bank_and_fixes_last_bank() {
+    let mut mapper = Mapper002::default();
+    mapper.load_prg_rom(&prg_rom(8)).unwrap();
+
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(7));
+
+    mapper.cpu_store8(0x8000, 3);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(3));
+    assert_eq!(mapper.cpu_peek8(0xBFFF), Some(3));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(7));
+
+    mapper.cpu_store8(0xFFFF, 5);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(5));
+    assert_eq!(mapper.cpu_peek8(0xFFFF), Some(7));
+}
+
+#[test]
+fn cnrom_selects_chr_bank() {
+    let mut mapper = Mapper003::default();
+    mapper.load_prg_rom(&prg_rom(2)).unwrap();
+    mapper.load_chr_rom(&banked_rom(4, 0x2000)).unwrap();
+
+    assert_eq!(mapper.ppu_load8(0x0000), 0);
+
+    mapper.cpu_store8(0x8000, 2);
+    assert_eq!(mapper.ppu_load8(0x0000), 2);
+    assert_eq!(mapper.ppu_load8(0x1FFF), 2);
+
+    // only the low two bits select the bank
+    mapper.cpu_store8(0xFFFF, 0xF3);
+    assert_eq!(mapper.ppu_load8(0x1000), 3);
+    // PRG ROM is not banked
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(1));
+}
+
+#[test]
+fn mmc1_loads_registers_after_five_writes() {
+    let mut mapper = Mapper001::default();
+    mapper.load_prg_rom(&prg_rom(8)).unwrap();
+
+    // control: vertical mirroring, 16 KB PRG mode with the last bank fixed
+    mmc1_write(&mut mapper, 0x8000, 0x0E);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+
+    // four writes do not change the PRG bank yet
+    for bit in [1, 0, 1, 0] {
+        mapper.cpu_store8(0xE000, bit);
+    }
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(0));
+    mapper.cpu_store8(0xE000, 0);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(5));
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(7));
+}
+
+#[test]
+fn mmc1_bit_7_resets_shift_register() {
+    let mut mapper = Mapper001::default();
+    mapper.load_prg_rom(&prg_rom(8)).unwrap();
+
+    // control: 32 KB PRG mode
+    mmc1_write(&mut mapper, 0x8000, 0x00);
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(1));
+
+    // the reset discards the partial load and switches back to PRG mode 3
+    mapper.cpu_store8(0xE000, 1);
+    mapper.cpu_store8(0xE000, 1);
+    mapper.cpu_store8(0x8000, 0x80);
+    assert_eq!(mapper.cpu_peek8(0xC000), Some(7));
+
+    mmc1_write(&mut mapper, 0xE000, 2);
+    assert_eq!(mapper.cpu_peek8(0x8000), Some(2));
+}
+
+#[test]
+fn mmc3_irq_counter_reloads_decrements_and_fires_at_zero() {
+    let mut mapper = Mapper004::default();
+    mapper.load_prg_rom(&prg_rom(4)).unwrap();
+
+    // latch = 2, reload, enable
+    mapper.cpu_store8(0xC000, 2);
+    mapper.cpu_store8(0xC001, 0);
+    mapper.cpu_store8(0xE001, 0);
+
+    // reload to 2, then 1, then 0
+    mapper.notify_a12_rising();
+    mapper.notify_a12_rising();
+    assert!(!mapper.irq_pending());
+    mapper.notify_a12_rising();
+    assert!(mapper.irq_pending());
+
+    // disabling acknowledges the IRQ
+    mapper.cpu_store8(0xE000, 0);
+    assert!(!mapper.irq_pending());
+
+    // a counter of 0 is reloaded from the latch, a disabled counter does not fire
+    mapper.notify_a12_rising();
+    mapper.notify_a12_rising();
+    mapper.notify_a12_rising();
+    assert!(!mapper.irq_pending());
+
+    // a reload request takes effect on the next rising edge
+    mapper.cpu_store8(0xE001, 0);
+    mapper.notify_a12_rising();
+    mapper.cpu_store8(0xC001, 0);
+    mapper.notify_a12_rising();
+    assert!(!mapper.irq_pending());
+    mapper.notify_a12_rising();
+    mapper.notify_a12_rising();
+    assert!(mapper.irq_pending());
+}
@@ -0,0 +1,59 @@
+//! Compares the CPU against the golden log of nestest (http://www.qmtpro.com/~nes/misc/nestest.txt)
+//!
+//! Expects `roms/nestest.nes` and `roms/nestest.log` in the workspace root,
+//! the test is skipped if they are not present
+
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc};
+
+use nes_core::{bus::Bus, cpu::{Cpu, CpuTrace}, ines};
+
+fn rom_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../roms")
+}
+
+/// Parses the two digit hex value following `key` in a log line
+fn hex_field(line: &str, key: &str) -> u8 {
+    let start = line.find(key).unwrap_or_else(|| panic!("missing {} in {}", key, line)) + key.len();
+    u8::from_str_radix(&line[start..start + 2], 16).unwrap()
+}
+
+/// Parses the cycle count at the end of a log line
+fn cycle_field(line: &str) -> u64 {
+    let start = line.find("CYC:").unwrap() + 4;
+    line[start..].trim().parse().unwrap()
+}
+
+#[test]
+fn nestest_golden_log() {
+    let (rom, log) = match (fs::read(rom_dir().join("nestest.nes")), fs::read_to_string(rom_dir().join("nestest.log"))) {
+        (Ok(rom), Ok(log)) => (rom, log),
+        _ => {
+            eprintln!("roms/nestest.nes or roms/nestest.log not found, skipping");
+            return;
+        }
+    };
+
+    let mut bus = Bus::new(ines::from_bytes(&rom).unwrap());
+    let mut cpu = Cpu::new();
+
+    let traces = Rc::new(RefCell::new(Vec::<CpuTrace>::new()));
+    let sink = Rc::clone(&traces);
+    cpu.set_trace_hook(Some(Box::new(move |trace| sink.borrow_mut().push(*trace))));
+
+    // automated mode starts at 0xC000 instead of the reset vector
+    cpu.reset_to(&mut bus, 0xC000);
+
+    let lines: Vec<&str> = log.lines().filter(|line| !line.is_empty()).collect();
+    cpu.run_instructions(&mut bus, lines.len() as u64);
+
+    let traces = traces.borrow();
+    assert_eq!(traces.len(), lines.len(), "CPU halted early");
+
+    for (i, (trace, line)) in traces.iter().zip(&lines).enumerate() {
+        let pc = u16::from_str_radix(&line[0..4], 16).unwrap();
+        let expected = (pc, hex_field(line, "A:"), hex_field(line, "X:"), hex_field(line, "Y:"), hex_field(line, "P:"), hex_field(line, "SP:"), cycle_field(line));
+        let actual = (trace.pc, trace.a, trace.x, trace.y, trace.p, trace.s, trace.cycle);
+
+        assert_eq!(actual, expected, "mismatch in line {}\nexpected: {}\nactual:   {}", i + 1, line, trace);
+    }
+}
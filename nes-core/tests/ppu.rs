@@ -0,0 +1,365 @@
+//! Register level tests of the PPU
+
+use nes_core::{mappers::{Mapper, Mapper000}, palette::NES_PALETTE, ppu::{Ppu, OPEN_BUS_DECAY_FRAMES, SCREEN_HEIGHT, SCREEN_WIDTH}};
+
+const PPUCTRL: u8 = 0;
+const PPUMASK: u8 = 1;
+const PPUSTATUS: u8 = 2;
+const OAMADDR: u8 = 3;
+const OAMDATA: u8 = 4;
+const PPUSCROLL: u8 = 5;
+const PPUADDR: u8 = 6;
+const PPUDATA: u8 = 7;
+
+/// Points the VRAM address at `addr` through PPUADDR
+fn set_vram_addr(ppu: &mut Ppu, mapper: &mut Mapper000, addr: u16) {
+    ppu.write_register(PPUADDR, (addr >> 8) as u8, mapper);
+    ppu.write_register(PPUADDR, (addr & 0xFF) as u8, mapper);
+}
+
+/// NROM cartridge with test tiles in CHR ROM, all in color 1:
+/// tile 1 is solid, tile 2 only has its left column set and tile 3 only its top row
+fn pattern_mapper() -> Mapper000 {
+    let mut chr = vec![0; 0x2000];
+    chr[0x10..0x18].fill(0xFF);
+    chr[0x20..0x28].fill(0x80);
+    chr[0x30] = 0xFF;
+
+    let mut mapper = Mapper000::default();
+    mapper.load_chr_rom(&chr).unwrap();
+    mapper
+}
+
+/// Writes `data` to PPU address space starting at `addr`
+fn write_vram(ppu: &mut Ppu, mapper: &mut Mapper000, addr: u16, data: &[u8]) {
+    set_vram_addr(ppu, mapper, addr);
+    for &val in data {
+        ppu.write_register(PPUDATA, val, mapper);
+    }
+}
+
+/// Sets the backdrop to 0x0F, color 1 of background palette 0 to 0x16 and color 1 of sprite palette 0 to 0x2A
+fn set_test_palette(ppu: &mut Ppu, mapper: &mut Mapper000) {
+    write_vram(ppu, mapper, 0x3F00, &[0x0F, 0x16]);
+    write_vram(ppu, mapper, 0x3F11, &[0x2A]);
+}
+
+/// Fills OAM with `sprites` (Y, tile, attributes, X), all other sprites are moved off screen
+fn write_sprites(ppu: &mut Ppu, mapper: &mut Mapper000, sprites: &[[u8; 4]]) {
+    ppu.write_register(OAMADDR, 0, mapper);
+    for _ in 0..0x100 {
+        ppu.write_register(OAMDATA, 0xFF, mapper);
+    }
+
+    ppu.write_register(OAMADDR, 0, mapper);
+    for &val in sprites.iter().flatten() {
+        ppu.write_register(OAMDATA, val, mapper);
+    }
+}
+
+/// Scrolls to the top left corner of the first nametable
+fn reset_scroll(ppu: &mut Ppu, mapper: &mut Mapper000) {
+    ppu.write_register(PPUCTRL, ppu.ctrl() & !0x03, mapper);
+    ppu.write_register(PPUSCROLL, 0, mapper);
+    ppu.write_register(PPUSCROLL, 0, mapper);
+}
+
+/// Steps `ppu` until the visible part of the next frame has been rendered
+fn render_frame(ppu: &mut Ppu, mapper: &mut Mapper000) {
+    let frame = ppu.frame();
+    while ppu.frame() == frame {
+        ppu.step(mapper);
+    }
+    while ppu.scanline() < 240 {
+        ppu.step(mapper);
+    }
+}
+
+/// Steps `ppu` until it is about to process `dot` of `scanline`
+fn step_until(ppu: &mut Ppu, mapper: &mut Mapper000, scanline: u16, dot: u16) {
+    while ppu.scanline() != scanline || ppu.dot() != dot {
+        ppu.step(mapper);
+    }
+}
+
+/// Palette index of the rendered pixel at (`x`, `y`)
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+    ppu.frame_buffer()[y * SCREEN_WIDTH + x]
+}
+
+#[test]
+fn sprite_backdrop_entries_mirror_background_entries() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F10);
+    ppu.write_register(PPUDATA, 0x21, &mut mapper);
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F00);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x21);
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F0C);
+    ppu.write_register(PPUDATA, 0x16, &mut mapper);
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F1C);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x16);
+
+    // the other sprite palette entries are distinct
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F11);
+    ppu.write_register(PPUDATA, 0x30, &mut mapper);
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F01);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x00);
+}
+
+#[test]
+fn palette_reads_apply_grayscale() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F01);
+    ppu.write_register(PPUDATA, 0x27, &mut mapper);
+    ppu.write_register(PPUMASK, 0x01, &mut mapper);
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F01);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x20);
+}
+
+#[test]
+fn ppudata_reads_are_delayed_by_the_read_buffer() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x2000);
+    ppu.write_register(PPUDATA, 0x11, &mut mapper);
+    ppu.write_register(PPUDATA, 0x22, &mut mapper);
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x2000);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x00, "first read returns the stale buffer");
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x11);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x22);
+}
+
+#[test]
+fn palette_reads_fill_the_buffer_from_the_nametable_underneath() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x2F05);
+    ppu.write_register(PPUDATA, 0x5A, &mut mapper);
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F05);
+    ppu.write_register(PPUDATA, 0x0F, &mut mapper);
+
+    set_vram_addr(&mut ppu, &mut mapper, 0x3F05);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x0F, "palette reads are not delayed");
+    set_vram_addr(&mut ppu, &mut mapper, 0x0000);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), 0x5A);
+    assert_eq!(ppu.read_register(PPUDATA, &mut mapper), mapper.ppu_load8(0x0000));
+}
+
+#[test]
+fn write_only_registers_and_status_return_open_bus() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    ppu.write_register(OAMADDR, 0x5F, &mut mapper);
+
+    assert_eq!(ppu.read_register(PPUCTRL, &mut mapper), 0x5F);
+    // vblank, sprite 0 hit and sprite overflow are clear after power on
+    assert_eq!(ppu.read_register(PPUSTATUS, &mut mapper), 0x1F);
+}
+
+#[test]
+fn open_bus_decays_without_refresh() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    ppu.write_register(OAMADDR, 0x5F, &mut mapper);
+    while ppu.frame() < OPEN_BUS_DECAY_FRAMES {
+        ppu.step(&mut mapper);
+    }
+
+    assert_eq!(ppu.read_register(PPUCTRL, &mut mapper), 0x00);
+}
+
+#[test]
+fn background_tile_is_rendered_into_frame_buffer() {
+    let mut ppu = Ppu::new();
+    let mut mapper = pattern_mapper();
+
+    set_test_palette(&mut ppu, &mut mapper);
+    // tile 1 at coarse X 2, coarse Y 1
+    write_vram(&mut ppu, &mut mapper, 0x2022, &[1]);
+    reset_scroll(&mut ppu, &mut mapper);
+    ppu.write_register(PPUMASK, 0x0A, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 16, 8), 0x16);
+    assert_eq!(pixel(&ppu, 23, 15), 0x16);
+    assert_eq!(pixel(&ppu, 15, 8), 0x0F);
+    assert_eq!(pixel(&ppu, 24, 8), 0x0F);
+    assert_eq!(pixel(&ppu, 16, 7), 0x0F);
+    assert_eq!(pixel(&ppu, 16, 16), 0x0F);
+
+    // scrolling right by 8 pixels moves the tile one tile to the left
+    ppu.write_register(PPUSCROLL, 8, &mut mapper);
+    ppu.write_register(PPUSCROLL, 0, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 8, 8), 0x16);
+    assert_eq!(pixel(&ppu, 15, 15), 0x16);
+    assert_eq!(pixel(&ppu, 16, 8), 0x0F);
+}
+
+#[test]
+fn sprites_are_rendered_with_flip_and_priority() {
+    let mut ppu = Ppu::new();
+    let mut mapper = pattern_mapper();
+
+    set_test_palette(&mut ppu, &mut mapper);
+    // solid background tile covering (16, 8) - (23, 15)
+    write_vram(&mut ppu, &mut mapper, 0x2022, &[1]);
+    write_sprites(&mut ppu, &mut mapper, &[
+        // left column
+        [49, 2, 0x00, 100],
+        // left column, flipped horizontally
+        [49, 2, 0x40, 120],
+        // top row, flipped vertically
+        [49, 3, 0x80, 140],
+        // left column behind and in front of the background tile
+        [7, 2, 0x20, 16],
+        [7, 2, 0x00, 18],
+    ]);
+    reset_scroll(&mut ppu, &mut mapper);
+    ppu.write_register(PPUMASK, 0x1E, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    // sprites are drawn one line below their Y coordinate
+    assert_eq!(pixel(&ppu, 100, 49), 0x0F);
+    assert_eq!(pixel(&ppu, 100, 50), 0x2A);
+    assert_eq!(pixel(&ppu, 100, 57), 0x2A);
+    assert_eq!(pixel(&ppu, 100, 58), 0x0F);
+    assert_eq!(pixel(&ppu, 101, 50), 0x0F, "color 0 is transparent");
+
+    assert_eq!(pixel(&ppu, 120, 50), 0x0F);
+    assert_eq!(pixel(&ppu, 127, 50), 0x2A);
+
+    assert_eq!(pixel(&ppu, 140, 50), 0x0F);
+    assert_eq!(pixel(&ppu, 140, 57), 0x2A);
+    assert_eq!(pixel(&ppu, 147, 57), 0x2A);
+
+    assert_eq!(pixel(&ppu, 16, 8), 0x16);
+    assert_eq!(pixel(&ppu, 18, 8), 0x2A);
+}
+
+#[test]
+fn sprite_zero_hit_is_set_at_the_overlapping_dot() {
+    let mut ppu = Ppu::new();
+    let mut mapper = pattern_mapper();
+
+    set_test_palette(&mut ppu, &mut mapper);
+    write_vram(&mut ppu, &mut mapper, 0x2022, &[1]);
+    write_sprites(&mut ppu, &mut mapper, &[[7, 1, 0x00, 16]]);
+    reset_scroll(&mut ppu, &mut mapper);
+    ppu.write_register(PPUMASK, 0x1E, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    // the first overlapping pixel (16, 8) is output at dot 17 of scanline 8
+    step_until(&mut ppu, &mut mapper, 8, 17);
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0x40, 0);
+    ppu.step(&mut mapper);
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0x40, 0x40);
+}
+
+#[test]
+fn sprite_zero_hit_is_not_detected_at_x_255() {
+    let mut ppu = Ppu::new();
+    let mut mapper = pattern_mapper();
+
+    set_test_palette(&mut ppu, &mut mapper);
+    // solid background tile covering (248, 8) - (255, 15), sprite 0 only overlaps it at x=255
+    write_vram(&mut ppu, &mut mapper, 0x203F, &[1]);
+    write_sprites(&mut ppu, &mut mapper, &[[7, 1, 0x00, 255]]);
+    reset_scroll(&mut ppu, &mut mapper);
+    ppu.write_register(PPUMASK, 0x1E, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 255, 8), 0x2A);
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0x40, 0);
+}
+
+#[test]
+fn vblank_requests_nmi_when_enabled() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    ppu.write_register(PPUCTRL, 0x80, &mut mapper);
+    step_until(&mut ppu, &mut mapper, 241, 1);
+    assert!(!ppu.nmi_pending());
+    ppu.step(&mut mapper);
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0x80, 0x80);
+    assert!(ppu.nmi_pending());
+    assert!(!ppu.nmi_pending(), "the request is cleared by reading it");
+}
+
+#[test]
+fn enabling_nmi_during_vblank_requests_nmi() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+
+    step_until(&mut ppu, &mut mapper, 241, 2);
+    assert!(!ppu.nmi_pending());
+
+    ppu.write_register(PPUCTRL, 0x80, &mut mapper);
+    assert!(ppu.nmi_pending());
+
+    // toggling the enable bit creates another rising edge
+    ppu.write_register(PPUCTRL, 0x00, &mut mapper);
+    ppu.write_register(PPUCTRL, 0x80, &mut mapper);
+    assert!(ppu.nmi_pending());
+
+    // reading PPUSTATUS clears vblank, so no NMI can be requested anymore
+    ppu.read_register(PPUSTATUS, &mut mapper);
+    ppu.write_register(PPUCTRL, 0x00, &mut mapper);
+    ppu.write_register(PPUCTRL, 0x80, &mut mapper);
+    assert!(!ppu.nmi_pending());
+}
+
+#[test]
+fn pre_render_line_clears_status_flags() {
+    let mut ppu = Ppu::new();
+    let mut mapper = pattern_mapper();
+
+    set_test_palette(&mut ppu, &mut mapper);
+    write_vram(&mut ppu, &mut mapper, 0x2022, &[1]);
+    // sprite 0 hits the background tile, nine sprites on one line overflow
+    let sprites: Vec<[u8; 4]> = (0..9).map(|i| [7, 1, 0x00, 16 + i * 8]).collect();
+    write_sprites(&mut ppu, &mut mapper, &sprites);
+    reset_scroll(&mut ppu, &mut mapper);
+    ppu.write_register(PPUMASK, 0x1E, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    step_until(&mut ppu, &mut mapper, 261, 1);
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0xE0, 0xE0);
+    ppu.step(&mut mapper);
+    assert_eq!(ppu.peek_register(PPUSTATUS) & 0xE0, 0x00);
+}
+
+#[test]
+fn render_rgb_converts_palette_indices() {
+    let mut ppu = Ppu::new();
+    let mut mapper = Mapper000::default();
+    let mut rgb = vec![0xAA; SCREEN_WIDTH * SCREEN_HEIGHT * 3];
+
+    // rendering is disabled, every pixel shows the backdrop
+    write_vram(&mut ppu, &mut mapper, 0x3F00, &[0x0F]);
+    render_frame(&mut ppu, &mut mapper);
+    ppu.render_rgb(&mut rgb);
+    assert!(rgb.chunks_exact(3).all(|color| color == [0, 0, 0]));
+
+    write_vram(&mut ppu, &mut mapper, 0x3F00, &[0x16]);
+    render_frame(&mut ppu, &mut mapper);
+    ppu.render_rgb(&mut rgb);
+    assert_eq!(rgb[..3], NES_PALETTE[0x16]);
+
+    // grayscale drops the hue
+    ppu.write_register(PPUMASK, 0x01, &mut mapper);
+    ppu.render_rgb(&mut rgb);
+    assert_eq!(rgb[..3], NES_PALETTE[0x10]);
+}
@@ -0,0 +1,69 @@
+//! Tests of the whole console
+
+use nes_core::{bus::RamInit, cpu::Region, mappers::Mapper000, memory::Memory, system::System};
+
+/// CPU RAM contents of `system`
+fn ram(system: &System) -> Vec<u8> {
+    (0x0000..0x0800).map(|addr| system.bus().cpu_peek8(addr)).collect()
+}
+
+#[test]
+fn power_on_fills_ram_with_pattern() {
+    let mut system = System::new(Box::new(Mapper000::default()));
+    system.power_on_with_ram_pattern(RamInit::Filled(0xFF));
+
+    assert_eq!(system.bus().cpu_peek8(0x0000), 0xFF);
+    assert!(ram(&system).iter().all(|&val| val == 0xFF));
+
+    system.power_on();
+    assert!(ram(&system).iter().all(|&val| val == 0x00));
+}
+
+#[test]
+fn seeded_ram_is_deterministic() {
+    let mut a = System::new(Box::new(Mapper000::default()));
+    let mut b = System::new(Box::new(Mapper000::default()));
+    a.power_on_with_ram_pattern(RamInit::Seeded(42));
+    b.power_on_with_ram_pattern(RamInit::Seeded(42));
+
+    assert_eq!(ram(&a), ram(&b));
+    assert!(ram(&a).iter().any(|&val| val != 0));
+
+    b.power_on_with_ram_pattern(RamInit::Seeded(43));
+    assert_ne!(ram(&a), ram(&b));
+}
+
+#[test]
+fn corrupt_state_loads_without_panicking() {
+    let mut system = System::new(Box::new(Mapper000::default()));
+    system.power_on();
+
+    // Keep the header, but fill every field with a large value
+    let mut state = system.save_state();
+    for val in &mut state[6..] {
+        *val = 0x7F;
+    }
+
+    system.load_state(&state).unwrap();
+    system.step_frame();
+}
+
+#[test]
+fn ppu_dots_per_cpu_cycle_follow_region() {
+    for &(region, dots_per_5_cycles) in &[(Region::Ntsc, 15), (Region::Pal, 16)] {
+        let mut system = System::with_region(region, Box::new(Mapper000::default()));
+        system.power_on();
+
+        // Both PPUs start at dot 0 and go through 341 dots per scanline
+        let start = system.cpu().cycles();
+        while system.cpu().cycles() - start < 5000 {
+            system.step();
+        }
+        let cycles = system.cpu().cycles() - start;
+        let ppu = system.bus().ppu();
+        let dots = ppu.scanline() as u64 * 341 + ppu.dot() as u64;
+
+        let expected = (start + cycles) * dots_per_5_cycles / 5 - start * dots_per_5_cycles / 5;
+        assert_eq!(dots, expected, "{:?}", region);
+    }
+}
@@ -1,45 +1,242 @@
-use std::fs;
+use std::{error::Error, fs, path::{Path, PathBuf}, process, thread, time::{Duration, Instant}};
 
-use nes_core::{cpu::Cpu, mappers::{Mapper, Mapper000}};
+use nes_core::{ines, mappers::Mapper, ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, system::System};
 
-fn create_mapper(id: u8) -> Box<dyn Mapper> {
-    match id {
-        0x00 => { Box::new(Mapper000::new()) }
-        _ => { panic!("No mapper with id {}", id) }
+const USAGE: &str = "Usage: nes-frontend [--turbo] [--trace] [--frames N] [--load-state FILE] [--save-state FILE] <rom.nes>";
+
+/// Command line options of the frontend
+struct Options {
+    rom_path: String,
+    /// Run as fast as possible instead of in real time
+    turbo: bool,
+    /// Print every executed instruction
+    trace: bool,
+    /// Run headless for this many frames, then print a hash of the last frame
+    frames: Option<u64>,
+    /// Save state to restore after power on
+    load_state: Option<String>,
+    /// File the machine state is written to when the emulation ends
+    ///
+    /// The real time loop only ends if the CPU halts, so in practice this requires `--frames`
+    save_state: Option<String>,
+}
+
+/// Parses the command line arguments (without the program name)
+/// # Returns
+/// `None` if the arguments are invalid
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<Options> {
+    let mut rom_path = None;
+    let mut turbo = false;
+    let mut trace = false;
+    let mut frames = None;
+    let mut load_state = None;
+    let mut save_state = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--turbo" => turbo = true,
+            "--trace" => trace = true,
+            "--frames" => frames = Some(args.next()?.parse().ok()?),
+            "--load-state" => load_state = Some(args.next()?),
+            "--save-state" => save_state = Some(args.next()?),
+            _ if arg.starts_with("--") => return None,
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => return None,
+        }
+    }
+
+    Some(Options {
+        rom_path: rom_path?,
+        turbo,
+        trace,
+        frames,
+        load_state,
+        save_state,
+    })
+}
+
+fn load_ines(path: &str) -> Result<Box<dyn Mapper>, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    let mut mapper = ines::from_bytes(&data)?;
+
+    if mapper.has_battery() {
+        if let Ok(save) = fs::read(save_path(path)) {
+            mapper.load_ram(&save);
+        }
+    }
+
+    Ok(mapper)
+}
+
+/// Path of the battery save file belonging to the ROM at `rom_path`
+fn save_path(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension("sav")
+}
+
+/// Number of frames between two checks whether the battery backed RAM has to be written (~1 s)
+const SAVE_INTERVAL_FRAMES: u64 = 60;
+
+/// Keeps the `.sav` file next to the ROM in sync with the cartridge's battery backed RAM
+///
+/// The RAM is flushed periodically while the emulation runs, so a save survives the process
+/// being killed (e.g. with Ctrl+C), which skips any cleanup at the end of `main`
+struct SaveFile {
+    path: PathBuf,
+    /// RAM contents the file was last written with (or loaded from)
+    written: Vec<u8>,
+}
+
+impl SaveFile {
+    fn new(rom_path: &str, mapper: &dyn Mapper) -> Self {
+        Self {
+            path: save_path(rom_path),
+            written: mapper.save_ram().map_or_else(Vec::new, <[u8]>::to_vec),
+        }
+    }
+
+    /// Writes the battery backed RAM to the save file if it changed since the last write
+    fn flush(&mut self, mapper: &dyn Mapper) {
+        if let Some(ram) = mapper.save_ram() {
+            if ram == self.written.as_slice() {
+                return;
+            }
+
+            match fs::write(&self.path, ram) {
+                Ok(()) => self.written = ram.to_vec(),
+                Err(e) => eprintln!("Failed to write save file: {}", e),
+            }
+        }
     }
 }
 
-fn load_ines(path: &str) -> Box<dyn Mapper> {
-    let data = fs::read(path).unwrap();
+/// Restores the save state in the file at `path`
+fn load_state(system: &mut System, path: &str) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(path)?;
+    system.load_state(&data)?;
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3) of `data`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
 
-    if data[0] != b'N' || data[1] != b'E' || data[2] != b'S' || data[3] != 0x1A {
-        panic!("Invalid INES Magic");
+/// Runs `frames` frames as fast as possible and prints the CRC-32 of the last frame's RGB picture
+///
+/// Used for automated regression tests, the output only depends on the ROM and the frame count
+fn run_headless(system: &mut System, frames: u64) {
+    for _ in 0..frames {
+        system.step_frame();
     }
 
-    let prg_rom_size = data[4] as usize* 0x4000;
-    let chr_rom_size = data[5] as usize * 0x2000;
+    let mut rgb = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3];
+    system.bus().ppu().render_rgb(&mut rgb);
+    println!("{:08x}", crc32(&rgb));
+}
+
+/// Frame rate of an NTSC console (the PPU renders 89341.5 dots per frame at 5.369318 MHz)
+const NTSC_FRAME_RATE: f64 = 60.0988;
 
-    let mapper_id = ((data[6] & 0xF0) >> 4) | (data[7] & 0xF0);
+/// Paces the emulation to real time by sleeping until a frame's deadline has passed
+struct FramePacer {
+    frame_time: Duration,
+    next_frame: Instant,
+    /// Disables the pacing, the emulation runs as fast as possible
+    turbo: bool,
+}
+
+impl FramePacer {
+    fn new(frame_rate: f64) -> Self {
+        Self {
+            frame_time: Duration::from_secs_f64(1.0 / frame_rate),
+            next_frame: Instant::now(),
+            turbo: false,
+        }
+    }
+
+    fn set_turbo(&mut self, turbo: bool) {
+        self.turbo = turbo;
+    }
 
-    let mut mapper = create_mapper(mapper_id);
+    /// Waits until the current frame's time is used up
+    fn wait_frame(&mut self) {
+        let now = Instant::now();
 
-    mapper.load_prg_rom(&data[16..16+prg_rom_size]);
-    mapper.load_chr_rom(&data[16+prg_rom_size..16+prg_rom_size+chr_rom_size]);
+        if self.turbo {
+            self.next_frame = now;
+            return;
+        }
 
-    mapper
+        self.next_frame += self.frame_time;
+        if self.next_frame > now {
+            thread::sleep(self.next_frame - now);
+        } else if now - self.next_frame > self.frame_time {
+            // more than a frame behind (e.g. the process was suspended), don't try to catch up
+            self.next_frame = now;
+        }
+    }
 }
 
 fn main() {
-    let mut cpu = Cpu::new();
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|| {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+    let rom_path = options.rom_path.as_str();
+
+    let mapper = load_ines(rom_path).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {}", rom_path, e);
+        process::exit(1);
+    });
+
+    if options.save_state.is_some() && options.frames.is_none() {
+        eprintln!("Note: --save-state is only written once the emulation ends, use it together with --frames");
+    }
+
+    let mut save_file = SaveFile::new(rom_path, mapper.as_ref());
+    let mut system = System::new(mapper);
+    if options.trace {
+        system.cpu_mut().set_trace_hook(Some(Box::new(|trace| println!("{}", trace))));
+    }
+
+    system.power_on();
 
-    let mut mapper = load_ines("roms/nestest.nes");
+    if let Some(path) = &options.load_state {
+        if let Err(e) = load_state(&mut system, path) {
+            eprintln!("Failed to load state {}: {}", path, e);
+            process::exit(1);
+        }
+    }
 
-    mapper.overwrite_prg_rom(0xFFFC, 0x00);
-    mapper.overwrite_prg_rom(0xFFFD, 0xC0);
+    if let Some(frames) = options.frames {
+        run_headless(&mut system, frames);
+    } else {
+        let mut pacer = FramePacer::new(NTSC_FRAME_RATE);
+        pacer.set_turbo(options.turbo);
+
+        while !system.cpu().is_halted() {
+            system.step_frame();
+            if system.bus().ppu().frame().is_multiple_of(SAVE_INTERVAL_FRAMES) {
+                save_file.flush(system.bus().mapper());
+            }
+            pacer.wait_frame();
+        }
+    }
 
-    cpu.reset(mapper.as_mut());
+    save_file.flush(system.bus().mapper());
 
-    for _ in 0..9000 {
-        cpu.execute_single_instruction(mapper.as_mut());
+    if let Some(path) = &options.save_state {
+        if let Err(e) = fs::write(path, system.save_state()) {
+            eprintln!("Failed to write state {}: {}", path, e);
+            process::exit(1);
+        }
     }
 }